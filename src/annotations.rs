@@ -0,0 +1,314 @@
+//! Annotations the viewers of `todo serve` share: a status, triaged or won't
+//! fix, and notes on a TODO. They are stored by TODO ID in
+//! `.git/todo/annotations.sqlite`, beside the scan history, so they survive
+//! rescans and restarts, and stay with a TODO as long as its ID does.
+
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::api::Scan;
+use crate::dates;
+use crate::server::{Request, Response};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS statuses (
+        todo TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        author TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS notes (
+        id INTEGER PRIMARY KEY,
+        todo TEXT NOT NULL,
+        author TEXT NOT NULL,
+        text TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS notes_by_todo ON notes (todo);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Triaged,
+    WontFix,
+}
+
+impl Status {
+    /// The status as the API writes it; `open` means no status.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Triaged => "triaged",
+            Self::WontFix => "wontfix",
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "triaged" => Ok(Self::Triaged),
+            "wontfix" => Ok(Self::WontFix),
+            _ => Err(format!(
+                "unknown status '{s}', expected triaged, wontfix or open"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Note {
+    pub id: i64,
+    pub author: String,
+    pub text: String,
+    pub created_at: String,
+}
+
+/// Everything said about one TODO.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Annotation {
+    pub status: Option<Status>,
+    /// Who set the status, and when.
+    pub status_by: Option<String>,
+    pub status_at: Option<String>,
+    /// Oldest first.
+    pub notes: Vec<Note>,
+}
+
+fn timestamp(seconds: i64) -> String {
+    dates::rfc3339(DateTime::from_timestamp(seconds, 0).unwrap_or_default())
+}
+
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Opens the database, creating it if needed.
+    pub fn open(repo: &Repository) -> rusqlite::Result<Self> {
+        let dir = repo.path().join("todo");
+        // a missing directory surfaces as SQLite failing to open the file
+        let _ = fs::create_dir_all(&dir);
+        Self::init(Connection::open(dir.join("annotations.sqlite"))?)
+    }
+
+    fn init(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// The annotations of every annotated TODO, by ID.
+    pub fn all(&self) -> rusqlite::Result<HashMap<String, Annotation>> {
+        let mut annotations: HashMap<String, Annotation> = HashMap::new();
+        let mut statuses = self
+            .connection
+            .prepare("SELECT todo, status, author, updated_at FROM statuses")?;
+        let rows = statuses.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (todo, status, author, updated_at) = row?;
+            let annotation = annotations.entry(todo).or_default();
+            annotation.status = status.parse().ok();
+            annotation.status_by = Some(author);
+            annotation.status_at = Some(timestamp(updated_at));
+        }
+
+        let mut notes = self
+            .connection
+            .prepare("SELECT todo, id, author, text, created_at FROM notes ORDER BY id")?;
+        let rows = notes.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                Note {
+                    id: row.get(1)?,
+                    author: row.get(2)?,
+                    text: row.get(3)?,
+                    created_at: timestamp(row.get(4)?),
+                },
+            ))
+        })?;
+        for row in rows {
+            let (todo, note) = row?;
+            annotations.entry(todo).or_default().notes.push(note);
+        }
+        Ok(annotations)
+    }
+
+    /// The annotation of one TODO, empty if it has none.
+    pub fn get(&self, todo: &str) -> rusqlite::Result<Annotation> {
+        Ok(self.all()?.remove(todo).unwrap_or_default())
+    }
+
+    /// Sets the TODO's status, or clears it with `None`.
+    pub fn set_status(
+        &self,
+        todo: &str,
+        status: Option<Status>,
+        author: &str,
+        at: DateTime<Utc>,
+    ) -> rusqlite::Result<()> {
+        match status {
+            Some(status) => self.connection.execute(
+                "INSERT INTO statuses (todo, status, author, updated_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (todo) DO UPDATE SET
+                     status = excluded.status,
+                     author = excluded.author,
+                     updated_at = excluded.updated_at",
+                params![todo, status.as_str(), author, at.timestamp()],
+            )?,
+            None => self
+                .connection
+                .execute("DELETE FROM statuses WHERE todo = ?1", [todo])?,
+        };
+        Ok(())
+    }
+
+    pub fn add_note(
+        &self,
+        todo: &str,
+        author: &str,
+        text: &str,
+        at: DateTime<Utc>,
+    ) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT INTO notes (todo, author, text, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![todo, author, text, at.timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes a note, returning whether there was one with the ID.
+    pub fn delete_note(&self, id: i64) -> rusqlite::Result<bool> {
+        let todo: Option<String> = self
+            .connection
+            .query_row(
+                "DELETE FROM notes WHERE id = ?1 RETURNING todo",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(todo.is_some())
+    }
+}
+
+fn internal(e: &rusqlite::Error) -> Response {
+    Response::error(500, format!("annotations: {e}"))
+}
+
+/// `GET /api/annotations`: every annotation by TODO ID, or with `todo=ID`
+/// that TODO's.
+pub fn list(store: &Store, request: &Request) -> Response {
+    let annotations = request.param("todo").map_or_else(
+        || store.all().map(|all| json!(all)),
+        |todo| store.get(todo).map(|annotation| json!(annotation)),
+    );
+    annotations.map_or_else(|e| internal(&e), |json| Response::json(&json))
+}
+
+/// `POST /api/annotations` with `{"todo": ID, "author": NAME}` and a
+/// `"status"` (`triaged`, `wontfix`, or `open` to clear it), a `"note"`, or
+/// both, answered with the TODO's annotation.
+pub fn annotate(store: &Store, scan: &Scan, request: &Request) -> Response {
+    let body = request.json();
+    let text = |key: &str| {
+        body[key]
+            .as_str()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let Some(todo) = text("todo") else {
+        return Response::error(400, "'todo' is required");
+    };
+    let Some(author) = text("author") else {
+        return Response::error(400, "'author' is required");
+    };
+    let status = match text("status") {
+        None => None,
+        Some("open") => Some(None),
+        Some(status) => match status.parse() {
+            Ok(status) => Some(Some(status)),
+            Err(e) => return Response::error(400, e),
+        },
+    };
+    let note = text("note");
+    if status.is_none() && note.is_none() {
+        return Response::error(400, "expected a 'status', a 'note' or both");
+    }
+    if !scan.todos.iter().any(|scanned| scanned.id == todo) {
+        return Response::error(404, format!("no TODO has the ID '{todo}'"));
+    }
+
+    let now = Utc::now();
+    let updated = status
+        .map_or(Ok(()), |status| store.set_status(todo, status, author, now))
+        .and_then(|()| note.map_or(Ok(()), |note| store.add_note(todo, author, note, now)))
+        .and_then(|()| store.get(todo));
+    updated.map_or_else(
+        |e| internal(&e),
+        |annotation| Response::json(&json!(annotation)),
+    )
+}
+
+/// `DELETE /api/annotations?note=ID`.
+pub fn delete(store: &Store, request: &Request) -> Response {
+    let Some(id) = request.param("note").and_then(|id| id.parse().ok()) else {
+        return Response::error(400, "'note' must be the ID of a note");
+    };
+    match store.delete_note(id) {
+        Ok(true) => Response::json(&json!({ "deleted": id })),
+        Ok(false) => Response::error(404, format!("no note has the ID {id}")),
+        Err(e) => internal(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_statuses_and_notes_by_todo() {
+        let store = Store::init(Connection::open_in_memory().unwrap()).unwrap();
+        let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        store
+            .set_status("3f9a1c2e", Some(Status::Triaged), "alice", at)
+            .unwrap();
+        store
+            .set_status("3f9a1c2e", Some(Status::WontFix), "bob", at)
+            .unwrap();
+        store
+            .add_note("3f9a1c2e", "bob", "upstream's bug", at)
+            .unwrap();
+        store
+            .add_note("77aa0b1c", "alice", "next sprint", at)
+            .unwrap();
+
+        let annotation = store.get("3f9a1c2e").unwrap();
+        assert_eq!(annotation.status, Some(Status::WontFix));
+        assert_eq!(annotation.status_by.as_deref(), Some("bob"));
+        assert_eq!(annotation.notes.len(), 1);
+        assert_eq!(annotation.notes[0].text, "upstream's bug");
+
+        store.set_status("3f9a1c2e", None, "bob", at).unwrap();
+        assert_eq!(store.get("3f9a1c2e").unwrap().status, None);
+        let note = store.get("77aa0b1c").unwrap().notes[0].id;
+        assert!(store.delete_note(note).unwrap());
+        assert!(!store.delete_note(note).unwrap());
+        assert_eq!(store.get("77aa0b1c").unwrap(), Annotation::default());
+        assert_eq!(json!(Status::WontFix), json!("wontfix"));
+    }
+}
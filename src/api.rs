@@ -0,0 +1,336 @@
+//! The JSON API of `todo serve`, for internal tools building on the latest
+//! scan: `/api/todos` lists TODOs with filtering, sorting and cursor
+//! pagination, `/api/stats` counts them and `/api/refresh` rescans right
+//! away. `/api/annotations` holds what viewers say about TODOs, through
+//! [`annotations`]. `/api/openapi.json` describes it all.
+//!
+//! A cursor holds the sort key and ID of the last TODO of a page rather than
+//! an offset, so paging on after a rescan neither repeats nor skips the TODOs
+//! that stayed.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use git2::Repository;
+use serde_json::json;
+
+use crate::annotations::{Annotation, Status};
+use crate::export::record;
+use crate::server::{Request, Response};
+use crate::severity::Severity;
+use crate::stats::{Stats, Summary};
+use crate::{dates, repo_relative_path, Todo};
+
+pub const OPENAPI: &str = include_str!("openapi.json");
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// The scan the API answers from.
+pub struct Scan {
+    pub todos: Vec<Todo>,
+    pub scanned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Path,
+    Date,
+    Severity,
+    Author,
+    Due,
+    Priority,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Self::Path),
+            "date" => Ok(Self::Date),
+            "severity" => Ok(Self::Severity),
+            "author" => Ok(Self::Author),
+            "due" => Ok(Self::Due),
+            "priority" => Ok(Self::Priority),
+            _ => Err(format!(
+                "unknown sort '{s}', expected path, date, severity, author, due or \
+                 priority, with - in front to reverse it"
+            )),
+        }
+    }
+}
+
+/// What a TODO sorts by, as text that sorts the same way.
+fn sort_key(repo: &Repository, todo: &Todo, by: SortBy) -> String {
+    match by {
+        SortBy::Path => format!(
+            "{}\0{:010}",
+            repo_relative_path(repo, &todo.file_path),
+            todo.line
+        ),
+        SortBy::Date => todo
+            .introduced_date
+            .unwrap_or(todo.author_date)
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        SortBy::Severity => (todo.severity as u8).to_string(),
+        SortBy::Author => todo.author.to_lowercase(),
+        // TODOs without a due date or priority come last
+        SortBy::Due => todo
+            .fields
+            .due
+            .map_or_else(|| "~".to_string(), |due| due.to_string()),
+        SortBy::Priority => todo
+            .fields
+            .priority
+            .map_or_else(|| "~".to_string(), |priority| format!("{priority:010}")),
+    }
+}
+
+/// `/api/todos` filters, each of which a TODO must pass.
+#[derive(Debug, Default)]
+struct Filter {
+    tag: Option<String>,
+    author: Option<String>,
+    /// The start of the path relative to the repository root.
+    path: Option<String>,
+    /// The lowest severity.
+    severity: Option<Severity>,
+    language: Option<String>,
+    test: Option<bool>,
+    issue: Option<String>,
+    /// The least urgent priority, as a number.
+    priority: Option<u32>,
+    /// Lower-cased text to find in the TODO's text.
+    text: Option<String>,
+    /// The annotated status, or `open` for TODOs without one.
+    status: Option<String>,
+}
+
+impl Filter {
+    fn parse(request: &Request) -> Result<Self, String> {
+        let param = |name| request.param(name).map(ToString::to_string);
+        Ok(Self {
+            tag: param("tag"),
+            author: param("author"),
+            path: param("path"),
+            severity: request.param("severity").map(str::parse).transpose()?,
+            language: param("language"),
+            test: match request.param("test") {
+                None => None,
+                Some("true") => Some(true),
+                Some("false") => Some(false),
+                Some(test) => return Err(format!("invalid test '{test}', expected true or false")),
+            },
+            text: request.param("q").map(str::to_lowercase),
+            issue: param("issue"),
+            priority: request
+                .param("priority")
+                .map(|priority| {
+                    priority
+                        .parse()
+                        .map_err(|_| format!("invalid priority '{priority}', expected a number"))
+                })
+                .transpose()?,
+            status: match request.param("status") {
+                Some(status) if status != "open" => {
+                    Some(status.parse::<Status>()?.as_str().to_string())
+                }
+                status => status.map(ToString::to_string),
+            },
+        })
+    }
+
+    fn matches(&self, repo: &Repository, todo: &Todo, status: Option<Status>) -> bool {
+        self.tag.as_ref().is_none_or(|tag| todo.tags.contains(tag))
+            && self
+                .author
+                .as_ref()
+                .is_none_or(|author| todo.is_owned_by(author))
+            && self.path.as_ref().is_none_or(|path| {
+                repo_relative_path(repo, &todo.file_path).starts_with(path.as_str())
+            })
+            && self
+                .severity
+                .is_none_or(|severity| todo.severity >= severity)
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|language| language.eq_ignore_ascii_case(&todo.language))
+            && self.test.is_none_or(|test| todo.test == test)
+            && self
+                .issue
+                .as_ref()
+                .is_none_or(|issue| todo.fields.issue.as_ref() == Some(issue))
+            && self
+                .priority
+                .is_none_or(|priority| todo.fields.priority.is_some_and(|own| own <= priority))
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| todo.normalized.to_lowercase().contains(text.as_str()))
+            && self
+                .status
+                .as_ref()
+                .is_none_or(|wanted| status.map_or("open", Status::as_str) == wanted)
+    }
+}
+
+/// The cursor of the page after the TODO with `key` and `id`, as hex.
+fn encode_cursor(sort: &str, key: &str, id: &str) -> String {
+    format!("{sort}\0{key}\0{id}")
+        .bytes()
+        .fold(String::new(), |mut cursor, byte| {
+            let _ = write!(cursor, "{byte:02x}");
+            cursor
+        })
+}
+
+/// The sort, key and ID a cursor holds.
+fn decode_cursor(cursor: &str) -> Option<(String, String, String)> {
+    if !cursor.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(cursor.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let text = String::from_utf8(bytes).ok()?;
+    let (sort, rest) = text.split_once('\0')?;
+    let (key, id) = rest.rsplit_once('\0')?;
+    Some((sort.to_string(), key.to_string(), id.to_string()))
+}
+
+/// A page of the TODOs matching the request's filters, each with its
+/// annotation.
+fn page(
+    repo: &Repository,
+    scan: &Scan,
+    annotations: &HashMap<String, Annotation>,
+    request: &Request,
+) -> Result<serde_json::Value, String> {
+    let filter = Filter::parse(request)?;
+    let sort = request.param("sort").unwrap_or("path");
+    let descending = sort.starts_with('-');
+    let by: SortBy = sort.trim_start_matches('-').parse()?;
+    let limit = match request.param("limit") {
+        None => DEFAULT_LIMIT,
+        Some(limit) => limit
+            .parse()
+            .ok()
+            .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+            .ok_or_else(|| format!("invalid limit '{limit}', expected 1 to {MAX_LIMIT}"))?,
+    };
+    let after = request
+        .param("cursor")
+        .map(|cursor| {
+            decode_cursor(cursor)
+                .filter(|(cursor_sort, _, _)| cursor_sort == sort)
+                .ok_or_else(|| format!("invalid cursor '{cursor}' for sort '{sort}'"))
+        })
+        .transpose()?;
+
+    let mut matched: Vec<(String, &Todo)> = scan
+        .todos
+        .iter()
+        .filter(|todo| {
+            let status = annotations
+                .get(&todo.id)
+                .and_then(|annotation| annotation.status);
+            filter.matches(repo, todo, status)
+        })
+        .map(|todo| (sort_key(repo, todo, by), todo))
+        .collect();
+    matched.sort_by(|(a_key, a), (b_key, b)| (a_key, &a.id).cmp(&(b_key, &b.id)));
+    if descending {
+        matched.reverse();
+    }
+
+    let total = matched.len();
+    let start = after.map_or(0, |(_, key, id)| {
+        matched
+            .iter()
+            .position(|(todo_key, todo)| {
+                let order = (todo_key.as_str(), todo.id.as_str()).cmp(&(&key, &id));
+                if descending {
+                    order.is_lt()
+                } else {
+                    order.is_gt()
+                }
+            })
+            .unwrap_or(total)
+    });
+    let page = &matched[start..total.min(start + limit)];
+    let next_cursor = page
+        .last()
+        .filter(|_| start + page.len() < total)
+        .map(|(key, todo)| encode_cursor(sort, key, &todo.id));
+
+    Ok(json!({
+        "todos": page
+            .iter()
+            .map(|(_, todo)| {
+                let mut record = record(repo, todo);
+                record["annotation"] = json!(annotations.get(&todo.id));
+                record
+            })
+            .collect::<Vec<_>>(),
+        "total": total,
+        "next_cursor": next_cursor,
+        "scanned_at": dates::rfc3339(scan.scanned_at),
+    }))
+}
+
+/// `GET /api/todos`.
+pub fn todos(
+    repo: &Repository,
+    scan: &Scan,
+    annotations: &HashMap<String, Annotation>,
+    request: &Request,
+) -> Response {
+    match page(repo, scan, annotations, request) {
+        Ok(page) => Response::json(&page),
+        Err(e) => Response::error(400, e),
+    }
+}
+
+/// `GET /api/stats`: the report summary's counts, plus TODOs by author.
+pub fn stats(scan: &Scan) -> Response {
+    let mut stats = Summary::compute(&scan.todos).json();
+    stats["authors"] = json!(Stats::compute(&scan.todos).by_author);
+    stats["scanned_at"] = json!(dates::rfc3339(scan.scanned_at));
+    Response::json(&stats)
+}
+
+/// `POST /api/refresh`, answered after the rescan.
+pub fn refreshed(scan: &Scan) -> Response {
+    Response::json(&json!({
+        "total": scan.todos.len(),
+        "scanned_at": dates::rfc3339(scan.scanned_at),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip() {
+        let cursor = encode_cursor("-path", "src/a.rs\u{0}0000000012", "3f9a1c2e");
+        assert!(cursor.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(
+            decode_cursor(&cursor),
+            Some((
+                "-path".to_string(),
+                "src/a.rs\u{0}0000000012".to_string(),
+                "3f9a1c2e".to_string()
+            ))
+        );
+        assert_eq!(decode_cursor("zz"), None);
+        assert_eq!(decode_cursor("616"), None);
+        assert_eq!("due".parse(), Ok(SortBy::Due));
+        assert!("size".parse::<SortBy>().is_err());
+    }
+}
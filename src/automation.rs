@@ -0,0 +1,74 @@
+//! Keeps bots from owning TODOs: commits by authors matching `--ignore-author`
+//! patterns are credited to a shared "automation" author instead.
+
+use regex::RegexSet;
+
+use crate::Todo;
+
+pub const AUTOMATION: &str = "automation";
+
+pub struct Automation {
+    patterns: RegexSet,
+}
+
+/// Translates a pattern where `*` matches any run of characters into an
+/// anchored, case-insensitive regex.
+pub fn wildcard_regex(pattern: &str) -> String {
+    let escaped: Vec<_> = pattern.split('*').map(regex::escape).collect();
+    format!("(?i)^{}$", escaped.join(".*"))
+}
+
+impl Automation {
+    pub fn new(patterns: &[String]) -> Result<Self, regex::Error> {
+        let patterns = RegexSet::new(patterns.iter().map(|pattern| wildcard_regex(pattern)))?;
+        Ok(Self { patterns })
+    }
+
+    fn is_bot(&self, name: &str) -> bool {
+        self.patterns.is_match(name)
+    }
+
+    pub fn reattribute(&self, todos: &mut [Todo]) {
+        for todo in todos {
+            if self.is_bot(&todo.author) || self.is_bot(&todo.author_email) {
+                todo.author = AUTOMATION.to_string();
+                todo.author_email.clear();
+            }
+            todo.co_authors.retain(|co_author| !self.is_bot(co_author));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn credits_bot_commits_to_automation() {
+        let test = TestRepo::new("automation");
+        test.write("a.rs", "// TODO: bump\n");
+        test.commit("dependabot[bot]", "a");
+        test.write("b.rs", "// TODO: pair\n");
+        test.commit(
+            "Alice",
+            "b\n\nCo-authored-by: Renovate Bot <bot@renovateapp.com>\n",
+        );
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (mut todos, _) = crate::get_todos(&test.repo, &options);
+        todos.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        let patterns = ["*[bot]".to_string(), "renovate*".to_string()];
+        Automation::new(&patterns).unwrap().reattribute(&mut todos);
+
+        assert_eq!(todos[0].author, AUTOMATION);
+        assert!(todos[0].author_email.is_empty());
+        assert_eq!(todos[1].author, "Alice");
+        assert!(todos[1].co_authors.is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+//! The ref the current branch is compared against: `--base`, else the
+//! repository's `todo.base` git config, else its default branch as `origin`
+//! reports it, else the first of the usual default branch names that exists.
+
+use git2::{BranchType, Commit, Repository};
+
+use crate::cli::Options;
+
+/// Branch names tried, in order, when nothing names the base.
+const DEFAULT_BRANCHES: [&str; 4] = ["main", "master", "trunk", "develop"];
+
+/// The branch `origin/HEAD` points at, as a local branch if one of that name
+/// exists and as the remote-tracking branch otherwise.
+fn remote_default(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("refs/remotes/origin/HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    let name = target.strip_prefix("refs/remotes/origin/")?;
+    if repo.find_branch(name, BranchType::Local).is_ok() {
+        Some(name.to_string())
+    } else {
+        Some(format!("origin/{name}"))
+    }
+}
+
+/// The name of the base ref, which may be any revision git understands, such
+/// as `master`, `origin/main` or `v2.1`.
+pub fn name(repo: &Repository, options: &Options) -> String {
+    if let Some(base) = &options.base {
+        return base.clone();
+    }
+    if let Ok(base) = repo
+        .config()
+        .and_then(|config| config.get_string("todo.base"))
+    {
+        return base;
+    }
+    remote_default(repo)
+        .or_else(|| {
+            DEFAULT_BRANCHES
+                .into_iter()
+                .find(|name| repo.find_branch(name, BranchType::Local).is_ok())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| DEFAULT_BRANCHES[0].to_string())
+}
+
+/// The commit the base ref points at.
+pub fn commit<'repo>(
+    repo: &'repo Repository,
+    options: &Options,
+) -> Result<Commit<'repo>, git2::Error> {
+    let name = name(repo, options);
+    repo.revparse_single(&name)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| {
+            git2::Error::from_str(&format!(
+                "base '{name}' not found ({}); pick one with --base",
+                e.message()
+            ))
+        })
+}
+
+/// Where the current branch forked from the base. Diffing against it rather
+/// than the base's tip, like `git diff base...HEAD`, keeps changes made on the
+/// base since then from being attributed to the branch.
+pub fn merge_base<'repo>(
+    repo: &'repo Repository,
+    options: &Options,
+) -> Result<Commit<'repo>, git2::Error> {
+    let base = commit(repo, options)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let merge_base = repo.merge_base(base.id(), head.id())?;
+    repo.find_commit(merge_base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn compares_with_where_the_branch_forked() {
+        let test = TestRepo::new("merge-base");
+        test.write("a.rs", "// TODO: old\n");
+        let fork = test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("b.rs", "// TODO: branch\n");
+        test.commit("Bob", "b");
+        // the base moves on after the branch forked
+        test.checkout("main");
+        test.write("a.rs", "fn a() {}\n");
+        test.write("c.rs", "// TODO: base only\n");
+        test.commit("Alice", "c");
+        test.checkout("feature");
+
+        let options = Options {
+            no_cache: true,
+            ..Options::default()
+        };
+        assert_eq!(merge_base(&test.repo, &options).unwrap().id(), fork);
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let files: Vec<_> = todos
+            .iter()
+            .map(|todo| todo.file_path.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(files, ["b.rs"]);
+        let trend = crate::trend::Comparison::compute(&test.repo, &options)
+            .unwrap()
+            .trend();
+        assert_eq!((trend.added, trend.removed), (1, 0));
+
+        test.repo
+            .config()
+            .unwrap()
+            .set_str("todo.base", "feature")
+            .unwrap();
+        assert_eq!(name(&test.repo, &options), "feature");
+        let error = commit(
+            &test.repo,
+            &Options {
+                base: Some("gone".to_string()),
+                ..Options::default()
+            },
+        )
+        .unwrap_err();
+        assert!(error.message().starts_with("base 'gone' not found"));
+    }
+}
@@ -0,0 +1,192 @@
+//! Baselines, for adopting `todo` in a codebase that already has plenty of
+//! TODOs: `todo baseline write` records every TODO in the working tree in
+//! `.todo-baseline` at the repository root, and later runs leave out the ones
+//! it lists, by ID, unless `--no-baseline` is given.
+//!
+//! The file is text with one TODO per line, sorted by ID, after a header.
+//! Line numbers are not stored, so a line only changes when its TODO does, and
+//! since IDs are hashes, TODOs two branches add land apart rather than next to
+//! each other. When they do conflict, `todo baseline merge` merges the sides
+//! as sets: it keeps what either side added and drops what either removed.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use git2::Repository;
+
+use crate::{dry_run, repo_relative_path, Todo};
+
+pub const FILE: &str = ".todo-baseline";
+
+const HEADER: &str = "\
+# todo baseline: TODOs left out of reports until they change.
+# One per line, sorted by ID: ID, path and text; only the ID is matched.
+# `todo baseline write` regenerates this file and `todo baseline merge`
+# resolves conflicts in it.
+";
+
+/// Each TODO's line, by ID.
+type Entries = BTreeMap<String, String>;
+
+fn parse(contents: &str) -> Entries {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let id = line.split_whitespace().next()?;
+            Some((id.to_string(), line.to_string()))
+        })
+        .collect()
+}
+
+fn render(entries: &Entries) -> String {
+    let mut out = HEADER.to_string();
+    for line in entries.values() {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Records `todos` as the baseline of the repository.
+pub fn write(repo: &Repository, todos: &[Todo]) -> Result<(), String> {
+    let entries: Entries = todos
+        .iter()
+        .map(|todo| {
+            let path = repo_relative_path(repo, &todo.file_path);
+            let line = format!("{} {path}: {}", todo.id, todo.normalized);
+            (todo.id.clone(), line)
+        })
+        .collect();
+    let path = repo.workdir().unwrap().join(FILE);
+    let written = dry_run::write(&path, FILE, &render(&entries))
+        .map_err(|e| format!("writing {}: {e}", path.display()))?;
+    if written {
+        println!("Wrote {} TODOs to {FILE}", entries.len());
+    }
+    Ok(())
+}
+
+/// The IDs in the baseline at `root`, or `None` if there is none.
+fn load(root: &Path) -> Result<Option<HashSet<String>>, String> {
+    let path = root.join(FILE);
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(parse(&contents).into_keys().collect())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("reading {}: {e}", path.display())),
+    }
+}
+
+/// Leaves out the TODOs the repository's baseline lists.
+pub fn suppress(repo: &Repository, todos: &mut Vec<Todo>) {
+    match load(repo.workdir().unwrap()) {
+        Ok(Some(ids)) => todos.retain(|todo| !ids.contains(&todo.id)),
+        Ok(None) => {}
+        Err(e) => eprintln!("Error reading the baseline: {e}"),
+    }
+}
+
+/// Three-way merge of baselines as sets: an entry stays if both sides kept it
+/// or either side added it.
+fn merge(base: &Entries, ours: &Entries, theirs: &Entries) -> Entries {
+    let mut merged = Entries::new();
+    for (id, line) in ours.iter().chain(theirs) {
+        let kept = ours.contains_key(id) && theirs.contains_key(id);
+        if kept || !base.contains_key(id) {
+            merged.entry(id.clone()).or_insert_with(|| line.clone());
+        }
+    }
+    merged
+}
+
+fn read(path: &Path) -> Result<Entries, String> {
+    fs::read_to_string(path)
+        .map(|contents| parse(&contents))
+        .map_err(|e| format!("reading {}: {e}", path.display()))
+}
+
+/// Merges `theirs` into `ours` given their common ancestor `base`, as a git
+/// merge driver run as `todo baseline merge %O %A %B` does.
+pub fn merge_files(base: &Path, ours: &Path, theirs: &Path) -> Result<(), String> {
+    let merged = merge(&read(base)?, &read(ours)?, &read(theirs)?);
+    dry_run::write(ours, &ours.display().to_string(), &render(&merged))
+        .map(|_| ())
+        .map_err(|e| format!("writing {}: {e}", ours.display()))
+}
+
+/// Resolves a merge conflict in the repository's baseline, writing and
+/// staging the merged file.
+pub fn merge_conflict(repo: &Repository) -> Result<(), String> {
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    let conflict = index
+        .conflicts()
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .find(|conflict| {
+            [&conflict.ancestor, &conflict.our, &conflict.their]
+                .into_iter()
+                .flatten()
+                .any(|entry| entry.path == FILE.as_bytes())
+        })
+        .ok_or_else(|| format!("{FILE} has no merge conflict"))?;
+
+    // a side without the file, such as a branch that added it, is empty
+    let side = |entry: Option<git2::IndexEntry>| -> Result<Entries, String> {
+        let Some(entry) = entry else {
+            return Ok(Entries::new());
+        };
+        let blob = repo.find_blob(entry.id).map_err(|e| e.to_string())?;
+        Ok(parse(&String::from_utf8_lossy(blob.content())))
+    };
+    let merged = merge(
+        &side(conflict.ancestor)?,
+        &side(conflict.our)?,
+        &side(conflict.their)?,
+    );
+
+    let path = repo.workdir().unwrap().join(FILE);
+    let written = dry_run::write(&path, FILE, &render(&merged))
+        .map_err(|e| format!("writing {}: {e}", path.display()))?;
+    if !written {
+        println!("[dry run] {FILE} would be staged");
+        return Ok(());
+    }
+    index
+        .add_path(Path::new(FILE))
+        .and_then(|()| index.write())
+        .map_err(|e| e.to_string())?;
+    println!("Merged {FILE}: {} TODOs", merged.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_as_sets() {
+        let entries = |ids: &[&str]| -> Entries {
+            ids.iter()
+                .map(|id| (id.to_string(), format!("{id} src/a.rs: text {id}")))
+                .collect()
+        };
+        let base = entries(&["11", "22", "33"]);
+        // ours removed 22 and added 44; theirs removed 33 and added 55
+        let ours = entries(&["11", "33", "44"]);
+        let theirs = entries(&["11", "22", "55"]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(
+            merged.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["11", "44", "55"]
+        );
+
+        let rendered = render(&merged);
+        assert!(rendered.starts_with(HEADER));
+        assert!(rendered
+            .ends_with("11 src/a.rs: text 11\n44 src/a.rs: text 44\n55 src/a.rs: text 55\n"));
+        assert_eq!(parse(&rendered), merged);
+    }
+}
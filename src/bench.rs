@@ -0,0 +1,177 @@
+//! `todo bench`: measures scan throughput on a generated repository, so
+//! performance regressions show up as numbers in CI.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use git2::{Repository, RepositoryInitOptions, Signature};
+
+use crate::cli::{BenchOptions, Options};
+use crate::scan;
+
+/// Deterministic pseudo-random numbers so every run benchmarks the same input.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        #[allow(clippy::cast_precision_loss)] // 53 random bits are plenty
+        let value = (self.0 >> 11) as f64 / (1u64 << 53) as f64;
+        value
+    }
+}
+
+fn commit_all(repo: &Repository, message: &str) -> Result<(), git2::Error> {
+    let signature = Signature::now("Bench", "bench@example.com")?;
+    let mut index = repo.index()?;
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?;
+    Ok(())
+}
+
+fn source_file(index: usize, lines: usize, todos: &mut dyn FnMut() -> bool) -> String {
+    (0..lines)
+        .map(|line| {
+            if todos() {
+                format!(
+                    "    // TODO(tag{}): follow up on item {index}.{line}\n",
+                    line % 5
+                )
+            } else {
+                format!("    let value_{line} = compute({index}, {line});\n")
+            }
+        })
+        .collect()
+}
+
+/// Generates a repository whose base branch has `files` source files and whose
+/// checked-out branch rewrites all of them, sprinkling in TODOs.
+fn generate(dir: &Path, bench: &BenchOptions) -> Result<Repository, git2::Error> {
+    let repo = Repository::init_opts(dir, RepositoryInitOptions::new().initial_head("main"))?;
+
+    let write = |name: &str, content: &str| {
+        fs::write(dir.join(name), content).map_err(|e| git2::Error::from_str(&e.to_string()))
+    };
+
+    for index in 0..bench.files {
+        write(
+            &format!("file_{index}.rs"),
+            &source_file(index, bench.lines, &mut || false),
+        )?;
+    }
+    commit_all(&repo, "base")?;
+
+    {
+        let base = repo.head()?.peel_to_commit()?;
+        repo.branch("bench", &base, false)?;
+    }
+    repo.set_head("refs/heads/bench")?;
+
+    let mut random = Lcg(0x5eed);
+    for index in 0..bench.files {
+        let content = source_file(index, bench.lines, &mut || {
+            random.next_f64() * 100.0 < bench.density
+        });
+        write(&format!("file_{index}.rs"), &content)?;
+    }
+    commit_all(&repo, "add TODOs")?;
+
+    Ok(repo)
+}
+
+pub fn run(options: &Options, bench: &BenchOptions) -> io::Result<()> {
+    let dir: PathBuf = std::env::temp_dir().join(format!("todo-bench-{}", std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+
+    eprintln!(
+        "Generating {} files of {} lines ({}% TODOs) in {}",
+        bench.files,
+        bench.lines,
+        bench.density,
+        dir.display()
+    );
+    let repo = generate(&dir, bench).map_err(io::Error::other)?;
+
+    let started = Instant::now();
+    let todos = scan(&repo, options);
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let lines = bench.files * bench.lines;
+    #[allow(clippy::cast_precision_loss)]
+    let per_second = |count: usize| count as f64 / elapsed;
+
+    println!(
+        "jobs      {}",
+        options
+            .jobs
+            .map_or_else(|| "auto".to_string(), |jobs| jobs.to_string())
+    );
+    println!("files     {}", bench.files);
+    println!("lines     {lines}");
+    println!("todos     {}", todos.len());
+    println!("elapsed   {elapsed:.3}s");
+    println!("files/s   {:.1}", per_second(bench.files));
+    println!("lines/s   {:.0}", per_second(lines));
+
+    if bench.keep {
+        eprintln!("Kept benchmark repository at {}", dir.display());
+    } else {
+        fs::remove_dir_all(&dir)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scans_every_generated_todo() {
+        let dir = std::env::temp_dir().join(format!("todo-bench-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let bench = BenchOptions {
+            files: 4,
+            lines: 50,
+            density: 10.0,
+            keep: false,
+        };
+        let repo = generate(&dir, &bench).unwrap();
+
+        let generated: usize = (0..bench.files)
+            .map(|index| {
+                let content = fs::read_to_string(dir.join(format!("file_{index}.rs"))).unwrap();
+                content.matches("// TODO").count()
+            })
+            .sum();
+        // the same input on every run
+        assert_eq!(generated, 20);
+        let options = Options {
+            no_cache: true,
+            ..Options::default()
+        };
+        assert_eq!(scan(&repo, &options).len(), generated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
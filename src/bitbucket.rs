@@ -0,0 +1,189 @@
+//! Reporting to Bitbucket Cloud's Code Insights API, which shows a report on
+//! the commit page and annotates the affected lines in pull request diffs.
+
+use std::env;
+use std::io;
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::credentials::{self, Provider};
+use crate::github::strip_ansi;
+use crate::http::Request;
+use crate::queue;
+use crate::{redact, repo_relative_path, Todo};
+
+const REPORT_ID: &str = "todo";
+
+/// Bitbucket accepts at most this many annotations per request.
+const ANNOTATION_BATCH: usize = 100;
+
+/// Longest annotation summary the API accepts.
+const MAX_SUMMARY: usize = 450;
+
+/// Pipelines exposes an authenticating proxy so builds need no credentials.
+const PIPELINES_PROXY: &str = "http://localhost:29418";
+
+struct Target {
+    base_url: String,
+    /// Sends a token, outside of Pipelines.
+    authenticated: bool,
+    proxy: Option<String>,
+}
+
+impl Target {
+    fn from_env(repo: &Repository) -> io::Result<Self> {
+        let var = |name: &str| {
+            env::var(name).map_err(|_| io::Error::other(format!("${name} is not set")))
+        };
+
+        let workspace = var("BITBUCKET_WORKSPACE")?;
+        let repo_slug = var("BITBUCKET_REPO_SLUG")?;
+        let commit = match env::var("BITBUCKET_COMMIT") {
+            Ok(commit) => commit,
+            Err(_) => repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .map_err(io::Error::other)?
+                .id()
+                .to_string(),
+        };
+
+        let path = format!(
+            "/2.0/repositories/{workspace}/{repo_slug}/commit/{commit}/reports/{REPORT_ID}"
+        );
+
+        // outside of Pipelines we talk to the API directly with a token
+        Ok(if credentials::token(Provider::Bitbucket).is_some() {
+            Self {
+                base_url: format!("https://api.bitbucket.org{path}"),
+                authenticated: true,
+                proxy: None,
+            }
+        } else {
+            Self {
+                base_url: format!("http://api.bitbucket.org{path}"),
+                authenticated: false,
+                proxy: Some(PIPELINES_PROXY.to_string()),
+            }
+        })
+    }
+
+    fn request(&self, method: &str, suffix: &str) -> Request {
+        let mut request = Request::new(method, format!("{}{suffix}", self.base_url));
+        if self.authenticated {
+            request = request.authorization("Bearer", Provider::Bitbucket);
+        }
+        if let Some(proxy) = &self.proxy {
+            request = request.proxy(proxy.clone());
+        }
+        request
+    }
+}
+
+/// Replaces the TODO report on the current commit and annotates every TODO.
+///
+/// The repository is identified by the `BITBUCKET_WORKSPACE`,
+/// `BITBUCKET_REPO_SLUG` and `BITBUCKET_COMMIT` variables that Pipelines
+/// sets; a token from `BITBUCKET_TOKEN` or `todo auth login bitbucket`
+/// authenticates runs outside of Pipelines.
+pub fn report(repo: &Repository, todos: &[Todo], new: usize, failed: bool) -> io::Result<()> {
+    let target = Target::from_env(repo)?;
+    // annotations accumulate on a report, so start from a clean one
+    let _ = target.request("DELETE", "").send();
+
+    // the annotations need the report, so they are queued behind it
+    queue::send_all(repo, &requests(repo, &target, todos, new, failed))
+}
+
+/// The report, followed by its annotations in batches the API accepts.
+fn requests(
+    repo: &Repository,
+    target: &Target,
+    todos: &[Todo],
+    new: usize,
+    failed: bool,
+) -> Vec<Request> {
+    let mut requests = vec![target.request("PUT", "").json(&json!({
+        "title": "TODOs",
+        "details": format!("{} TODO comments introduced on this branch.", todos.len()),
+        "report_type": "BUG",
+        "reporter": env!("CARGO_PKG_NAME"),
+        "result": if failed { "FAILED" } else { "PASSED" },
+        "data": [
+            { "title": "TODOs", "type": "NUMBER", "value": todos.len() },
+            { "title": "New TODOs", "type": "NUMBER", "value": new },
+        ],
+    }))];
+
+    let annotations: Vec<_> = todos
+        .iter()
+        .map(|todo| {
+            let path = repo_relative_path(repo, &todo.file_path);
+            json!({
+                "external_id": format!("{path}:{}", todo.line),
+                "annotation_type": "CODE_SMELL",
+                "summary": redact::shared(&strip_ansi(todo.statement.trim()))
+                    .chars()
+                    .take(MAX_SUMMARY)
+                    .collect::<String>(),
+                "path": path,
+                "line": todo.line,
+                "severity": todo.severity.api_name(),
+            })
+        })
+        .collect();
+
+    for batch in annotations.chunks(ANNOTATION_BATCH) {
+        requests.push(target.request("POST", "/annotations").json(&json!(batch)));
+    }
+    requests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn reports_then_annotates_in_batches() {
+        let test = TestRepo::new("bitbucket");
+        let target = Target {
+            base_url: "http://api.bitbucket.org/report".to_string(),
+            authenticated: false,
+            proxy: Some(PIPELINES_PROXY.to_string()),
+        };
+        let todos: Vec<_> = (1..=150)
+            .map(|line| {
+                Todo::new(
+                    &Matcher::default(),
+                    test.dir.join("src/a.rs"),
+                    line,
+                    "// TODO: x",
+                )
+            })
+            .collect();
+
+        let requests: Vec<serde_json::Value> = requests(&test.repo, &target, &todos, 3, true)
+            .iter()
+            .map(|request| serde_json::to_value(request).unwrap())
+            .collect();
+        let body = |index: usize| -> serde_json::Value {
+            let bytes: Vec<u8> = serde_json::from_value(requests[index]["body"].clone()).unwrap();
+            serde_json::from_slice(&bytes).unwrap()
+        };
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0]["method"], "PUT");
+        assert_eq!(requests[0]["proxy"], PIPELINES_PROXY);
+        assert_eq!(body(0)["result"], "FAILED");
+        assert_eq!(body(0)["data"][1]["value"], 3);
+        assert_eq!(
+            requests[1]["url"],
+            "http://api.bitbucket.org/report/annotations"
+        );
+        assert_eq!(body(1).as_array().unwrap().len(), ANNOTATION_BATCH);
+        assert_eq!(body(2).as_array().unwrap().len(), 50);
+        assert_eq!(body(2)[0]["external_id"], "src/a.rs:101");
+    }
+}
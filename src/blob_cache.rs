@@ -0,0 +1,176 @@
+//! Which lines of a file are TODOs, cached under `.git/todo-cache/` by the
+//! file's blob ID. Unlike the run cache of [`crate::cache`], this doesn't
+//! depend on `HEAD` or blame, so after switching branches or rebasing only
+//! contents never seen before are matched again, and files without TODOs are
+//! never blamed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use git2::{ObjectType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Options;
+use crate::timings;
+
+pub const FILE: &str = "blobs.json";
+
+/// Blobs that no run has seen for this many runs are dropped.
+const KEPT_RUNS: u64 = 20;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Stored {
+    /// The matching options the lines were found with.
+    settings: String,
+    /// Counts runs, to tell how long ago a blob was last seen.
+    run: u64,
+    blobs: HashMap<String, Entry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    /// The last run that saw the blob.
+    run: u64,
+    /// The TODO lines, from 1.
+    lines: Vec<usize>,
+}
+
+pub struct BlobCache {
+    path: PathBuf,
+    stored: Stored,
+    /// The blobs this run saw, found or matched anew.
+    seen: Mutex<HashMap<String, Vec<usize>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+/// The ID git gives a blob of `contents`.
+pub fn blob_id(contents: &[u8]) -> String {
+    Oid::hash_object(ObjectType::Blob, contents)
+        .map_or_else(|_| String::new(), |oid| oid.to_string())
+}
+
+impl BlobCache {
+    /// The cache, or `None` with `--no-cache`.
+    pub fn open(repo: &Repository, options: &Options) -> Option<Self> {
+        if options.no_cache {
+            return None;
+        }
+        let settings = format!(
+            "{} {:?} {:?} {} {} {}",
+            env!("CARGO_PKG_VERSION"),
+            options.keywords,
+            options.match_preset,
+            options.case_sensitive,
+            options.require_colon,
+            options.comment_start,
+        );
+        let path = repo.path().join(crate::cache::DIR).join(FILE);
+        let mut stored: Stored = fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .filter(|stored: &Stored| stored.settings == settings)
+            .unwrap_or_default();
+        stored.settings = settings;
+        stored.run += 1;
+        Some(Self {
+            path,
+            stored,
+            seen: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    /// The TODO lines of the blob `id`, from the cache or else from `find`.
+    pub fn lines(&self, id: &str, find: impl FnOnce() -> Vec<usize>) -> Vec<usize> {
+        let lines = self.stored.blobs.get(id).map_or_else(
+            || {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                find()
+            },
+            |entry| {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                entry.lines.clone()
+            },
+        );
+        self.seen
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), lines.clone());
+        lines
+    }
+
+    /// Writes the blobs seen lately, including this run's, and reports the
+    /// hits and misses to `--timings`.
+    pub fn save(self) -> io::Result<()> {
+        let hits = self.hits.into_inner();
+        let misses = self.misses.into_inner();
+        timings::count("blob cache hits", hits);
+        timings::count("blob cache misses", misses);
+        if misses == 0 && hits == 0 {
+            return Ok(());
+        }
+
+        let mut stored = self.stored;
+        let run = stored.run;
+        stored.blobs.retain(|_, entry| entry.run + KEPT_RUNS > run);
+        for (id, lines) in self.seen.into_inner().unwrap() {
+            stored.blobs.insert(id, Entry { run, lines });
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec(&stored)?)
+    }
+
+    /// Whether `path` is this cache's file, which the run cache's pruning
+    /// leaves alone.
+    pub fn is_file(path: &Path) -> bool {
+        path.file_name().is_some_and(|name| name == FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_each_blob_once() {
+        let cache = BlobCache {
+            path: PathBuf::new(),
+            stored: Stored {
+                run: 2,
+                blobs: HashMap::from([(
+                    blob_id(b"// TODO: a\n"),
+                    Entry {
+                        run: 1,
+                        lines: vec![1],
+                    },
+                )]),
+                ..Stored::default()
+            },
+            seen: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        };
+        assert_eq!(
+            blob_id(b"hello\n"),
+            "ce013625030ba8dba906f756967f9e9ca394464a"
+        );
+        assert_eq!(
+            cache.lines(&blob_id(b"// TODO: a\n"), || unreachable!()),
+            [1]
+        );
+        assert!(cache
+            .lines(&blob_id(b"fn main() {}\n"), Vec::new)
+            .is_empty());
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.seen.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,468 @@
+//! Attribution of TODOs to the commits of the current branch from their diffs,
+//! rather than from blame.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use colored::Colorize;
+use git2::{Commit, DiffFindOptions, DiffOptions, Oid, Repository, Sort};
+
+use crate::base;
+use crate::cli::Options;
+use crate::commit_info::CommitInfos;
+use crate::github::strip_ansi;
+use crate::matcher::Matcher;
+use crate::trend::fingerprint;
+use crate::{highlight_todo, repo_relative_path, Todo};
+
+/// A TODO line added or removed by a commit.
+#[derive(Debug, Clone)]
+pub struct TodoChange {
+    pub path: String,
+    /// Line number in the commit's version of the file, or in its parent's
+    /// for removed lines.
+    pub line: u32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitTodos {
+    pub id: Oid,
+    pub added: Vec<TodoChange>,
+    pub removed: Vec<TodoChange>,
+}
+
+/// TODO lines added and removed by each commit in `base..HEAD`, oldest commit
+/// first.
+/// Merge commits are skipped, as the merged commits are listed themselves.
+pub fn walk(repo: &Repository, options: &Options) -> Result<Vec<CommitTodos>, git2::Error> {
+    let base = base::commit(repo, options)?;
+    let matcher = Matcher::configured(options);
+    let head = repo.head()?.peel_to_commit()?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(head.id())?;
+    revwalk.hide(base.id())?;
+
+    let mut commits = Vec::new();
+
+    for id in revwalk {
+        let commit = repo.find_commit(id?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        commits.push(todo_changes(repo, &matcher, &commit)?);
+    }
+
+    Ok(commits)
+}
+
+/// TODO lines a (non-merge) commit adds and removes relative to its parent.
+pub fn todo_changes(
+    repo: &Repository,
+    matcher: &Matcher,
+    commit: &Commit<'_>,
+) -> Result<CommitTodos, git2::Error> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+
+    let mut todos = CommitTodos {
+        id: commit.id(),
+        added: Vec::new(),
+        removed: Vec::new(),
+    };
+
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            let text = String::from_utf8_lossy(line.content());
+            let text = text.trim_end();
+            if !matcher.is_match(text) {
+                return true;
+            }
+
+            let (changes, file, line_number) = match line.origin() {
+                '+' => (&mut todos.added, delta.new_file(), line.new_lineno()),
+                '-' => (&mut todos.removed, delta.old_file(), line.old_lineno()),
+                _ => return true,
+            };
+            if let (Some(path), Some(line_number)) = (file.path(), line_number) {
+                changes.push(TodoChange {
+                    path: path.to_string_lossy().replace('\\', "/"),
+                    line: line_number,
+                    text: text.to_string(),
+                });
+            }
+            true
+        }),
+    )?;
+
+    Ok(todos)
+}
+
+/// A file a commit changes: where it was and is, the old-side ranges of its
+/// hunks, and the lines it adds.
+#[derive(Default)]
+struct FileChange {
+    old_path: Option<PathBuf>,
+    new_path: Option<PathBuf>,
+    /// The first line and number of lines each hunk replaces (with no lines,
+    /// the line its lines are inserted after), and how many lines it adds
+    /// overall.
+    hunks: Vec<(usize, usize, isize)>,
+    added: Vec<usize>,
+}
+
+impl FileChange {
+    /// Where a line of the old file ends up, or `None` if the change deletes
+    /// or rewrites it.
+    fn new_line(&self, line: usize) -> Option<usize> {
+        let mut shift = 0;
+        for &(start, lines, delta) in &self.hunks {
+            let end = start + lines.max(1);
+            if lines > 0 && (start..end).contains(&line) {
+                return None;
+            }
+            if end <= line {
+                shift += delta;
+            }
+        }
+        line.checked_add_signed(shift)
+    }
+}
+
+/// The lines a commit's diff changes, file by file, without context so the
+/// hunks hold only changed lines.
+fn file_changes(repo: &Repository, commit: &Commit<'_>) -> Result<Vec<FileChange>, git2::Error> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+    let mut diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&commit.tree()?),
+        Some(&mut options),
+    )?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let changes = std::cell::RefCell::new(Vec::<FileChange>::new());
+    diff.foreach(
+        &mut |delta, _| {
+            changes.borrow_mut().push(FileChange {
+                old_path: delta.old_file().path().map(PathBuf::from),
+                new_path: delta.new_file().path().map(PathBuf::from),
+                ..FileChange::default()
+            });
+            true
+        },
+        None,
+        Some(&mut |_, hunk| {
+            if let Some(change) = changes.borrow_mut().last_mut() {
+                let delta = i64::from(hunk.new_lines()) - i64::from(hunk.old_lines());
+                change.hunks.push((
+                    hunk.old_start() as usize,
+                    hunk.old_lines() as usize,
+                    isize::try_from(delta).unwrap_or_default(),
+                ));
+            }
+            true
+        }),
+        Some(&mut |_, _, line| {
+            if let (Some(change), Some(number), '+') = (
+                changes.borrow_mut().last_mut(),
+                line.new_lineno(),
+                line.origin(),
+            ) {
+                change.added.push(number as usize);
+            }
+            true
+        }),
+    )?;
+    Ok(changes.into_inner())
+}
+
+/// The commit that added each line, by path and line number.
+pub type AddedBy = HashMap<PathBuf, BTreeMap<usize, Oid>>;
+
+/// Which commit of `base..HEAD` last added each line of `HEAD` the branch
+/// adds, by path and line number, following the lines through each commit's
+/// hunks. This costs one diff per commit instead of a blame per file, but
+/// can't see through merges: `None` if the branch has any.
+pub fn added_lines(repo: &Repository, options: &Options) -> Result<Option<AddedBy>, git2::Error> {
+    let base = base::commit(repo, options)?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(head.id())?;
+    revwalk.hide(base.id())?;
+
+    let mut added_by = AddedBy::new();
+    for id in revwalk {
+        let commit = repo.find_commit(id?)?;
+        if commit.parent_count() > 1 {
+            return Ok(None);
+        }
+        let changes = file_changes(repo, &commit)?;
+
+        // take every old file first, so swapped or renamed files keep theirs
+        let before: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                change
+                    .old_path
+                    .as_ref()
+                    .and_then(|path| added_by.remove(path))
+                    .unwrap_or_default()
+            })
+            .collect();
+        for (change, before) in changes.iter().zip(before) {
+            let Some(path) = &change.new_path else {
+                continue;
+            };
+            let mut after: BTreeMap<usize, Oid> = before
+                .into_iter()
+                .filter_map(|(line, id)| Some((change.new_line(line)?, id)))
+                .collect();
+            after.extend(change.added.iter().map(|&line| (line, commit.id())));
+            if !after.is_empty() {
+                added_by.insert(path.clone(), after);
+            }
+        }
+    }
+
+    Ok(Some(added_by))
+}
+
+/// Re-attributes each TODO to the latest branch commit that added its line.
+/// TODOs no branch commit added, such as uncommitted ones, keep their blame.
+pub fn attribute(
+    repo: &Repository,
+    options: &Options,
+    todos: &mut [Todo],
+) -> Result<(), git2::Error> {
+    let mut added_by: HashMap<(String, String), Oid> = HashMap::new();
+    for commit in walk(repo, options)? {
+        for change in commit.added {
+            added_by.insert((change.path, fingerprint(&change.text)), commit.id);
+        }
+    }
+
+    let mut commit_infos = CommitInfos::new(repo, options);
+
+    for todo in todos {
+        let key = (
+            repo_relative_path(repo, &todo.file_path),
+            fingerprint(&strip_ansi(&todo.statement)),
+        );
+        let Some(&id) = added_by.get(&key) else {
+            continue;
+        };
+        let info = commit_infos.get(&repo.find_commit(id)?);
+        todo.set_commit(&info);
+    }
+
+    Ok(())
+}
+
+/// The commits of the branch in `git rebase -i` order, each followed by
+/// comment lines listing the TODOs it introduces, so fixups for those TODOs
+/// can be squashed into the commits that created them.
+fn rebase_plan(repo: &Repository, options: &Options) -> Result<String, git2::Error> {
+    let commits = walk(repo, options)?;
+
+    if commits.is_empty() {
+        return Ok(format!(
+            "# no commits on this branch since {}\n",
+            base::name(repo, options)
+        ));
+    }
+
+    let mut plan = String::new();
+    for commit_todos in commits {
+        let commit = repo.find_commit(commit_todos.id)?;
+        let short_id = commit.as_object().short_id()?;
+        let _ = writeln!(
+            plan,
+            "pick {} {}",
+            short_id.as_str().unwrap_or_default(),
+            commit.summary().unwrap_or("")
+        );
+
+        for change in commit_todos.added {
+            let _ = writeln!(
+                plan,
+                "#   {}:{} {}",
+                change.path,
+                change.line,
+                change.text.trim()
+            );
+        }
+    }
+
+    Ok(plan)
+}
+
+pub fn print_rebase_plan(repo: &Repository, options: &Options) -> Result<(), git2::Error> {
+    print!("{}", rebase_plan(repo, options)?);
+    Ok(())
+}
+
+/// Prints each commit of the branch, oldest first, with the TODO lines its diff
+/// adds and removes, so they can be cleaned up before opening a pull request.
+pub fn print_per_commit(repo: &Repository, options: &Options) -> Result<(), git2::Error> {
+    let commits = walk(repo, options)?;
+
+    if commits.is_empty() {
+        println!(
+            "No commits on this branch since {}",
+            base::name(repo, options)
+        );
+        return Ok(());
+    }
+    let matcher = Matcher::configured(options);
+
+    for commit_todos in commits {
+        let commit = repo.find_commit(commit_todos.id)?;
+        let short_id = commit.as_object().short_id()?;
+        println!(
+            "{} {} {} {}",
+            short_id.as_str().unwrap_or_default().yellow(),
+            commit.summary().unwrap_or(""),
+            format!("+{}", commit_todos.added.len()).red(),
+            format!("−{}", commit_todos.removed.len()).green(),
+        );
+
+        for change in &commit_todos.added {
+            let location = format!("{}:{}", change.path, change.line);
+            println!(
+                "  {} {} {}",
+                "+".red(),
+                location.dimmed(),
+                highlight_todo(&matcher, change.text.trim())
+            );
+        }
+        for change in &commit_todos.removed {
+            let location = format!("{}:{}", change.path, change.line);
+            println!(
+                "  {} {} {}",
+                "−".green(),
+                location.dimmed(),
+                change.text.trim()
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_info::short_hash;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn follows_lines_through_hunks() {
+        // line 2 rewritten, two lines inserted after line 5, line 9 deleted
+        let change = FileChange {
+            hunks: vec![(2, 1, 0), (5, 0, 2), (9, 1, -1)],
+            ..FileChange::default()
+        };
+        let moved: Vec<_> = (1..=10).map(|line| change.new_line(line)).collect();
+        assert_eq!(
+            moved,
+            [
+                Some(1),
+                None,
+                Some(3),
+                Some(4),
+                Some(5),
+                Some(8),
+                Some(9),
+                Some(10),
+                None,
+                Some(11),
+            ]
+        );
+    }
+
+    #[test]
+    fn attributes_todos_to_the_branch_commits_that_added_them() {
+        let test = TestRepo::new("branch-commit");
+        test.write("a.rs", "fn a() {}\n// TODO: old\n");
+        let old = test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("a.rs", "// TODO: one\nfn a() {}\n// TODO: old\n");
+        let one = test.commit("Bob", "one");
+        test.write(
+            "a.rs",
+            "fn z() {}\n// TODO: one\nfn a() {}\n// TODO: old\n// TODO: two\n",
+        );
+        let two = test.commit("Carol", "two");
+
+        let added_by = added_lines(&test.repo, &Options::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            added_by[&PathBuf::from("a.rs")],
+            BTreeMap::from([(1, two), (2, one), (5, two)])
+        );
+
+        let commits = |all_files| {
+            let options = Options {
+                all_files,
+                no_cache: true,
+                ..Options::default()
+            };
+            let (mut todos, _) = crate::get_todos(&test.repo, &options);
+            attribute(&test.repo, &options, &mut todos).unwrap();
+            todos
+                .iter()
+                .map(|todo| todo.commit_hash.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(commits(false), [one.to_string(), two.to_string()]);
+        // TODOs the branch didn't add keep their blame
+        assert_eq!(
+            commits(true),
+            [one.to_string(), old.to_string(), two.to_string()]
+        );
+    }
+
+    #[test]
+    fn plans_a_rebase_with_the_todos_of_each_commit() {
+        let test = TestRepo::new("rebase-plan");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        assert_eq!(
+            rebase_plan(&test.repo, &Options::default()).unwrap(),
+            "# no commits on this branch since main\n"
+        );
+
+        test.write("a.rs", "fn a() {\n    // TODO: one\n}\n");
+        let one = test.commit("Bob", "one");
+        test.write("b.rs", "fn b() {}\n");
+        let two = test.commit("Bob", "two");
+        let short = |id| short_hash(&test.repo.find_object(id, None).unwrap());
+        assert_eq!(
+            rebase_plan(&test.repo, &Options::default()).unwrap(),
+            format!(
+                "pick {} one\n#   a.rs:2 // TODO: one\npick {} two\n",
+                short(one),
+                short(two)
+            )
+        );
+    }
+}
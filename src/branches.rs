@@ -0,0 +1,243 @@
+//! Finding TODOs stranded on branches that were never merged, which usually
+//! means abandoned work that nobody remembers.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::DateTime;
+use colored::Colorize;
+use git2::{BranchType, Repository, Tree};
+use ptree::{print_tree, TreeBuilder};
+
+use crate::cli::Options;
+use crate::matcher::Matcher;
+use crate::trend::{fingerprint, todo_fingerprints};
+use crate::{base, dates, highlight_todo};
+
+struct StrandedTodo {
+    path: PathBuf,
+    line: u32,
+    text: String,
+}
+
+struct StrandedBranch {
+    name: String,
+    last_commit: String,
+    todos: Vec<StrandedTodo>,
+}
+
+/// Fingerprints of the TODOs in a file on the base branch, loaded on demand.
+struct BaseTodos<'repo> {
+    repo: &'repo Repository,
+    matcher: &'repo Matcher,
+    tree: Tree<'repo>,
+    files: RefCell<HashMap<PathBuf, HashSet<String>>>,
+}
+
+impl BaseTodos<'_> {
+    fn contains(&self, path: &Path, fingerprint: &str) -> bool {
+        let mut files = self.files.borrow_mut();
+        let todos = files.entry(path.to_path_buf()).or_insert_with(|| {
+            self.tree
+                .get_path(path)
+                .and_then(|entry| self.repo.find_blob(entry.id()))
+                .map(|blob| {
+                    let content = String::from_utf8_lossy(blob.content());
+                    todo_fingerprints(self.matcher, &content).collect()
+                })
+                .unwrap_or_default()
+        });
+        todos.contains(fingerprint)
+    }
+}
+
+/// TODOs added on `branch` since it forked off the base branch that the base
+/// branch does not have (anywhere in the same file).
+fn stranded_todos(
+    repo: &Repository,
+    base: &BaseTodos<'_>,
+    base_tip: git2::Oid,
+    tip: git2::Oid,
+) -> Result<Vec<StrandedTodo>, git2::Error> {
+    let merge_base = repo.merge_base(base_tip, tip)?;
+    let old_tree = repo.find_commit(merge_base)?.tree()?;
+    let new_tree = repo.find_commit(tip)?.tree()?;
+
+    let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+
+    let mut todos = Vec::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            let (Some(path), Some(line_number)) = (delta.new_file().path(), line.new_lineno())
+            else {
+                return true;
+            };
+            if line.origin() != '+' {
+                return true;
+            }
+
+            let text = String::from_utf8_lossy(line.content());
+            let text = text.trim_end();
+            if base.matcher.is_match(text) && !base.contains(path, &fingerprint(text)) {
+                todos.push(StrandedTodo {
+                    path: path.to_path_buf(),
+                    line: line_number,
+                    text: text.to_string(),
+                });
+            }
+            true
+        }),
+    )?;
+
+    Ok(todos)
+}
+
+fn stranded_branches(
+    repo: &Repository,
+    options: &Options,
+    matcher: &Matcher,
+    remote: bool,
+) -> Result<Vec<StrandedBranch>, git2::Error> {
+    let base_commit = base::commit(repo, options)?;
+    let base_tip = base_commit.id();
+
+    let base = BaseTodos {
+        repo,
+        matcher,
+        tree: base_commit.tree()?,
+        files: RefCell::default(),
+    };
+
+    let branch_type = if remote {
+        None
+    } else {
+        Some(BranchType::Local)
+    };
+
+    let mut stranded = Vec::new();
+
+    for branch in repo.branches(branch_type)? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+
+        // skips symbolic refs such as `origin/HEAD`
+        let Some(tip) = branch.get().target() else {
+            continue;
+        };
+
+        let merged = tip == base_tip || repo.graph_descendant_of(base_tip, tip)?;
+        if merged {
+            continue;
+        }
+
+        let todos = stranded_todos(repo, &base, base_tip, tip)?;
+        if todos.is_empty() {
+            continue;
+        }
+
+        let commit = repo.find_commit(tip)?;
+        let when = DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_default();
+        let last_commit = format!(
+            "{} by {}",
+            dates::ago(when),
+            commit.author().name().unwrap_or("Unknown")
+        );
+
+        stranded.push(StrandedBranch {
+            name,
+            last_commit,
+            todos,
+        });
+    }
+
+    Ok(stranded)
+}
+
+/// Prints each unmerged branch that carries TODOs the base branch lacks.
+pub fn report(repo: &Repository, options: &Options, remote: bool) -> Result<(), git2::Error> {
+    let matcher = Matcher::configured(options);
+    let branches = stranded_branches(repo, options, &matcher, remote)?;
+
+    if branches.is_empty() {
+        println!("✅ No TODOs stranded on unmerged branches.");
+        return Ok(());
+    }
+
+    for branch in &branches {
+        let mut tree = TreeBuilder::new(format!(
+            "{} (last commit {})",
+            branch.name.underline(),
+            branch.last_commit
+        ));
+
+        for todo in &branch.todos {
+            tree.add_empty_child(format!(
+                "{}:{} - {}",
+                todo.path.display(),
+                todo.line,
+                highlight_todo(&matcher, todo.text.trim())
+            ));
+        }
+
+        print_tree(&tree.build()).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        println!();
+    }
+
+    let count: usize = branches.iter().map(|branch| branch.todos.len()).sum();
+    println!(
+        "{count} TODO{} stranded on {} unmerged branch{}",
+        if count == 1 { "" } else { "s" },
+        branches.len(),
+        if branches.len() == 1 { "" } else { "es" }
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn finds_todos_only_unmerged_branches_add() {
+        let test = TestRepo::new("branches");
+        test.write("a.rs", "// TODO: shared\n");
+        test.commit("Alice", "a");
+        test.branch("merged");
+        test.write("b.rs", "// TODO: merged\n");
+        test.commit("Bob", "b");
+        test.checkout("main");
+        test.merge("merged", "Alice", "merge");
+        test.branch("abandoned");
+        // the base already has this one
+        test.write("a.rs", "// TODO: shared\n    // TODO: shared\n");
+        test.write("c.rs", "fn c() {}\n// TODO: stranded\n");
+        test.commit("Carol", "c");
+        test.checkout("main");
+        test.branch("quiet");
+        test.write("d.rs", "fn d() {}\n");
+        test.commit("Carol", "d");
+        test.checkout("main");
+
+        let branches =
+            stranded_branches(&test.repo, &Options::default(), &Matcher::default(), false).unwrap();
+        let found: Vec<_> = branches
+            .iter()
+            .flat_map(|branch| {
+                branch.todos.iter().map(|todo| {
+                    let path = todo.path.display();
+                    format!("{} {path}:{}", branch.name, todo.line)
+                })
+            })
+            .collect();
+        assert_eq!(found, ["abandoned c.rs:2"]);
+        assert!(branches[0].last_commit.ends_with("by Carol"));
+    }
+}
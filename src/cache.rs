@@ -0,0 +1,194 @@
+//! An on-disk cache of each file's TODOs under `.git/todo-cache/`, so a run
+//! on a branch that hasn't changed since the last one skips blaming, which is
+//! most of a scan's time.
+//!
+//! Each cache file holds one run's results, named after what they depend on
+//! across files: the `HEAD` commit, the options that change what is found or
+//! which files are scanned, and the branch's commits. Each file's entry is
+//! only reused while its contents and the lines the branch touches are
+//! unchanged.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use git2::{ObjectType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::blob_cache::BlobCache;
+use crate::cli::Options;
+use crate::Todo;
+
+pub const DIR: &str = "todo-cache";
+
+/// Cache files kept, most recently written first, so switching between a few
+/// branches stays fast without the directory growing forever.
+const KEPT_RUNS: usize = 8;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    todos: Vec<Todo>,
+}
+
+pub struct Cache {
+    path: PathBuf,
+    entries: HashMap<PathBuf, Entry>,
+    /// What this run found, written back by [`Cache::save`].
+    fresh: HashMap<PathBuf, Entry>,
+}
+
+fn hash(bytes: &[u8]) -> String {
+    // git's own hashing, which stays the same from one build to the next
+    Oid::hash_object(ObjectType::Blob, bytes).map_or_else(|_| String::new(), |oid| oid.to_string())
+}
+
+/// A key for `contents` with these lines reported.
+fn contents_key(mut contents: Vec<u8>, lines_to_report: Option<&HashSet<usize>>) -> String {
+    if let Some(lines) = lines_to_report {
+        let mut lines: Vec<_> = lines.iter().collect();
+        lines.sort_unstable();
+        contents.extend(format!("\0{lines:?}").into_bytes());
+    }
+    hash(&contents)
+}
+
+impl Cache {
+    /// The cache for this run, or `None` with `--no-cache` or before the
+    /// first commit.
+    pub fn open(
+        repo: &Repository,
+        options: &Options,
+        commits: Option<&HashSet<Oid>>,
+    ) -> Option<Self> {
+        if options.no_cache {
+            return None;
+        }
+        let head = repo.head().ok()?.target()?;
+
+        let mut commits: Vec<_> = commits.into_iter().flatten().map(Oid::to_string).collect();
+        commits.sort_unstable();
+        let settings = format!(
+            "{} {head} {:?} {:?} {:?} {} {} {} {} {:?} {} {} {} {} {}",
+            env!("CARGO_PKG_VERSION"),
+            options.keywords,
+            options.severities,
+            options.match_preset,
+            options.case_sensitive,
+            options.require_colon,
+            options.comment_start,
+            options.first_parent,
+            options.merge_attribution,
+            options.all_files,
+            options.staged,
+            options.dirty,
+            options.whole_files,
+            commits.join(","),
+        );
+        let path = repo
+            .path()
+            .join(DIR)
+            .join(format!("{}.json", hash(settings.as_bytes())));
+
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+        Some(Self {
+            path,
+            entries,
+            fresh: HashMap::new(),
+        })
+    }
+
+    /// What a file's entry depends on: its contents and the lines reported.
+    pub fn key(file_path: &Path, lines_to_report: Option<&HashSet<usize>>) -> Option<String> {
+        Some(contents_key(fs::read(file_path).ok()?, lines_to_report))
+    }
+
+    /// Like [`key`](Self::key), for a file read from the blob `id` rather
+    /// than the working tree.
+    pub fn blob_key(id: Oid, lines_to_report: Option<&HashSet<usize>>) -> String {
+        contents_key(id.as_bytes().to_vec(), lines_to_report)
+    }
+
+    /// The TODOs found in `path` when it last had `key`.
+    pub fn get(&self, path: &Path, key: &str) -> Option<Vec<Todo>> {
+        let entry = self.entries.get(path).filter(|entry| entry.key == key)?;
+        Some(entry.todos.clone())
+    }
+
+    pub fn insert(&mut self, path: PathBuf, key: String, todos: Vec<Todo>) {
+        self.fresh.insert(path, Entry { key, todos });
+    }
+
+    /// Writes this run's entries, replacing the previous ones, and prunes
+    /// the oldest cache files.
+    pub fn save(self) -> io::Result<()> {
+        let dir = self.path.parent().unwrap();
+        fs::create_dir_all(dir)?;
+        fs::write(&self.path, serde_json::to_vec(&self.fresh)?)?;
+
+        let mut runs: Vec<_> = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| !BlobCache::is_file(&entry.path()))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+        runs.sort_by_key(|(modified, _)| Reverse(*modified));
+        for (_, path) in runs.into_iter().skip(KEPT_RUNS) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_covers_reported_lines() {
+        let file = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let whole = Cache::key(&file, None).unwrap();
+        let first = Cache::key(&file, Some(&HashSet::from([1]))).unwrap();
+        assert_eq!(Cache::key(&file, None).unwrap(), whole);
+        assert_ne!(first, whole);
+        assert_ne!(
+            first,
+            Cache::key(&file, Some(&HashSet::from([1, 2]))).unwrap()
+        );
+        assert!(Cache::key(&file.with_extension("missing"), None).is_none());
+        assert_ne!(Cache::blob_key(Oid::zero(), None), whole);
+    }
+
+    #[test]
+    fn scan_modes_are_cached_apart() {
+        let test = crate::test_repo::TestRepo::new("cache");
+        test.write("a.rs", "// TODO: a\n");
+        test.commit("Alice", "a");
+        let path = |options: Options| Cache::open(&test.repo, &options, None).unwrap().path;
+        let paths = HashSet::from([
+            path(Options::default()),
+            path(Options {
+                all_files: true,
+                ..Options::default()
+            }),
+            path(Options {
+                staged: true,
+                ..Options::default()
+            }),
+            path(Options {
+                dirty: true,
+                ..Options::default()
+            }),
+            path(Options {
+                dirty: true,
+                whole_files: true,
+                ..Options::default()
+            }),
+        ]);
+        assert_eq!(paths.len(), 5);
+    }
+}
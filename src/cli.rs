@@ -0,0 +1,1194 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::process::exit;
+use std::str::FromStr;
+
+use crate::credentials::Provider;
+use crate::limits::Limits;
+use crate::matcher::Preset;
+use crate::owners;
+use crate::redact;
+use crate::rotate::Week;
+use crate::severity::Severity;
+
+/// Names the group of TODOs without tags unless `--untagged-label` renames it.
+const UNTAGGED: &str = "untagged";
+
+const USAGE: &str = "\
+Usage: todo [OPTIONS] [COMMAND]
+
+Scans the commits on the current branch for TODO comments.
+
+Commands:
+  list      Report the TODOs the current branch introduces (the default)
+  check     Like list, but exit with a non-zero status if the branch adds
+            TODOs that do not exist on the base branch (--fail-on-new) or
+            exceeds the [check] limits of .todo.toml
+  branches  Report TODOs that only exist on branches not merged into the base
+            branch (add --remote to include remote-tracking branches)
+  stats     Print TODO counts by tag and author (add --push <URL> to send
+            them to influx://host/db or graphite://host, --by-language for
+            counts and density per language)
+  hotspots  List the files with the most TODOs per 1000 lines (--top <N>,
+            default 20)
+  serve     Serve the scan history to Grafana's JSON datasource plugin, and
+            the latest scan's TODOs on /api/todos, with the team's triage
+            statuses and notes from /api/annotations (--port <PORT>, default
+            8080; --bind <ADDR>, default 127.0.0.1, other than loopback only
+            with TODO_WEBHOOK_SECRET set; --interval <SECONDS> between
+            rescans, default 300; --allow-origin <ORIGIN> for a web page
+            on ORIGIN to use the API); with TODO_WEBHOOK_SECRET set, push
+            webhooks to /webhook fetch and rescan
+  bench     Time a scan of a generated repository (--files <N>, --lines <N>,
+            --density <PERCENT> of TODO lines, --keep to keep the repository)
+  snapshot  Store this scan as a git note under refs/notes/todo
+            (`snapshot log` lists snapshots, `snapshot diff [FROM] [TO]`
+            compares two, by default the latest two)
+  handover  Write a Markdown handover of every TODO owned by --author <NAME>
+            (name, email or @handle), with code context and permalinks
+  rotate    Assign each member of --team a few TODOs for this week's debt
+            duty, the same on every run (--week <YYYY-Www> for another
+            week, --send to post them to the team file's webhook)
+  init      Write a starter .todo.toml and .todoignore for the repository,
+            asking about the base branch, keywords and the vendored and
+            generated code it finds (--yes to accept its suggestions,
+            --force to overwrite existing files)
+  workspace [DIR]
+            Report every git repository under DIR (default: the current
+            directory), selected and configured by DIR/.todo-workspace or
+            --repos <FILE>
+  flush     Resend requests to integrations that failed and were queued
+  auth login <PROVIDER>
+            Store a token for bitbucket, github, influx, jira or upload in
+            the system keychain, or a private credentials file without one
+  per-commit
+            List the TODOs each commit of the branch adds and removes
+  rebase-plan
+            List the commits of the branch like `git rebase -i`, each with
+            the TODOs it introduces
+  tui       Browse the TODOs in the terminal: filter them, group them by
+            commit, author, tag or file, see the code around each and open
+            it in $EDITOR
+  lint      Report TODOs with tags missing from the allowed-tags of
+            .todo.toml, suggesting the allowed tag each was likely meant to
+            be, and exit with a non-zero status if there are any
+  baseline write
+            Record every TODO in the working tree in .todo-baseline; later
+            runs leave those out unless --no-baseline is given
+  baseline merge [BASE OURS THEIRS]
+            Resolve a merge conflict in .todo-baseline, keeping the TODOs
+            either side added and dropping those either removed; with
+            files, merge them into OURS, as a git merge driver
+  done <FILE:LINE|ID>
+            Remove the TODO at FILE:LINE, or with this ID, deleting its
+            comment, or the line when the comment is all it holds, and
+            print the change (--dry-run to only print it)
+  export github-issues [ID...]
+            Open a GitHub issue for each TODO with these IDs, or each one
+            reported, that has no issue yet, and write its number into the
+            comment as TODO(#456) (--dry-run to only print the requests)
+
+Options:
+  -C, --repo <PATH>      Run in the repository at PATH instead of the current
+                         directory, like `git -C`
+      --base <REF>       Branch or other ref to compare against, e.g. master or
+                         origin/main [default: the todo.base git config, else
+                         the remote's default branch, else main or master]
+      --format <FORMAT>  Output format [default: tree]
+                         [possible values: tree, gerrit, sonar, json,
+                         csv, html, markdown, sarif, github]
+      --out <FORMAT[=PATH]>
+                         Also write a report in FORMAT to PATH, or to stdout
+                         when PATH is omitted or `stdout`; repeatable, e.g.
+                         --out tree --out json=report.json, and replaces
+                         --format
+      --grouped          With --format json, nest TODOs by commit, tag and
+                         author with counts at each level
+      --fail-on-new      Exit with a non-zero status if the branch introduces
+                         TODOs that do not exist on the base branch
+      --fail-on-severity <SEVERITY>
+                         Exit with a non-zero status if any reported TODO is
+                         at least this severe [possible values: low, medium,
+                         high]
+      --fail-expired     Exit with a non-zero status if any reported TODO is
+                         past the due date of its `by 2025-03-01` or
+                         `due=2025-03-01` tag
+      --max-todos <N>    Exit with a non-zero status if more than N TODOs are
+                         reported
+      --max-per-severity <SEVERITY=N>
+                         Likewise for TODOs of one severity, e.g. high=0;
+                         repeatable
+      --max-per-tag <TAG=N>
+                         Likewise for TODOs with one tag, e.g. perf=3;
+                         repeatable
+      --max-untagged <N> Likewise for TODOs without tags
+      --match <PRESET>   How strictly lines must look like TODOs [default:
+                         default] [possible values: loose (any `todo`
+                         substring), default (the word in any case), strict
+                         (`TODO:` opening a comment)]
+      --keyword <WORD[=SEVERITY]>
+                         Also match this keyword, e.g. PENDIENTE or 要修正,
+                         optionally with its severity, e.g. FIXME=high;
+                         repeatable [default severity: high for FIXME and
+                         BUG, medium for HACK and XXX, else low]
+      --case-sensitive   Only match the upper-case keyword
+      --require-colon    Require a colon after the keyword and its tags
+      --comment-start    Require the keyword to open a comment
+      --group-by <GROUPING>[,<GROUPING>...]
+                         The levels of the tree, outermost first, or none for
+                         a flat list; a single commit, milestone, week, month
+                         or priority keeps tags and authors below it
+                         [default: commit,tag,author] [possible values:
+                         commit, branch-commit, milestone, week (of the
+                         commit), month, priority, tag, author, org (by
+                         email domain), file, dir, none]
+      --untagged-label <LABEL>
+                         What the group of untagged TODOs is called in the
+                         tree and the GitHub step summary [default: untagged]
+      --sort <ORDER>     Order TODOs, and the groups by their first TODO,
+                         instead of newest commit first [possible values: age
+                         (oldest first), file, author, priority, severity]
+      --reverse          Reverse the order of --sort
+      --milestones <FILE>
+                         File mapping tags to milestones, one `tag =
+                         milestone` per line
+      --owner <TAG=OWNER>
+                         Default owner of TODOs with TAG and no @assignee,
+                         e.g. perf=@perf-team; repeatable
+      --all              Scan every file in the working tree that git does not
+                         ignore, not only the branch's changes
+      --staged           Scan what is staged for the next commit instead of the
+                         branch's changes, e.g. in a pre-commit hook
+      --dirty            Scan what is not committed yet, staged or not, and
+                         untracked files instead of the branch's changes
+  -t, --type <TYPES>     Only scan files of these types, ripgrep's names
+                         separated by commas, e.g. rs,ts,py; repeatable
+      --path <GLOB>      Only scan paths matching this glob, e.g. src/**;
+                         repeatable
+      --exclude <GLOB>   Skip paths matching this glob, in .gitignore syntax,
+                         e.g. **/*.generated.rs; repeatable
+      --type-add <NAME:GLOB>
+                         Define a file type for --type, e.g. proto:*.proto;
+                         repeatable
+      --fs-markers       Also list scanned files and directories named like
+                         leftovers, e.g. hack_todo.rs or WIP_old/
+  -j, --jobs <N>         Scan and blame N files at once [default: the number
+                         of CPUs]
+      --no-cache         Scan every file again instead of reusing what the
+                         last run found in files that have not changed
+      --timings          Print how long each phase took and how often the
+                         caches were hit, to stderr
+  -q, --quiet            Don't print the report, only errors; reports with
+                         --out paths are still written
+      --machine          With --quiet, print one line of JSON instead: the
+                         counts, timings and cache hits, for benchmarks and
+                         scripts
+      --whole-files      Report every branch TODO in the files the branch
+                         changes, not only those on lines its diff touches
+      --show-branches    List the local branches that contain each TODO's
+                         commit, to spot fixes already made elsewhere
+      --first-parent     Follow only the first parent of merge commits when
+                         blaming, attributing merged lines to the merge itself
+      --merge-attribution <MODE>
+                         Whose name TODOs blamed on a merge commit are listed
+                         under [default: merge] [possible values: merge,
+                         original]
+      --squash-aware     Recover when TODOs were first written from branch
+                         history that was squash merged, when refs or reflogs
+                         still reach it
+      --ignore-author <PATTERN>
+                         Credit TODOs from matching authors, e.g.
+                         'dependabot[bot]' or '*-ci@*' (name or email, `*`
+                         matches anything), to 'automation'; repeatable
+      --utc              Show dates in UTC instead of the local time zone
+      --precise-ages     Count ages in exact days, like `23 days ago`, rather
+                         than rounding them to `3 weeks ago`
+      --absolute-after <DAYS>
+                         Show dates instead of ages for commits older than
+                         this many days
+      --max-age <DAYS>   Escalate TODOs older than this many days: raise their
+                         severity, tag them 'stale' and list them first
+      --team <FILE>      File listing current team members, one name, email or
+                         @handle per line (or a .toml file listing them
+                         under members); TODOs owned by anyone else are
+                         marked orphaned
+      --orphaned         Only report orphaned TODOs (requires --team)
+      --check-issues     Ask GitHub, or Jira at $JIRA_URL, about the issue each
+                         TODO references, as TODO(#123) or TODO(PROJ-42),
+                         and flag TODOs whose issue is resolved
+      --author <NAME>    Only report TODOs owned by this person: the commit
+                         author by name or email, or an @assignee tag;
+                         repeatable
+      --mine             Only report TODOs owned by the user.name or
+                         user.email of your git config
+      --prod-only        Only report TODOs in production code, leaving out
+                         test directories and files named like tests, e.g.
+                         tests/, parser_test.go or app.spec.ts
+      --test-only        Only report TODOs in tests
+      --test-path <PATTERN>
+                         Also treat paths matching this gitignore-style
+                         pattern as tests, e.g. qa/**; repeatable
+      --language <NAME>  Only report TODOs in this language, e.g. Rust or
+                         TypeScript; repeatable
+      --no-baseline      Also report the TODOs listed in .todo-baseline
+      --otel-endpoint <URL>
+                         Send OpenTelemetry traces of the scan phases to this
+                         OTLP/HTTP collector, e.g. http://localhost:4318
+      --upload <URL>     PUT the report to this URL, e.g. a presigned object
+                         storage URL (JSON unless --format is machine-readable)
+      --bitbucket        Publish a Code Insights report with line annotations
+                         to Bitbucket Cloud
+      --redact           In every report but the terminal tree, replace
+                         secret-looking text in TODOs, such as emails, tokens
+                         and `password=...`, with [redacted]
+      --redact-pattern <REGEX>
+                         Also redact text matching this regular expression;
+                         repeatable, and implies --redact
+      --max-statement-length <CHARS>
+                         In every report but the terminal tree, cut TODOs
+                         longer than this short with an ellipsis
+      --dry-run          Change nothing: print the requests integrations would
+                         send, with credentials hidden, and the changes
+                         commands would make to files, notes and stored
+                         tokens, instead of making them
+  -h, --help             Print this help
+";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The human-readable tree grouped by commit, tag and author.
+    #[default]
+    Tree,
+    /// A Gerrit `ReviewInput` carrying one robot comment per TODO.
+    Gerrit,
+    /// `SonarQube`'s Generic Issue Import JSON.
+    Sonar,
+    /// One JSON object per TODO, next to the scan's metadata.
+    Json,
+    /// One CSV row per TODO.
+    Csv,
+    /// A standalone HTML page with charts.
+    Html,
+    /// Nested Markdown lists for pull request comments.
+    Markdown,
+    /// SARIF for GitHub code scanning.
+    Sarif,
+    /// GitHub Actions workflow commands that annotate each TODO's line.
+    Github,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Self::Tree),
+            "gerrit" => Ok(Self::Gerrit),
+            "sonar" => Ok(Self::Sonar),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "html" => Ok(Self::Html),
+            "markdown" => Ok(Self::Markdown),
+            "sarif" => Ok(Self::Sarif),
+            "github" => Ok(Self::Github),
+            other => Err(format!("unknown format '{other}'")),
+        }
+    }
+}
+
+/// Where one report of the run goes, from `--out FORMAT[=PATH]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub format: Format,
+    /// `None` for stdout.
+    pub path: Option<PathBuf>,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = s.split_once('=').unwrap_or((s, "stdout"));
+        Ok(Self {
+            format: format.parse()?,
+            path: match path {
+                "" => return Err(format!("missing path in '{s}'")),
+                "stdout" | "-" => None,
+                path => Some(path.into()),
+            },
+        })
+    }
+}
+
+/// One level of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// The commit `git blame` reports for the line.
+    Commit,
+    /// The commit on the current branch whose diff added the line.
+    BranchCommit,
+    /// The milestone the TODO's tags are mapped to with `--milestones`.
+    Milestone,
+    /// The week the blamed commit was written in.
+    Week,
+    /// The month the blamed commit was written in.
+    Month,
+    /// The TODO's `priority=` field.
+    Priority,
+    /// Each of the TODO's tags, or none.
+    Tag,
+    /// The TODO's author and co-authors.
+    Author,
+    /// The organization of the author's email domain.
+    Org,
+    /// The file the TODO is in.
+    File,
+    /// The directory of that file.
+    Dir,
+}
+
+impl FromStr for Grouping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "commit" => Ok(Self::Commit),
+            "branch-commit" => Ok(Self::BranchCommit),
+            "milestone" => Ok(Self::Milestone),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            "priority" => Ok(Self::Priority),
+            "tag" => Ok(Self::Tag),
+            "author" => Ok(Self::Author),
+            "org" => Ok(Self::Org),
+            "file" => Ok(Self::File),
+            "dir" => Ok(Self::Dir),
+            other => Err(format!("unknown grouping '{other}'")),
+        }
+    }
+}
+
+/// What `--sort` orders TODOs by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// Oldest first, by when the TODO was written.
+    Age,
+    /// By path, then line.
+    File,
+    /// By author name.
+    Author,
+    /// By the `priority=` field, most urgent first and TODOs without one last.
+    Priority,
+    /// Highest severity first.
+    Severity,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "age" => Ok(Self::Age),
+            "file" => Ok(Self::File),
+            "author" => Ok(Self::Author),
+            "priority" => Ok(Self::Priority),
+            "severity" => Ok(Self::Severity),
+            other => Err(format!("unknown sort '{other}'")),
+        }
+    }
+}
+
+/// The levels of the tree, outermost first; none for a flat list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupBy(pub Vec<Grouping>);
+
+impl Default for GroupBy {
+    fn default() -> Self {
+        Self(vec![Grouping::Commit, Grouping::Tag, Grouping::Author])
+    }
+}
+
+impl GroupBy {
+    #[must_use]
+    pub fn contains(&self, grouping: Grouping) -> bool {
+        self.0.contains(&grouping)
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    /// A comma-separated list, or `none`. A single grouping other than by
+    /// tag, author, file or directory keeps tags and authors below it, as
+    /// `--group-by` did before it took a list.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(Self(Vec::new()));
+        }
+        let mut levels: Vec<Grouping> = Vec::new();
+        for level in s.split(',') {
+            let level = level.trim().parse()?;
+            if levels.contains(&level) {
+                return Err(format!("'{s}' groups by the same thing twice"));
+            }
+            levels.push(level);
+        }
+        if let [level] = levels[..] {
+            if !matches!(
+                level,
+                Grouping::Tag | Grouping::Author | Grouping::File | Grouping::Dir
+            ) {
+                levels.extend([Grouping::Tag, Grouping::Author]);
+            }
+        }
+        Ok(Self(levels))
+    }
+}
+
+/// Who owns a TODO whose line blames to a merge commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeAttribution {
+    /// The author of the merge commit.
+    #[default]
+    Merge,
+    /// The author of the commit that wrote the line on the merged branch.
+    Original,
+}
+
+impl FromStr for MergeAttribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(Self::Merge),
+            "original" => Ok(Self::Original),
+            other => Err(format!("unknown merge attribution '{other}'")),
+        }
+    }
+}
+
+/// Shape of the repository `todo bench` generates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchOptions {
+    pub files: usize,
+    /// Lines per file.
+    pub lines: usize,
+    /// Percentage of lines that are TODOs.
+    pub density: f64,
+    /// Keep the generated repository instead of deleting it.
+    pub keep: bool,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            files: 500,
+            lines: 200,
+            density: 2.0,
+            keep: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotAction {
+    Record,
+    Log,
+    Diff {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Command {
+    /// Report the TODOs introduced on the current branch.
+    #[default]
+    List,
+    /// Report the branch's TODOs, failing if it adds new ones.
+    Check,
+    /// Report TODOs stranded on branches that were never merged.
+    Branches { remote: bool },
+    /// List the branch's commits with the TODOs each one introduces.
+    RebasePlan,
+    /// List the TODOs each commit of the branch adds and removes.
+    PerCommit,
+    /// Print aggregate counts, optionally pushing them to a time-series store.
+    Stats {
+        push: Option<String>,
+        by_language: bool,
+    },
+    /// Rank files by TODOs per 1000 lines.
+    Hotspots { top: usize },
+    /// Record scans and serve their history over HTTP.
+    Serve {
+        bind: IpAddr,
+        port: u16,
+        interval_secs: u64,
+        /// The one web origin whose pages may call the API.
+        allow_origin: Option<String>,
+    },
+    /// Record, list or compare scans stored in git notes.
+    Snapshot(SnapshotAction),
+    /// Write a Markdown document of one person's TODOs for handing them over.
+    Handover { author: String },
+    /// Hand out the week's debt duty to the team.
+    Rotate { week: Option<Week>, send: bool },
+    /// Write starter configuration files.
+    Init { yes: bool, force: bool },
+    /// Report each repository under a directory.
+    Workspace {
+        dir: Option<PathBuf>,
+        repos: Option<PathBuf>,
+    },
+    /// Resend queued requests.
+    Flush,
+    /// `todo auth` without a subcommand.
+    Auth,
+    /// Store a token for an integration.
+    AuthLogin { provider: Option<Provider> },
+    /// Measure scan throughput on a generated repository.
+    Bench(BenchOptions),
+    /// Browse the TODOs in the terminal.
+    Tui,
+    /// Report TODOs with tags outside the allowed set.
+    Lint,
+    /// Remove a TODO from its file.
+    Done { target: Option<String> },
+    /// `todo baseline` without a subcommand.
+    Baseline,
+    /// Record the working tree's TODOs as the baseline.
+    BaselineWrite,
+    /// Merge baselines, from three files or a conflict in the index.
+    BaselineMerge { files: Vec<PathBuf> },
+    /// `todo export` without a target.
+    Export,
+    /// Open GitHub issues for the TODOs with these IDs, or all reported ones.
+    ExportGithubIssues { ids: Vec<String> },
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number '{value}' for '{flag}'"))
+}
+
+#[allow(clippy::struct_excessive_bools)] // one per command-line flag
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub command: Command,
+    pub repo: Option<PathBuf>,
+    pub base: Option<String>,
+    pub format: Format,
+    /// Reports written by `--out`, replacing `format` when there are any.
+    pub out: Vec<Output>,
+    pub grouped: bool,
+    pub match_preset: Preset,
+    pub keywords: Vec<String>,
+    /// Severities given to keywords, later entries winning.
+    pub severities: Vec<(String, Severity)>,
+    /// The least severity of TODOs in paths matching each pattern, from
+    /// `[severity]` in `.todo.toml`.
+    pub path_severities: Vec<(String, Severity)>,
+    /// Paths never scanned, in gitignore syntax.
+    pub exclude: Vec<String>,
+    /// Globs limiting the paths scanned, if any.
+    pub paths: Vec<String>,
+    /// File types scanned, by default all of them.
+    pub types: Vec<String>,
+    /// `NAME:GLOB` definitions for `types`, besides the built-in ones.
+    pub type_defs: Vec<String>,
+    /// Report files and directories with `todo` or `wip` in their names.
+    pub fs_markers: bool,
+    pub case_sensitive: bool,
+    pub require_colon: bool,
+    pub comment_start: bool,
+    pub group_by: GroupBy,
+    /// Heads the group of untagged TODOs in the tree.
+    pub untagged_label: Option<String>,
+    /// Orders TODOs, and groups by their first TODO, instead of the usual
+    /// order.
+    pub sort: Option<Sort>,
+    pub reverse: bool,
+    pub fail_on_new: bool,
+    pub fail_on_severity: Option<Severity>,
+    pub fail_expired: bool,
+    /// How many TODOs may be reported before the run fails.
+    pub limits: Limits,
+    pub all_files: bool,
+    /// Scans the index's changes against `HEAD` instead of the branch's.
+    pub staged: bool,
+    /// Scans the working tree's changes against `HEAD` instead of the
+    /// branch's.
+    pub dirty: bool,
+    /// Files scanned at once, by default one per CPU.
+    pub jobs: Option<usize>,
+    /// Skips the cache under `.git/todo-cache/`.
+    pub no_cache: bool,
+    /// Print how long each phase took and what the caches saved.
+    pub timings: bool,
+    /// Leave the report out of stdout.
+    pub quiet: bool,
+    /// Print the counts and timings as one line of JSON.
+    pub machine: bool,
+    pub whole_files: bool,
+    pub show_branches: bool,
+    pub first_parent: bool,
+    pub merge_attribution: MergeAttribution,
+    pub squash_aware: bool,
+    pub bitbucket: bool,
+    pub dry_run: bool,
+    pub otel_endpoint: Option<String>,
+    pub upload: Option<String>,
+    pub utc: bool,
+    pub precise_ages: bool,
+    pub absolute_after_days: Option<u32>,
+    pub max_age_days: Option<u32>,
+    pub ignore_authors: Vec<String>,
+    pub milestones: Option<PathBuf>,
+    /// Default owners of tags, later entries winning.
+    pub owners: Vec<(String, String)>,
+    /// Organizations of email domains, for `--group-by org`.
+    pub orgs: Vec<(String, String)>,
+    /// The tags `todo lint` accepts, from `.todo.toml`.
+    pub allowed_tags: Vec<String>,
+    /// Paths of tests besides the conventional ones, in gitignore syntax.
+    pub test_paths: Vec<String>,
+    pub prod_only: bool,
+    pub test_only: bool,
+    /// Only report TODOs in these languages.
+    pub languages: Vec<String>,
+    /// Report the TODOs `.todo-baseline` lists too.
+    pub no_baseline: bool,
+    pub team: Option<PathBuf>,
+    pub orphaned: bool,
+    /// Flag TODOs whose referenced issue is resolved.
+    pub check_issues: bool,
+    /// Only report TODOs these people own.
+    pub authors: Vec<String>,
+    /// Only report TODOs the git config's `user.name` or `user.email` own.
+    pub mine: bool,
+    /// Hide secret-looking text in shared reports.
+    pub redact: bool,
+    pub redact_patterns: Vec<String>,
+    pub max_statement_length: Option<usize>,
+}
+
+impl Options {
+    /// Every report of the run: the `--out` ones, or `format` on stdout.
+    #[must_use]
+    pub fn outputs(&self) -> Vec<Output> {
+        if self.out.is_empty() {
+            vec![Output {
+                format: self.format,
+                path: None,
+            }]
+        } else {
+            self.out.clone()
+        }
+    }
+
+    /// What the group of untagged TODOs is called.
+    pub(crate) fn untagged_label(&self) -> &str {
+        self.untagged_label.as_deref().unwrap_or(UNTAGGED)
+    }
+
+    /// Whether any report is JSON, which `grouped` applies to.
+    pub(crate) fn has_json(&self) -> bool {
+        self.outputs()
+            .iter()
+            .any(|output| output.format == Format::Json)
+    }
+
+    /// Adds a keyword given as `WORD` or `WORD=SEVERITY`.
+    pub fn add_keyword(&mut self, spec: &str) -> Result<(), String> {
+        let (keyword, severity) = match spec.split_once('=') {
+            Some((keyword, severity)) => (keyword.trim(), Some(severity.trim().parse()?)),
+            None => (spec.trim(), None),
+        };
+        if keyword.is_empty() {
+            return Err(format!("missing keyword in '{spec}'"));
+        }
+        self.keywords.push(keyword.to_string());
+        if let Some(severity) = severity {
+            self.severities.push((keyword.to_string(), severity));
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn from_env() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("Error: {e}\n\n{USAGE}");
+                exit(2);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_lines)] // one arm per flag
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("a value is required for '{flag}'"))
+            };
+
+            match flag {
+                "-C" | "--repo" => options.repo = Some(value()?.into()),
+                "--base" => options.base = Some(value()?),
+                "--format" => options.format = value()?.parse()?,
+                "--out" => options.out.push(value()?.parse()?),
+                "--grouped" => options.grouped = true,
+                "--match" => options.match_preset = value()?.parse()?,
+                "--keyword" => options.add_keyword(&value()?)?,
+                "--case-sensitive" => options.case_sensitive = true,
+                "--require-colon" => options.require_colon = true,
+                "--comment-start" => options.comment_start = true,
+                "--group-by" => options.group_by = value()?.parse()?,
+                "--untagged-label" => options.untagged_label = Some(value()?),
+                "--sort" => options.sort = Some(value()?.parse()?),
+                "--reverse" => options.reverse = true,
+                "--fail-on-new" => options.fail_on_new = true,
+                "--fail-on-severity" => options.fail_on_severity = Some(value()?.parse()?),
+                "--fail-expired" => options.fail_expired = true,
+                "--max-todos" => options.limits.total = Some(parse_number(flag, &value()?)?),
+                "--max-per-severity" => options.limits.add_severity(flag, &value()?)?,
+                "--max-per-tag" => options.limits.add_tag(flag, &value()?)?,
+                "--max-untagged" => {
+                    options.limits.untagged = Some(parse_number(flag, &value()?)?);
+                }
+                "--all" => options.all_files = true,
+                "--staged" => options.staged = true,
+                "--dirty" => options.dirty = true,
+                "--path" => options.paths.push(value()?),
+                "--exclude" => options.exclude.push(value()?),
+                "-t" | "--type" => options.types.extend(
+                    value()?
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(String::from),
+                ),
+                "--type-add" => options.type_defs.push(value()?),
+                "--fs-markers" => options.fs_markers = true,
+                "-j" | "--jobs" => options.jobs = Some(parse_number(flag, &value()?)?),
+                "--no-cache" => options.no_cache = true,
+                "--timings" => options.timings = true,
+                "-q" | "--quiet" => options.quiet = true,
+                "--machine" => options.machine = true,
+                "--whole-files" => options.whole_files = true,
+                "--show-branches" => options.show_branches = true,
+                "--first-parent" => options.first_parent = true,
+                "--merge-attribution" => options.merge_attribution = value()?.parse()?,
+                "--squash-aware" => options.squash_aware = true,
+                "--bitbucket" => options.bitbucket = true,
+                "--dry-run" => options.dry_run = true,
+                "--otel-endpoint" => options.otel_endpoint = Some(value()?),
+                "--upload" => options.upload = Some(value()?),
+                "--utc" => options.utc = true,
+                "--precise-ages" => options.precise_ages = true,
+                "--absolute-after" => {
+                    options.absolute_after_days = Some(parse_number(flag, &value()?)?);
+                }
+                "--max-age" => options.max_age_days = Some(parse_number(flag, &value()?)?),
+                "--ignore-author" => options.ignore_authors.push(value()?),
+                "--milestones" => options.milestones = Some(value()?.into()),
+                "--owner" => options.owners.push(owners::parse(flag, &value()?)?),
+                "--team" => options.team = Some(value()?.into()),
+                "--orphaned" => options.orphaned = true,
+                "--check-issues" => options.check_issues = true,
+                "--mine" => options.mine = true,
+                "--test-path" => options.test_paths.push(value()?),
+                "--prod-only" => options.prod_only = true,
+                "--test-only" => options.test_only = true,
+                "--language" => options.languages.push(value()?),
+                "--no-baseline" => options.no_baseline = true,
+                "--redact" => options.redact = true,
+                "--redact-pattern" => {
+                    let pattern = value()?;
+                    redact::validate(&pattern)?;
+                    options.redact_patterns.push(pattern);
+                }
+                "--max-statement-length" => {
+                    options.max_statement_length = Some(parse_number(flag, &value()?)?);
+                }
+                "--remote" if matches!(options.command, Command::Branches { .. }) => {
+                    options.command = Command::Branches { remote: true };
+                }
+                "list" if options.command == Command::List => {}
+                "check" if options.command == Command::List => {
+                    options.command = Command::Check;
+                }
+                "branches" if options.command == Command::List => {
+                    options.command = Command::Branches { remote: false };
+                }
+                "rebase-plan" if options.command == Command::List => {
+                    options.command = Command::RebasePlan;
+                }
+                "per-commit" if options.command == Command::List => {
+                    options.command = Command::PerCommit;
+                }
+                "tui" if options.command == Command::List => options.command = Command::Tui,
+                "lint" if options.command == Command::List => options.command = Command::Lint,
+                "baseline" if options.command == Command::List => {
+                    options.command = Command::Baseline;
+                }
+                "write" if options.command == Command::Baseline => {
+                    options.command = Command::BaselineWrite;
+                }
+                "merge" if options.command == Command::Baseline => {
+                    options.command = Command::BaselineMerge { files: Vec::new() };
+                }
+                "done" if options.command == Command::List => {
+                    options.command = Command::Done { target: None };
+                }
+                "stats" if options.command == Command::List => {
+                    options.command = Command::Stats {
+                        push: None,
+                        by_language: false,
+                    };
+                }
+                "hotspots" if options.command == Command::List => {
+                    options.command = Command::Hotspots { top: 20 };
+                }
+                "--top" => match &mut options.command {
+                    Command::Hotspots { top } => *top = parse_number(flag, &value()?)?,
+                    _ => return Err(format!("'{flag}' is only valid for 'hotspots'")),
+                },
+                "serve" if options.command == Command::List => {
+                    options.command = Command::Serve {
+                        bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                        port: 8080,
+                        interval_secs: 300,
+                        allow_origin: None,
+                    };
+                }
+                "--port" => match &mut options.command {
+                    Command::Serve { port, .. } => *port = parse_number(flag, &value()?)?,
+                    _ => return Err(format!("'{flag}' is only valid for 'serve'")),
+                },
+                "--bind" => match &mut options.command {
+                    Command::Serve { bind, .. } => {
+                        let value = value()?;
+                        *bind = value
+                            .parse()
+                            .map_err(|_| format!("invalid address '{value}' for '{flag}'"))?;
+                    }
+                    _ => return Err(format!("'{flag}' is only valid for 'serve'")),
+                },
+                "--allow-origin" => match &mut options.command {
+                    Command::Serve { allow_origin, .. } => *allow_origin = Some(value()?),
+                    _ => return Err(format!("'{flag}' is only valid for 'serve'")),
+                },
+                "--interval" => match &mut options.command {
+                    Command::Serve { interval_secs, .. } => {
+                        *interval_secs = parse_number(flag, &value()?)?;
+                    }
+                    _ => return Err(format!("'{flag}' is only valid for 'serve'")),
+                },
+                "snapshot" if options.command == Command::List => {
+                    options.command = Command::Snapshot(SnapshotAction::Record);
+                }
+                "log" if options.command == Command::Snapshot(SnapshotAction::Record) => {
+                    options.command = Command::Snapshot(SnapshotAction::Log);
+                }
+                "diff" if options.command == Command::Snapshot(SnapshotAction::Record) => {
+                    options.command = Command::Snapshot(SnapshotAction::Diff {
+                        from: None,
+                        to: None,
+                    });
+                }
+                "handover" if options.command == Command::List => {
+                    options.command = Command::Handover {
+                        author: String::new(),
+                    };
+                }
+                "--author" => match &mut options.command {
+                    Command::Handover { author } => *author = value()?,
+                    _ => options.authors.push(value()?),
+                },
+                "rotate" if options.command == Command::List => {
+                    options.command = Command::Rotate {
+                        week: None,
+                        send: false,
+                    };
+                }
+                "--week" => match &mut options.command {
+                    Command::Rotate { week, .. } => *week = Some(value()?.parse()?),
+                    _ => return Err(format!("'{flag}' is only valid for 'rotate'")),
+                },
+                "--send" => match &mut options.command {
+                    Command::Rotate { send, .. } => *send = true,
+                    _ => return Err(format!("'{flag}' is only valid for 'rotate'")),
+                },
+                "init" if options.command == Command::List => {
+                    options.command = Command::Init {
+                        yes: false,
+                        force: false,
+                    };
+                }
+                "-y" | "--yes" => match &mut options.command {
+                    Command::Init { yes, .. } => *yes = true,
+                    _ => return Err(format!("'{flag}' is only valid for 'init'")),
+                },
+                "--force" => match &mut options.command {
+                    Command::Init { force, .. } => *force = true,
+                    _ => return Err(format!("'{flag}' is only valid for 'init'")),
+                },
+                "workspace" if options.command == Command::List => {
+                    options.command = Command::Workspace {
+                        dir: None,
+                        repos: None,
+                    };
+                }
+                "--repos" => match &mut options.command {
+                    Command::Workspace { repos, .. } => *repos = Some(value()?.into()),
+                    _ => return Err(format!("'{flag}' is only valid for 'workspace'")),
+                },
+                "flush" if options.command == Command::List => options.command = Command::Flush,
+                "export" if options.command == Command::List => options.command = Command::Export,
+                "github-issues" if options.command == Command::Export => {
+                    options.command = Command::ExportGithubIssues { ids: Vec::new() };
+                }
+                "auth" if options.command == Command::List => options.command = Command::Auth,
+                "login" if options.command == Command::Auth => {
+                    options.command = Command::AuthLogin { provider: None };
+                }
+                "bench" if options.command == Command::List => {
+                    options.command = Command::Bench(BenchOptions::default());
+                }
+                "--files" | "--lines" | "--density" | "--keep" => {
+                    let Command::Bench(bench) = &mut options.command else {
+                        return Err(format!("'{flag}' is only valid for 'bench'"));
+                    };
+                    match flag {
+                        "--files" => bench.files = parse_number(flag, &value()?)?,
+                        "--lines" => bench.lines = parse_number(flag, &value()?)?,
+                        "--density" => bench.density = parse_number(flag, &value()?)?,
+                        _ => bench.keep = true,
+                    }
+                }
+                "--push" | "--by-language" => {
+                    let Command::Stats { push, by_language } = &mut options.command else {
+                        return Err(format!("'{flag}' is only valid for 'stats'"));
+                    };
+                    if flag == "--push" {
+                        *push = Some(value()?);
+                    } else {
+                        *by_language = true;
+                    }
+                }
+                "-h" | "--help" => {
+                    print!("{USAGE}");
+                    exit(0);
+                }
+                rev if !rev.starts_with('-') => match &mut options.command {
+                    Command::Snapshot(SnapshotAction::Diff {
+                        from: from @ None, ..
+                    }) => {
+                        *from = Some(rev.to_string());
+                    }
+                    Command::Snapshot(SnapshotAction::Diff { to: to @ None, .. }) => {
+                        *to = Some(rev.to_string());
+                    }
+                    Command::Workspace {
+                        dir: dir @ None, ..
+                    } => *dir = Some(rev.into()),
+                    Command::Done {
+                        target: target @ None,
+                    } => *target = Some(rev.to_string()),
+                    Command::BaselineMerge { files } if files.len() < 3 => files.push(rev.into()),
+                    Command::ExportGithubIssues { ids } => ids.push(rev.to_string()),
+                    Command::AuthLogin {
+                        provider: provider @ None,
+                    } => {
+                        *provider = Some(rev.parse()?);
+                    }
+                    _ => return Err(format!("unexpected argument '{rev}'")),
+                },
+                other => return Err(format!("unexpected argument '{other}'")),
+            }
+        }
+
+        if options.grouped && !options.has_json() {
+            return Err("'--grouped' requires '--format json'".to_string());
+        }
+        if options.reverse && options.sort.is_none() {
+            return Err("'--reverse' requires '--sort'".to_string());
+        }
+        if options.machine && !options.quiet {
+            return Err("'--machine' requires '--quiet'".to_string());
+        }
+        if options
+            .out
+            .iter()
+            .filter(|output| output.path.is_none())
+            .count()
+            > 1
+        {
+            return Err("only one '--out' can write to stdout".to_string());
+        }
+        if options.group_by.contains(Grouping::Milestone) && options.milestones.is_none() {
+            return Err("'--group-by milestone' requires '--milestones'".to_string());
+        }
+        if options.staged && options.all_files {
+            return Err("'--staged' and '--all' can't be combined".to_string());
+        }
+        if options.dirty && (options.all_files || options.staged) {
+            let other = if options.staged { "--staged" } else { "--all" };
+            return Err(format!("'--dirty' and '{other}' can't be combined"));
+        }
+        if options.prod_only && options.test_only {
+            return Err("'--prod-only' and '--test-only' can't be combined".to_string());
+        }
+        if options.orphaned && options.team.is_none() {
+            return Err("'--orphaned' requires '--team'".to_string());
+        }
+        match options.command {
+            Command::Auth => return Err("'auth' requires 'login <PROVIDER>'".to_string()),
+            Command::Export => return Err("'export' requires 'github-issues'".to_string()),
+            Command::Baseline => {
+                return Err("'baseline' requires 'write' or 'merge'".to_string());
+            }
+            Command::BaselineMerge { ref files } if !matches!(files.len(), 0 | 3) => {
+                return Err("'baseline merge' takes BASE OURS THEIRS, or no files".to_string());
+            }
+            Command::AuthLogin { provider: None } => {
+                return Err("'auth login' requires a provider".to_string());
+            }
+            _ => {}
+        }
+        if matches!(options.command, Command::Rotate { .. }) && options.team.is_none() {
+            return Err("'rotate' requires '--team'".to_string());
+        }
+        if options.command == (Command::Done { target: None }) {
+            return Err("'done' requires a TODO, as FILE:LINE or an ID".to_string());
+        }
+        if matches!(&options.command, Command::Handover { author } if author.is_empty()) {
+            return Err("'handover' requires '--author'".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn reads_positionals_for_the_command() {
+        assert_eq!(
+            parse("done src/a.rs:3").unwrap().command,
+            Command::Done {
+                target: Some("src/a.rs:3".to_string())
+            }
+        );
+        assert_eq!(
+            parse("snapshot diff v1 v2").unwrap().command,
+            Command::Snapshot(SnapshotAction::Diff {
+                from: Some("v1".to_string()),
+                to: Some("v2".to_string())
+            })
+        );
+        assert_eq!(
+            parse("export github-issues 3f9a 1c2e").unwrap().command,
+            Command::ExportGithubIssues {
+                ids: vec!["3f9a".to_string(), "1c2e".to_string()]
+            }
+        );
+        assert_eq!(
+            parse("list extra").unwrap_err(),
+            "unexpected argument 'extra'"
+        );
+    }
+
+    #[test]
+    fn takes_values_inline_or_from_the_next_argument() {
+        let options = parse("--base=origin/main --max-age 30").unwrap();
+        assert_eq!(options.base.as_deref(), Some("origin/main"));
+        assert_eq!(options.max_age_days, Some(30));
+        assert_eq!(
+            parse("--base").unwrap_err(),
+            "a value is required for '--base'"
+        );
+        assert_eq!(
+            parse("--max-age=soon").unwrap_err(),
+            "invalid number 'soon' for '--max-age'"
+        );
+    }
+
+    #[test]
+    fn repeated_flags_accumulate() {
+        let options = parse(
+            "--keyword FIXME=high --keyword HACK --path src --path=docs -t rust,go --type js",
+        )
+        .unwrap();
+        assert_eq!(options.keywords, ["FIXME", "HACK"]);
+        assert_eq!(options.severities, [("FIXME".to_string(), Severity::High)]);
+        assert_eq!(options.paths, ["src", "docs"]);
+        assert_eq!(options.types, ["rust", "go", "js"]);
+    }
+
+    #[test]
+    fn flags_belong_to_their_command() {
+        let handover = parse("handover --author alice").unwrap();
+        assert_eq!(
+            handover.command,
+            Command::Handover {
+                author: "alice".to_string()
+            }
+        );
+        assert!(handover.authors.is_empty());
+
+        let list = parse("--author alice --author @bob").unwrap();
+        assert_eq!(list.command, Command::List);
+        assert_eq!(list.authors, ["alice", "@bob"]);
+
+        assert_eq!(
+            parse("hotspots --top 5").unwrap().command,
+            Command::Hotspots { top: 5 }
+        );
+        assert_eq!(
+            parse("--port 80").unwrap_err(),
+            "'--port' is only valid for 'serve'"
+        );
+    }
+
+    #[test]
+    fn rejects_flags_that_do_not_go_together() {
+        assert_eq!(
+            parse("--orphaned").unwrap_err(),
+            "'--orphaned' requires '--team'"
+        );
+        assert!(parse("--orphaned --team team.toml").is_ok());
+        assert_eq!(
+            parse("--staged --dirty").unwrap_err(),
+            "'--dirty' and '--staged' can't be combined"
+        );
+        assert_eq!(
+            parse("--all --staged").unwrap_err(),
+            "'--staged' and '--all' can't be combined"
+        );
+        assert_eq!(
+            parse("handover").unwrap_err(),
+            "'handover' requires '--author'"
+        );
+    }
+}
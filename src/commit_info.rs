@@ -0,0 +1,218 @@
+//! Details about the commit a TODO is blamed on, computed once per commit.
+
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+use chrono::{DateTime, Utc};
+use git2::{Commit, Object, Oid, Repository};
+use regex::Regex;
+
+use crate::cli::Options;
+use crate::pull_request::{PullRequest, PullRequests};
+
+#[derive(Debug, Clone)]
+pub struct CommitInfo {
+    pub author: String,
+    pub author_email: String,
+    /// Authors credited through `Co-authored-by:` trailers on the commit.
+    pub co_authors: Vec<String>,
+    pub hash: String,
+    /// The shortest unique prefix of `hash`, at least `core.abbrev` long.
+    pub short_hash: String,
+    pub author_date: DateTime<Utc>,
+    pub title: String,
+    pub is_merge: bool,
+    /// The commit has a GPG or SSH signature that verifies.
+    pub verified: bool,
+    pub pull_request: Option<PullRequest>,
+}
+
+pub struct CommitInfos<'repo> {
+    repo: &'repo Repository,
+    pull_requests: PullRequests<'repo>,
+    cache: HashMap<Oid, Rc<CommitInfo>>,
+}
+
+impl<'repo> CommitInfos<'repo> {
+    pub fn new(repo: &'repo Repository, options: &'repo Options) -> Self {
+        Self {
+            repo,
+            pull_requests: PullRequests::new(repo, options),
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, commit: &Commit<'_>) -> Rc<CommitInfo> {
+        if let Some(info) = self.cache.get(&commit.id()) {
+            return info.clone();
+        }
+
+        let info = Rc::new(CommitInfo {
+            author: commit.author().name().unwrap_or("Unknown").to_string(),
+            author_email: commit.author().email().unwrap_or("").to_string(),
+            co_authors: parse_co_authors(commit.message().unwrap_or("")),
+            hash: commit.id().to_string(),
+            short_hash: short_hash(commit.as_object()),
+            author_date: DateTime::from_timestamp(commit.author().when().seconds(), 0)
+                .unwrap_or_default(),
+            title: commit.summary().unwrap_or("").to_string(),
+            is_merge: commit.parent_count() > 1,
+            verified: is_verified(self.repo, commit.id()),
+            pull_request: self.pull_requests.find(commit),
+        });
+
+        self.cache.insert(commit.id(), info.clone());
+        info
+    }
+}
+
+/// The object's id abbreviated the way git abbreviates it: to `core.abbrev`
+/// characters, or more where that would be ambiguous.
+pub fn short_hash(object: &Object<'_>) -> String {
+    object
+        .short_id()
+        .ok()
+        .and_then(|id| id.as_str().map(str::to_string))
+        .unwrap_or_else(|| object.id().to_string())
+}
+
+/// Whether a commit is signed and `git verify-commit` accepts the signature,
+/// which covers both GPG and SSH signing as configured for the repository.
+fn is_verified(repo: &Repository, oid: Oid) -> bool {
+    // most commits are unsigned, so avoid spawning git for them
+    if repo.extract_signature(&oid, None).is_err() {
+        return false;
+    }
+
+    Command::new("git")
+        .arg("-C")
+        .arg(repo.workdir().unwrap_or_else(|| repo.path()))
+        .args(["verify-commit", &oid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Names from the `Co-authored-by: Name <email>` trailers of a commit message.
+fn parse_co_authors(message: &str) -> Vec<String> {
+    let re = Regex::new(r"(?im)^co-authored-by:\s*(.+?)\s*(?:<[^>]*>)?\s*$").unwrap();
+    re.captures_iter(message)
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    /// A commit of `HEAD`'s tree on top of it, with the signature `sign` makes.
+    fn signed_commit(test: &TestRepo, message: &str, sign: impl Fn(&str) -> String) -> Oid {
+        let head = test.repo.head().unwrap().peel_to_commit().unwrap();
+        let buffer = test
+            .repo
+            .commit_create_buffer(
+                &head.author(),
+                &head.committer(),
+                message,
+                &head.tree().unwrap(),
+                &[&head],
+            )
+            .unwrap();
+        let buffer = buffer.as_str().unwrap();
+        test.repo
+            .commit_signed(buffer, &sign(buffer), None)
+            .unwrap()
+    }
+
+    #[test]
+    fn verifies_ssh_signatures() {
+        let test = TestRepo::new("verified");
+        test.write("a.rs", "// TODO: a\n");
+        let unsigned = test.repo.find_commit(test.commit("Alice", "a")).unwrap();
+
+        let key = test.dir.join(".git/key");
+        let generated = Command::new("ssh-keygen")
+            .args([
+                "-q",
+                "-t",
+                "ed25519",
+                "-N",
+                "",
+                "-C",
+                "alice@example.com",
+                "-f",
+            ])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(generated.success());
+        let public = fs::read_to_string(key.with_extension("pub")).unwrap();
+        let allowed = test.dir.join(".git/allowed_signers");
+        fs::write(&allowed, format!("alice@example.com {public}")).unwrap();
+        let mut config = test.repo.config().unwrap();
+        config.set_str("gpg.format", "ssh").unwrap();
+        config
+            .set_str("gpg.ssh.allowedSignersFile", allowed.to_str().unwrap())
+            .unwrap();
+
+        let sign = |buffer: &str| {
+            let mut child = Command::new("ssh-keygen")
+                .args(["-Y", "sign", "-n", "git", "-f"])
+                .arg(&key)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(buffer.as_bytes())
+                .unwrap();
+            String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+        };
+        let signed = signed_commit(&test, "signed", sign);
+        // a valid signature, but of other contents
+        let forged = signed_commit(&test, "forged", |_| sign("other contents"));
+
+        assert!(!is_verified(&test.repo, unsigned.id()));
+        assert!(is_verified(&test.repo, signed));
+        assert!(!is_verified(&test.repo, forged));
+        let options = Options::default();
+        let mut infos = CommitInfos::new(&test.repo, &options);
+        assert!(infos.get(&test.repo.find_commit(signed).unwrap()).verified);
+    }
+
+    #[test]
+    fn abbreviates_hashes_like_git() {
+        let test = TestRepo::new("abbrev");
+        test.write("a.rs", "// TODO: committed\n");
+        let commit = test.commit("Alice", "a");
+        test.write("b.rs", "// TODO: not yet\n");
+        let short = || short_hash(&test.repo.find_object(commit, None).unwrap());
+        assert_eq!(short(), commit.to_string()[..7]);
+        test.repo
+            .config()
+            .unwrap()
+            .set_i32("core.abbrev", 12)
+            .unwrap();
+        assert_eq!(short(), commit.to_string()[..12]);
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (mut todos, _) = crate::get_todos(&test.repo, &options);
+        todos.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        let hashes: Vec<_> = todos.iter().map(|todo| todo.short_hash.as_str()).collect();
+        assert_eq!(hashes, [&commit.to_string()[..12], "(uncommitted)"]);
+    }
+}
@@ -0,0 +1,393 @@
+//! Settings shared through files instead of flags: `.todo.toml` at the
+//! repository root, falling back to `config.toml` in the user's configuration
+//! directory (`~/.config/todo/`), and `.todoignore`, with gitignore syntax,
+//! for paths that are never scanned. Flags given on the command line win,
+//! then the repository's file, then the user's.
+//!
+//! ```toml
+//! base = "origin/main"
+//! match = "strict"
+//! exclude = ["third_party/**", "*.pb.go"]
+//! types = ["rs", "proto"]
+//! type-add = ["proto:*.proto"]
+//! fs-markers = true
+//! format = "json"
+//! group-by = "milestone"  # or a list, like "file,tag"
+//! milestones = "milestones.txt"  # relative to this file
+//! allowed-tags = ["perf", "security", "ui"]  # for `todo lint`
+//! test-paths = ["qa/**"]  # tests besides tests/, *_test.go and the like
+//! untagged-label = "no tag"
+//! grouped = true
+//! redact = true
+//! redact-patterns = ['INC-\d+']
+//! max-statement-length = 120
+//!
+//! # limits on what `todo check` (or any run) reports
+//! [check]
+//! max-todos = 50
+//! max-per-severity = { high = 0 }
+//! max-per-tag = { perf = 3 }
+//! max-untagged = 0
+//!
+//! # default owners of TODOs with a tag and no `@assignee`
+//! [owners]
+//! perf = "@perf-team"
+//!
+//! # organizations of email domains and their subdomains, for `--group-by org`
+//! [orgs]
+//! "google.com" = "Google"
+//! "users.noreply.github.com" = "Community"
+//!
+//! # the least severity of TODOs in these paths, whatever their keyword
+//! [severity]
+//! "src/security/**" = "high"
+//!
+//! # keywords matched besides TODO, with their severity; a list such as
+//! # `keywords = ["FIXME", "HACK=medium"]` works too
+//! [keywords]
+//! FIXME = "high"
+//! HACK = "medium"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::Match;
+use serde::Deserialize;
+
+use crate::cli::{Command, Format, GroupBy, Grouping, Options};
+use crate::file_types;
+use crate::limits::Limits;
+use crate::matcher::Preset;
+use crate::redact;
+use crate::severity;
+use crate::test_code;
+
+pub const FILE: &str = ".todo.toml";
+pub const IGNORE_FILE: &str = ".todoignore";
+
+/// The user-level fallback, in [`user_dir`].
+const USER_FILE: &str = "config.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    base: Option<String>,
+    #[serde(default)]
+    keywords: Keywords,
+    #[serde(rename = "match")]
+    match_preset: Option<String>,
+    /// Paths never scanned, in gitignore syntax like `.todoignore`.
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Scanned file types, unless `--type` selects some.
+    #[serde(default)]
+    types: Vec<String>,
+    #[serde(default)]
+    type_add: Vec<String>,
+    fs_markers: Option<bool>,
+    format: Option<String>,
+    group_by: Option<String>,
+    untagged_label: Option<String>,
+    /// Relative to the file's directory.
+    milestones: Option<PathBuf>,
+    #[serde(default)]
+    owners: BTreeMap<String, String>,
+    #[serde(default)]
+    orgs: BTreeMap<String, String>,
+    #[serde(default)]
+    allowed_tags: Vec<String>,
+    #[serde(default)]
+    test_paths: Vec<String>,
+    /// Path patterns and the least severity of the TODOs they match.
+    #[serde(default)]
+    severity: BTreeMap<String, String>,
+    grouped: Option<bool>,
+    redact: Option<bool>,
+    #[serde(default)]
+    redact_patterns: Vec<String>,
+    max_statement_length: Option<usize>,
+    #[serde(default)]
+    check: Check,
+}
+
+/// The `[check]` table: limits on what a run may report.
+#[allow(clippy::struct_field_names)] // named like their flags
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Check {
+    max_todos: Option<usize>,
+    #[serde(default)]
+    max_per_severity: BTreeMap<String, usize>,
+    #[serde(default)]
+    max_per_tag: BTreeMap<String, usize>,
+    max_untagged: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Keywords {
+    /// `WORD` or `WORD=SEVERITY` entries.
+    List(Vec<String>),
+    Severities(BTreeMap<String, String>),
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        Self::List(Vec::new())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/todo`, by default under `~/.config`, or `%APPDATA%\todo`
+/// on Windows.
+pub fn user_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    let config = if cfg!(windows) {
+        PathBuf::from(var("APPDATA")?)
+    } else {
+        var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?
+    };
+    Some(config.join(env!("CARGO_PKG_NAME")))
+}
+
+impl Config {
+    /// Reads the file at `path`, if there is one.
+    fn load(path: &Path) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        toml::from_str(&contents).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e.message()),
+            )
+        })
+    }
+
+    /// Fills in what `options` leave unset, for a file in `dir`. Keywords add
+    /// to theirs, with severities already in `options` taking precedence.
+    fn apply(self, dir: &Path, options: &mut Options) -> Result<(), String> {
+        if options.base.is_none() {
+            options.base = self.base;
+        }
+
+        let mut file = Options::default();
+        match self.keywords {
+            Keywords::List(keywords) => {
+                for keyword in keywords {
+                    file.add_keyword(&keyword)?;
+                }
+            }
+            Keywords::Severities(keywords) => {
+                for (keyword, severity) in keywords {
+                    file.add_keyword(&format!("{keyword}={severity}"))?;
+                }
+            }
+        }
+        options.keywords.extend(file.keywords);
+        // later severities win, so the ones already set go last
+        file.severities.append(&mut options.severities);
+        options.severities = file.severities;
+
+        if let Some(preset) = self.match_preset {
+            if options.match_preset == Preset::default() {
+                options.match_preset = preset.parse()?;
+            }
+        }
+        options.exclude.extend(self.exclude);
+        if options.types.is_empty() {
+            options.types = self.types;
+        }
+        options.type_defs.extend(self.type_add);
+        options.fs_markers |= self.fs_markers.unwrap_or_default();
+        if let Some(format) = self.format {
+            if options.format == Format::default() {
+                options.format = format.parse()?;
+            }
+        }
+        if let Some(group_by) = self.group_by {
+            if options.group_by == GroupBy::default() {
+                options.group_by = group_by.parse()?;
+            }
+        }
+        if options.untagged_label.is_none() {
+            options.untagged_label = self.untagged_label;
+        }
+        if options.milestones.is_none() {
+            options.milestones = self.milestones.map(|path| dir.join(path));
+        }
+        // later owners win, so the ones already set go last
+        let mut owners: Vec<_> = self.owners.into_iter().collect();
+        owners.append(&mut options.owners);
+        options.owners = owners;
+        // the repository's file is applied first, so its mappings go last
+        let mut orgs: Vec<_> = self.orgs.into_iter().collect();
+        orgs.append(&mut options.orgs);
+        options.orgs = orgs;
+        if options.allowed_tags.is_empty() {
+            options.allowed_tags = self.allowed_tags;
+        }
+        options.test_paths.extend(self.test_paths);
+        for (pattern, severity) in self.severity {
+            options.path_severities.push((pattern, severity.parse()?));
+        }
+        options.grouped |= self.grouped.unwrap_or_default();
+        options.redact |= self.redact.unwrap_or_default();
+        for pattern in self.redact_patterns {
+            redact::validate(&pattern)?;
+            options.redact_patterns.push(pattern);
+        }
+        if options.max_statement_length.is_none() {
+            options.max_statement_length = self.max_statement_length;
+        }
+
+        let mut limits = Limits {
+            total: self.check.max_todos,
+            tags: self.check.max_per_tag.into_iter().collect(),
+            untagged: self.check.max_untagged,
+            ..Limits::default()
+        };
+        for (severity, limit) in self.check.max_per_severity {
+            limits.severities.push((severity.parse()?, limit));
+        }
+        options.limits.fill(limits);
+        Ok(())
+    }
+}
+
+/// `options` with the `.todo.toml` in `root`, then the user's configuration
+/// file, filling in what they leave unset.
+pub fn options(root: &Path, options: &Options) -> Result<Options, String> {
+    let mut options = options.clone();
+    Config::load(&root.join(FILE))
+        .map_err(|e| e.to_string())?
+        .apply(root, &mut options)?;
+    if let Some(dir) = user_dir() {
+        Config::load(&dir.join(USER_FILE))
+            .map_err(|e| e.to_string())?
+            .apply(&dir, &mut options)?;
+    }
+
+    if options.grouped && !options.has_json() {
+        return Err("'grouped' requires format json".to_string());
+    }
+    if options.group_by.contains(Grouping::Milestone) && options.milestones.is_none() {
+        return Err("grouping by milestone requires milestones".to_string());
+    }
+    if options.command == Command::Lint && options.allowed_tags.is_empty() {
+        return Err(format!("'lint' requires allowed-tags in {FILE}"));
+    }
+    file_types::types(&options)?;
+    test_code::Classifier::new(root, &options.test_paths)?;
+    severity::PathRules::new(root, &options.path_severities)?;
+    path_globs(root, &options.paths)?;
+    Ok(options)
+}
+
+/// The `--path` globs, relative to `root`, matching everything without any.
+fn path_globs(root: &Path, globs: &[String]) -> Result<Override, String> {
+    let mut builder = OverrideBuilder::new(root);
+    for glob in globs {
+        builder
+            .add(glob)
+            .map_err(|e| format!("invalid '--path {glob}': {e}"))?;
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Whether `path`, or a directory it is in, matches one of the `--path`
+/// globs, if there are any.
+fn within(paths: &Override, path: &Path) -> bool {
+    paths.num_whitelists() == 0
+        || path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| paths.matched(ancestor, ancestor != path).is_whitelist())
+}
+
+/// The paths scans leave out: those outside the `--path` globs, those
+/// `.todoignore` and the configured `exclude` patterns match, then those git
+/// ignores through `.gitignore`,
+/// `.git/info/exclude` or `core.excludesFile`, even when tracked, as vendored
+/// and generated code often is. A `!` pattern in `.todoignore` brings back a
+/// path git ignores.
+pub struct Ignored<'repo> {
+    repo: &'repo Repository,
+    paths: Override,
+    patterns: Gitignore,
+}
+
+impl<'repo> Ignored<'repo> {
+    pub fn new(repo: &'repo Repository, options: &Options) -> Self {
+        let root = repo.workdir().unwrap();
+        let mut builder = GitignoreBuilder::new(root);
+        // a missing file is no error; a malformed one still ignores its valid
+        // lines
+        let _ = builder.add(root.join(IGNORE_FILE));
+        for pattern in &options.exclude {
+            let _ = builder.add_line(None, pattern);
+        }
+        Self {
+            repo,
+            // checked when the options were read
+            paths: path_globs(root, &options.paths).unwrap_or_else(|_| Override::empty()),
+            patterns: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+        }
+    }
+
+    /// Whether `path`, relative to the repository root, is left out.
+    pub fn matches(&self, path: &Path) -> bool {
+        if !within(&self.paths, path) {
+            return true;
+        }
+        match self.patterns.matched_path_or_any_parents(path, false) {
+            Match::Ignore(_) => true,
+            Match::Whitelist(_) => false,
+            Match::None => self.repo.is_path_ignored(path).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todoignore_overrides_gitignore() {
+        let dir = std::env::temp_dir().join(format!("todo-ignored-{}", std::process::id()));
+        let repo = Repository::init(&dir).unwrap();
+        fs::write(dir.join(".gitignore"), "vendor/\n").unwrap();
+        fs::write(dir.join(IGNORE_FILE), "*.gen.rs\n!vendor/patched.rs\n").unwrap();
+
+        let ignored = Ignored::new(&repo, &Options::default());
+        let matches = |path: &str| ignored.matches(Path::new(path));
+        assert!(matches("vendor/lib.rs"));
+        assert!(!matches("vendor/patched.rs"));
+        assert!(matches("src/api.gen.rs"));
+        assert!(!matches("src/main.rs"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_globs_limit_scans() {
+        let root = Path::new("/repo");
+        let paths = path_globs(root, &["src/**".to_string(), "docs".to_string()]).unwrap();
+        assert!(within(&paths, Path::new("src/a/b.rs")));
+        assert!(within(&paths, Path::new("docs/guide.md")));
+        assert!(!within(&paths, Path::new("tests/t.rs")));
+        assert!(within(
+            &path_globs(root, &[]).unwrap(),
+            Path::new("tests/t.rs")
+        ));
+        assert!(path_globs(root, &["src/{a".to_string()]).is_err());
+    }
+}
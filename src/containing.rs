@@ -0,0 +1,75 @@
+//! Which branches contain the commit each TODO is blamed on, with the
+//! semantics of `git branch --contains`.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use git2::{BranchType, Oid, Repository};
+
+use crate::Todo;
+
+pub fn annotate(repo: &Repository, todos: &mut [Todo]) -> Result<(), git2::Error> {
+    let mut tips = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        if let (Some(name), Some(tip)) = (branch.name()?, branch.get().target()) {
+            tips.push((name.to_string(), tip));
+        }
+    }
+    tips.sort();
+
+    let mut cache: HashMap<Oid, Vec<String>> = HashMap::new();
+    for todo in todos {
+        // uncommitted lines are on no branch yet
+        let Ok(commit) = Oid::from_str(&todo.commit_hash) else {
+            continue;
+        };
+
+        if let Entry::Vacant(entry) = cache.entry(commit) {
+            let mut containing = Vec::new();
+            for (name, tip) in &tips {
+                if *tip == commit || repo.graph_descendant_of(*tip, commit)? {
+                    containing.push(name.clone());
+                }
+            }
+            entry.insert(containing);
+        }
+        todo.branches.clone_from(&cache[&commit]);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn lists_the_branches_containing_each_todo() {
+        let test = TestRepo::new("containing");
+        test.write("a.rs", "// TODO: shared\n");
+        test.commit("Alice", "a");
+        test.branch("release");
+        test.checkout("main");
+        test.branch("feature");
+        test.write("b.rs", "// TODO: feature only\n");
+        test.commit("Bob", "b");
+        test.write("c.rs", "// TODO: uncommitted\n");
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (mut todos, _) = crate::get_todos(&test.repo, &options);
+        todos.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        annotate(&test.repo, &mut todos).unwrap();
+        let branches: Vec<_> = todos.iter().map(|todo| todo.branches.clone()).collect();
+        assert_eq!(
+            branches,
+            [vec!["feature", "main", "release"], vec!["feature"], vec![]]
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! Tokens for the services integrations talk to, looked up in the
+//! environment, then the OS keychain, then a private credentials file, so they
+//! never need to be written into project files.
+//!
+//! The keychain is reached through the system's own tools, `security` on
+//! macOS and `secret-tool` (libsecret) on Linux. Elsewhere, or when those are
+//! missing, `todo auth login` falls back to the credentials file.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dry_run;
+
+/// The keychain service credentials are stored under.
+const SERVICE: &str = env!("CARGO_PKG_NAME");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// Bitbucket Cloud, for Code Insights reports.
+    Bitbucket,
+    /// GitHub, for `export github-issues` and `--check-issues`.
+    Github,
+    /// `InfluxDB` 2, for `stats --push`.
+    Influx,
+    /// Jira, for `--check-issues`.
+    Jira,
+    /// The artifact store `--upload` sends reports to.
+    Upload,
+}
+
+impl Provider {
+    const ALL: [Self; 5] = [
+        Self::Bitbucket,
+        Self::Github,
+        Self::Influx,
+        Self::Jira,
+        Self::Upload,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Bitbucket => "bitbucket",
+            Self::Github => "github",
+            Self::Influx => "influx",
+            Self::Jira => "jira",
+            Self::Upload => "upload",
+        }
+    }
+
+    /// The environment variable that overrides stored credentials.
+    const fn env_var(self) -> &'static str {
+        match self {
+            Self::Bitbucket => "BITBUCKET_TOKEN",
+            Self::Github => "GITHUB_TOKEN",
+            Self::Influx => "INFLUX_TOKEN",
+            Self::Jira => "JIRA_TOKEN",
+            Self::Upload => "TODO_UPLOAD_TOKEN",
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|provider| provider.name() == s)
+            .ok_or_else(|| format!("unknown provider '{s}'"))
+    }
+}
+
+/// The token for `provider`, from `$<PROVIDER>_TOKEN`, the keychain or the
+/// credentials file, in that order.
+pub fn token(provider: Provider) -> Option<String> {
+    lookup(provider, keychain::get, file::path().as_deref())
+}
+
+/// Like [`token`], with the keychain and the credentials file to look in.
+fn lookup(
+    provider: Provider,
+    keychain: impl FnOnce(Provider) -> Option<String>,
+    file: Option<&Path>,
+) -> Option<String> {
+    if let Ok(token) = std::env::var(provider.env_var()) {
+        return Some(token);
+    }
+    if let Some(token) = keychain(provider) {
+        return Some(token);
+    }
+    match file::get(file?, provider) {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Error reading credentials file: {e}");
+            None
+        }
+    }
+}
+
+/// `todo auth login`: prompts for a token and stores it in the keychain, or
+/// the credentials file when no keychain is available.
+pub fn login(provider: Provider) -> io::Result<()> {
+    if dry_run::enabled() {
+        let file = file::path().map_or_else(
+            || "a credentials file".to_string(),
+            |path| path.display().to_string(),
+        );
+        eprintln!(
+            "[dry run] would store a {provider} token in the system keychain, or {file} without one"
+        );
+        return Ok(());
+    }
+    let token = prompt(&format!("Token for {provider}: "))?;
+    if token.is_empty() {
+        return Err(io::Error::other("no token given"));
+    }
+
+    if keychain::set(provider, &token) {
+        eprintln!("Stored the {provider} token in the system keychain");
+    } else {
+        let path = file::path().ok_or_else(|| io::Error::other("no home directory"))?;
+        file::set(&path, provider, &token)?;
+        eprintln!("Stored the {provider} token in {}", path.display());
+    }
+    Ok(())
+}
+
+/// Reads a line from stdin without echoing it when stdin is a terminal.
+fn prompt(message: &str) -> io::Result<String> {
+    eprint!("{message}");
+    io::stderr().flush()?;
+
+    let echo_off = cfg!(unix) && set_echo(false);
+    let mut line = String::new();
+    let result = io::stdin().lock().read_line(&mut line);
+    if echo_off {
+        set_echo(true);
+        eprintln!();
+    }
+    result?;
+
+    Ok(line.trim().to_string())
+}
+
+/// Turns terminal echo on or off, returning whether that worked.
+fn set_echo(on: bool) -> bool {
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+mod keychain {
+    use super::{Provider, SERVICE};
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    pub fn get(provider: Provider) -> Option<String> {
+        let mut command = if cfg!(target_os = "macos") {
+            let mut command = Command::new("security");
+            command.args([
+                "find-generic-password",
+                "-s",
+                SERVICE,
+                "-a",
+                provider.name(),
+                "-w",
+            ]);
+            command
+        } else if cfg!(unix) {
+            let mut command = Command::new("secret-tool");
+            command.args(["lookup", "service", SERVICE, "account", provider.name()]);
+            command
+        } else {
+            return None;
+        };
+
+        let output = command.stderr(Stdio::null()).output().ok()?;
+        let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        (output.status.success() && !token.is_empty()).then_some(token)
+    }
+
+    /// Stores the token, returning whether a keychain took it.
+    pub fn set(provider: Provider, token: &str) -> bool {
+        let label = format!("{SERVICE} {provider} token");
+        // the token goes through stdin, never the command line, which other
+        // local users can see in the process list
+        let (mut command, stdin) = if cfg!(target_os = "macos") {
+            let mut command = Command::new("security");
+            command.args(["add-generic-password", "-U", "-s", SERVICE, "-a"]);
+            // a trailing -w prompts for the token, then again to confirm it
+            command.args([provider.name(), "-l", &label, "-w"]);
+            (command, format!("{token}\n{token}\n"))
+        } else if cfg!(unix) {
+            let mut command = Command::new("secret-tool");
+            command.args(["store", "--label", &label]);
+            command.args(["service", SERVICE, "account", provider.name()]);
+            (command, token.to_string())
+        } else {
+            return false;
+        };
+
+        let Ok(mut child) = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+        child.wait().is_ok_and(|status| status.success())
+    }
+}
+
+mod file {
+    use super::{fs, io, OpenOptions, Path, PathBuf, Provider, Write};
+
+    /// `credentials` in the user's configuration directory.
+    pub fn path() -> Option<PathBuf> {
+        Some(crate::config::user_dir()?.join("credentials"))
+    }
+
+    /// Entries of the file, one `provider = token` per line.
+    fn read(path: &Path) -> io::Result<Vec<(String, String)>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, token)| (name.trim().to_string(), token.trim().to_string()))
+            .collect())
+    }
+
+    pub fn get(path: &Path, provider: Provider) -> io::Result<Option<String>> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Ok(metadata) = fs::metadata(path) {
+                // like ssh, ignore secrets that other users can read
+                if metadata.permissions().mode() & 0o077 != 0 {
+                    return Err(io::Error::other(format!(
+                        "{} is accessible by other users; run `chmod 600` on it",
+                        path.display()
+                    )));
+                }
+            }
+        }
+
+        Ok(read(path)?
+            .into_iter()
+            .find(|(name, _)| name == provider.name())
+            .map(|(_, token)| token))
+    }
+
+    /// Stores the token, replacing any earlier one for the provider.
+    pub fn set(path: &Path, provider: Provider, token: &str) -> io::Result<()> {
+        let mut entries = read(path)?;
+        entries.retain(|(name, _)| name != provider.name());
+        entries.push((provider.name().to_string(), token.to_string()));
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // tighten files created before, since `mode` only applies to new ones
+            if path.exists() {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut file = options.open(path)?;
+        for (name, token) in entries {
+            writeln!(file, "{name} = {token}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn looks_in_the_environment_then_the_keychain_then_the_file() {
+        let test = TestRepo::new("credentials");
+        let path = test.dir.join("config").join("credentials");
+        file::set(&path, Provider::Jira, "old").unwrap();
+        file::set(&path, Provider::Github, "github").unwrap();
+        file::set(&path, Provider::Jira, "from-file").unwrap();
+
+        let token = |keychain: Option<&str>| {
+            lookup(Provider::Jira, |_| keychain.map(String::from), Some(&path))
+        };
+        std::env::remove_var(Provider::Jira.env_var());
+        assert_eq!(token(None).as_deref(), Some("from-file"));
+        assert_eq!(
+            token(Some("from-keychain")).as_deref(),
+            Some("from-keychain")
+        );
+        std::env::set_var(Provider::Jira.env_var(), "from-env");
+        assert_eq!(token(Some("from-keychain")).as_deref(), Some("from-env"));
+        std::env::remove_var(Provider::Jira.env_var());
+        assert_eq!(lookup(Provider::Jira, |_| None, None), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_the_file_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let test = TestRepo::new("credentials-mode");
+        let path = test.dir.join("credentials");
+        let mode = || fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        file::set(&path, Provider::Jira, "secret").unwrap();
+        assert_eq!(mode(), 0o600);
+
+        // like ssh, a file others can read is refused until it is fixed
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let error = file::get(&path, Provider::Jira).unwrap_err();
+        assert!(error.to_string().contains("chmod 600"), "{error}");
+        file::set(&path, Provider::Jira, "rotated").unwrap();
+        assert_eq!(mode(), 0o600);
+        assert_eq!(
+            file::get(&path, Provider::Jira).unwrap().as_deref(),
+            Some("rotated")
+        );
+    }
+}
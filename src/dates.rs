@@ -0,0 +1,160 @@
+//! Date rendering. Ages are all measured from one instant taken when the run
+//! starts, and absolute dates are shown in the viewer's time zone unless
+//! `--utc` asks for UTC.
+
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeDelta, Utc, Weekday};
+use chrono_humanize::HumanTime;
+
+use crate::cli::Options;
+
+static NOW: OnceLock<DateTime<Utc>> = OnceLock::new();
+static SETTINGS: RwLock<Settings> = RwLock::new(Settings {
+    utc: false,
+    precise: false,
+    horizon: None,
+});
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    utc: bool,
+    /// Count exact days rather than rounding to weeks, months and years.
+    precise: bool,
+    /// Ages beyond this are shown as dates instead.
+    horizon: Option<TimeDelta>,
+}
+
+impl Settings {
+    fn get() -> Self {
+        *SETTINGS.read().unwrap()
+    }
+}
+
+/// Applies the date options until they are configured again.
+pub fn configure(options: &Options) {
+    let settings = Settings {
+        utc: options.utc,
+        precise: options.precise_ages,
+        horizon: options
+            .absolute_after_days
+            .map(|days| TimeDelta::days(i64::from(days))),
+    };
+    *SETTINGS.write().unwrap() = settings;
+}
+
+/// The instant ages are measured from, fixed for the whole run so that
+/// TODOs from the same commit never show different ages.
+pub fn now() -> DateTime<Utc> {
+    *NOW.get_or_init(Utc::now)
+}
+
+/// How long before [`now`] the date was, e.g. "3 weeks ago" or, with
+/// `--precise-ages`, "23 days ago". Dates older than `--absolute-after` are
+/// shown as "on 2024-05-01" instead.
+pub fn ago(date: DateTime<Utc>) -> String {
+    let settings = Settings::get();
+    let age = date - now();
+
+    if settings.horizon.is_some_and(|horizon| -age > horizon) {
+        return format!("on {}", to_local(date).format("%Y-%m-%d"));
+    }
+
+    if settings.precise {
+        precise(-age)
+    } else {
+        HumanTime::from(age).to_string()
+    }
+}
+
+/// The age in its largest whole unit up to days, e.g. "23 days ago".
+fn precise(age: TimeDelta) -> String {
+    let (count, unit) = if age.num_days() > 0 {
+        (age.num_days(), "day")
+    } else if age.num_hours() > 0 {
+        (age.num_hours(), "hour")
+    } else if age.num_minutes() > 0 {
+        (age.num_minutes(), "minute")
+    } else {
+        return "now".to_string();
+    };
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+fn to_local(date: DateTime<Utc>) -> DateTime<chrono::FixedOffset> {
+    if Settings::get().utc {
+        date.fixed_offset()
+    } else {
+        date.with_timezone(&Local).fixed_offset()
+    }
+}
+
+/// The day of [`now`], in local time or UTC.
+pub fn today() -> NaiveDate {
+    to_local(now()).date_naive()
+}
+
+/// The date as `2024-05-01 14:03`, in local time or UTC.
+pub fn absolute(date: DateTime<Utc>) -> String {
+    let suffix = if Settings::get().utc { " UTC" } else { "" };
+    format!("{}{suffix}", to_local(date).format("%Y-%m-%d %H:%M"))
+}
+
+/// A week or month TODOs are grouped into, in local time or UTC.
+pub struct Period {
+    pub start: NaiveDate,
+    /// For machines, e.g. `2024-W18` or `2024-05`.
+    pub key: String,
+    /// For people, e.g. `Week of 2024-04-29` or `May 2024`.
+    pub title: String,
+}
+
+/// The ISO week, starting on Monday, that `date` falls in.
+pub fn week(date: DateTime<Utc>) -> Period {
+    let week = to_local(date).iso_week();
+    let start = NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon).unwrap();
+    Period {
+        start,
+        key: format!("{}-W{:02}", week.year(), week.week()),
+        title: format!("Week of {}", start.format("%Y-%m-%d")),
+    }
+}
+
+/// The calendar month that `date` falls in.
+pub fn month(date: DateTime<Utc>) -> Period {
+    let start = to_local(date).date_naive().with_day(1).unwrap();
+    Period {
+        start,
+        key: start.format("%Y-%m").to_string(),
+        title: start.format("%B %Y").to_string(),
+    }
+}
+
+/// The date in RFC 3339, with the local offset or `Z`.
+pub fn rfc3339(date: DateTime<Utc>) -> String {
+    let utc = Settings::get().utc;
+    to_local(date).to_rfc3339_opts(chrono::SecondsFormat::Secs, utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periods_follow_iso_weeks_and_months() {
+        // noon local time, the same day in any zone the test runs in
+        let date = NaiveDate::from_ymd_opt(2024, 12, 31)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .to_utc();
+        let week = week(date);
+        assert_eq!(week.key, "2025-W01");
+        assert_eq!(week.title, "Week of 2024-12-30");
+        let month = month(date);
+        assert_eq!(month.key, "2024-12");
+        assert_eq!(month.title, "December 2024");
+    }
+}
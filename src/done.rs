@@ -0,0 +1,341 @@
+//! `todo done <FILE:LINE|ID>`: removing a finished TODO from its file. A comment
+//! that is only the TODO goes, with its line when nothing else is on it and
+//! with the indented comment lines continuing it; a TODO that follows other
+//! text in its comment is cut from the comment, which stays.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use git2::Repository;
+use regex::Regex;
+
+use crate::cli::Options;
+use crate::matcher::{Matcher, COMMENT_OPENERS};
+use crate::{ids, repo_relative_path, scan, Todo, COMMENT_TERMINATORS};
+
+/// The lines replacing `removed`, starting at the 0-based `start`.
+#[derive(Debug, PartialEq, Eq)]
+struct Edit {
+    start: usize,
+    removed: Vec<String>,
+    added: Vec<String>,
+}
+
+/// Splits a `FILE:LINE` target.
+fn parse_target(target: &str) -> Result<(PathBuf, usize), String> {
+    let invalid = || format!("invalid TODO '{target}', expected FILE:LINE or an ID");
+    let (path, line) = target.rsplit_once(':').ok_or_else(invalid)?;
+    let line: usize = line.parse().map_err(|_| invalid())?;
+    if path.is_empty() || line == 0 {
+        return Err(invalid());
+    }
+    Ok((PathBuf::from(path), line))
+}
+
+/// `path` relative to the current directory, as the tree shows it, or else to
+/// the repository root.
+fn resolve(repo: &Repository, path: &Path) -> PathBuf {
+    if path.exists() {
+        return path.to_path_buf();
+    }
+    repo.workdir()
+        .map(|root| root.join(path))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// The TODO at `path` and `line` in `todos`.
+fn find_at<'a>(todos: &'a [Todo], path: &Path, line: usize) -> Option<&'a Todo> {
+    let path = path.canonicalize().ok()?;
+    todos.iter().find(|todo| {
+        todo.line == line && todo.file_path.canonicalize().is_ok_and(|file| file == path)
+    })
+}
+
+/// The TODO `target` names, as `FILE:LINE` of the report the options make,
+/// or of any TODO in the working tree, or by its ID or the start of it,
+/// which is looked up among every TODO in the working tree.
+fn locate(repo: &Repository, options: &Options, target: &str) -> Result<Todo, String> {
+    let options = Options {
+        no_baseline: true,
+        ..options.clone()
+    };
+    let all_files = Options {
+        all_files: true,
+        ..options.clone()
+    };
+
+    if !ids::is_id(target) {
+        let (path, line) = parse_target(target)?;
+        let path = resolve(repo, &path);
+        return find_at(&scan(repo, &options), &path, line)
+            .cloned()
+            .or_else(|| find_at(&scan(repo, &all_files), &path, line).cloned())
+            .ok_or_else(|| format!("no TODO is reported at {target}"));
+    }
+    let todos = scan(repo, &all_files);
+    let found: Vec<_> = todos
+        .iter()
+        .filter(|todo| todo.id.starts_with(target))
+        .collect();
+    match found.as_slice() {
+        [todo] => Ok((*todo).clone()),
+        [] => Err(format!("no TODO has the ID '{target}'")),
+        _ => Err(format!(
+            "the IDs of {} TODOs start with '{target}'",
+            found.len()
+        )),
+    }
+}
+
+/// Where the comment holding the keyword at `keyword` opens: the last opener
+/// before it, where `*` only counts at the start of the line, as in the
+/// middle of a block comment, and `'` never does, being more often a quote.
+fn comment_start(line: &str, keyword: usize) -> Option<(usize, usize)> {
+    let openers = Regex::new(COMMENT_OPENERS).unwrap();
+    openers
+        .find_iter(&line[..keyword])
+        .filter(|opener| match opener.as_str() {
+            "*" => line[..opener.start()].trim().is_empty(),
+            "'" => false,
+            _ => true,
+        })
+        .last()
+        .map(|opener| (opener.start(), opener.end()))
+}
+
+/// The comment of a whole-line TODO comment: its indentation and opener.
+fn opener_of(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let opener = Regex::new(&format!("^{COMMENT_OPENERS}")).unwrap();
+    opener
+        .find(trimmed)
+        .map(|found| &line[..line.len() - trimmed.len() + found.end()])
+}
+
+/// The TODO's line without the TODO, or `None` if the line goes entirely.
+fn rewrite(matcher: &Matcher, line: &str) -> Result<Option<String>, String> {
+    let keyword = matcher
+        .keyword()
+        .find(line)
+        .ok_or("the line has no TODO")?
+        .start();
+    let (start, end) = comment_start(line, keyword).ok_or("the TODO is not in a comment")?;
+    let opener = line[start..end].trim_end_matches('!');
+
+    // a block comment ends on the line, or the whole comment can't be removed
+    let terminator =
+        COMMENT_TERMINATORS
+            .into_iter()
+            .find(|terminator| match (opener, *terminator) {
+                (opener, "*/") => opener.starts_with("/*"),
+                ("<!--", "-->") | ("{#", "#}") => true,
+                _ => false,
+            });
+    let after = match terminator {
+        Some(terminator) => match line[keyword..].find(terminator) {
+            Some(index) => &line[keyword + index..],
+            None => return Err("the comment continues on the next line".to_string()),
+        },
+        None => "",
+    };
+
+    let before = line[end..keyword].trim();
+    if !before.is_empty() {
+        let kept = line[..keyword].trim_end();
+        return Ok(Some(match terminator {
+            Some(_) => format!("{kept} {}", after.trim_start()),
+            None => kept.to_string(),
+        }));
+    }
+    let rest = terminator.map_or("", |terminator| after[terminator.len()..].trim());
+    let code = line[..start].trim_end();
+    Ok(match (code.trim().is_empty(), rest.is_empty()) {
+        (true, true) => None,
+        (true, false) => Some(format!("{}{rest}", &line[..start])),
+        (false, true) => Some(code.to_string()),
+        (false, false) => Some(format!("{code} {rest}")),
+    })
+}
+
+/// The edit removing the TODO on the 0-based line `index` of `lines`.
+fn edit(matcher: &Matcher, lines: &[&str], index: usize) -> Result<Edit, String> {
+    let line = *lines
+        .get(index)
+        .ok_or_else(|| format!("the file has only {} lines", lines.len()))?;
+    if !matcher.is_match(line) {
+        return Err("the line has no TODO".to_string());
+    }
+    let rewritten = rewrite(matcher, line)?;
+
+    let mut removed = vec![line.to_string()];
+    if rewritten.is_none() {
+        // indented comment lines right below continue the TODO
+        if let Some(opener) = opener_of(line) {
+            let continued = |next: &&&str| {
+                next.strip_prefix(opener)
+                    .is_some_and(|text| text.starts_with("  ") && !text.trim().is_empty())
+            };
+            removed.extend(
+                lines[index + 1..]
+                    .iter()
+                    .take_while(continued)
+                    .map(ToString::to_string),
+            );
+        }
+    }
+    Ok(Edit {
+        start: index,
+        removed,
+        added: rewritten.into_iter().collect(),
+    })
+}
+
+/// The edit as a unified diff of `path`.
+fn diff(path: &str, edit: &Edit) -> String {
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+    let new_start = if edit.added.is_empty() {
+        edit.start
+    } else {
+        edit.start + 1
+    };
+    let hunk = format!(
+        "@@ -{},{} +{new_start},{} @@",
+        edit.start + 1,
+        edit.removed.len(),
+        edit.added.len()
+    );
+    let _ = writeln!(out, "{}", hunk.cyan());
+    for line in &edit.removed {
+        let _ = writeln!(out, "{}", format!("-{line}").red());
+    }
+    for line in &edit.added {
+        let _ = writeln!(out, "{}", format!("+{line}").green());
+    }
+    out
+}
+
+/// Removes the TODO `target` names, printing the change first, or only
+/// printing it with `--dry-run`.
+pub fn run(repo: &Repository, options: &Options, target: &str) -> Result<(), String> {
+    let todo = locate(repo, options, target)?;
+    let path = &todo.file_path;
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("reading {}: {e}", path.display()))?;
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let ending = |line: &str| {
+        if line.ends_with("\r\n") {
+            "\r\n"
+        } else if line.ends_with('\n') {
+            "\n"
+        } else {
+            ""
+        }
+    };
+    let bare: Vec<&str> = lines
+        .iter()
+        .map(|line| line.trim_end_matches(['\r', '\n']))
+        .collect();
+    // the report may show the committed file rather than the working tree
+    if bare.get(todo.line - 1).copied() != Some(todo.raw.trim_end_matches('\r')) {
+        return Err(format!(
+            "{target}: line {} of {} differs from the report, name the TODO by its ID instead",
+            todo.line,
+            path.display()
+        ));
+    }
+    let matcher = Matcher::configured(options);
+    let edit = edit(&matcher, &bare, todo.line - 1).map_err(|e| format!("{target}: {e}"))?;
+
+    let shown = path.canonicalize().map_or_else(
+        |_| path.display().to_string(),
+        |path| repo_relative_path(repo, &path),
+    );
+    print!("{}", diff(&shown, &edit));
+    if options.dry_run {
+        return Ok(());
+    }
+
+    let end = edit.start + edit.removed.len();
+    let mut out: String = lines[..edit.start].concat();
+    for added in &edit.added {
+        out.push_str(added);
+        out.push_str(ending(lines[end - 1]));
+    }
+    out.push_str(&lines[end..].concat());
+    fs::write(path, out).map_err(|e| format!("writing {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn done(lines: &[&str], line: usize) -> Result<(Vec<String>, Vec<String>), String> {
+        edit(&Matcher::default(), lines, line - 1).map(|edit| (edit.removed, edit.added))
+    }
+
+    #[test]
+    fn removes_whole_trailing_and_partial_comments() {
+        let lines = [
+            "fn main() {",
+            "    // TODO: handle errors",
+            "    //   and retry them",
+            "    // unrelated",
+            "    run(); // TODO(perf): cache",
+            "    /* TODO */ go();",
+            "    // Runs twice. TODO: once",
+            "    /* TODO: more",
+            "}",
+        ];
+        assert_eq!(
+            done(&lines, 2).unwrap(),
+            (vec![lines[1].to_string(), lines[2].to_string()], Vec::new())
+        );
+        assert_eq!(done(&lines, 5).unwrap().1, ["    run();"]);
+        assert_eq!(done(&lines, 6).unwrap().1, ["    go();"]);
+        assert_eq!(done(&lines, 7).unwrap().1, ["    // Runs twice."]);
+        assert_eq!(
+            done(&lines, 8).unwrap_err(),
+            "the comment continues on the next line"
+        );
+        assert_eq!(done(&lines, 1).unwrap_err(), "the line has no TODO");
+        assert_eq!(
+            parse_target("src/a.rs:0").unwrap_err(),
+            "invalid TODO 'src/a.rs:0', expected FILE:LINE or an ID"
+        );
+    }
+
+    #[test]
+    fn refuses_lines_the_working_tree_changed() {
+        let test = crate::test_repo::TestRepo::new("done");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("a.rs", "fn a() {}\n// TODO: one\n");
+        test.commit("Alice", "b");
+        // the report shows line 2 of the commit, where a new TODO now is
+        test.write("a.rs", "fn a() {}\n// TODO: unrelated\n// TODO: one\n");
+
+        let options = Options {
+            no_cache: true,
+            ..Options::default()
+        };
+        let target = format!("{}:2", test.dir.join("a.rs").display());
+        let error = run(&test.repo, &options, &target).unwrap_err();
+        assert!(error.contains("differs from the report"), "{error}");
+        assert_eq!(
+            fs::read_to_string(test.dir.join("a.rs")).unwrap(),
+            "fn a() {}\n// TODO: unrelated\n// TODO: one\n"
+        );
+
+        // lines of the working tree that no report shows can still go
+        let target = format!("{}:3", test.dir.join("a.rs").display());
+        run(&test.repo, &options, &target).unwrap();
+        assert_eq!(
+            fs::read_to_string(test.dir.join("a.rs")).unwrap(),
+            "fn a() {}\n// TODO: unrelated\n"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! `--dry-run`: every command that would change something, be it a file, a git
+//! ref, a stored credential or an external service, prints the change instead
+//! of making it, so the commands that edit code can be tried out in CI first.
+//! Requests are printed by [`crate::http`], files by [`write`].
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use colored::Colorize;
+use git2::Patch;
+
+use crate::cli::Options;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Applies `--dry-run` until it is configured again.
+pub fn configure(options: &Options) {
+    ENABLED.store(options.dry_run, Ordering::Relaxed);
+}
+
+/// Whether changes are printed rather than made.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The change from `old` to `new` as a unified diff of the file at `shown`,
+/// empty when they are the same.
+pub fn diff(shown: &str, old: &str, new: &str) -> String {
+    let mut out = String::new();
+    let Ok(mut patch) = Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, None)
+    else {
+        return out;
+    };
+    if patch.num_hunks() == 0 {
+        return out;
+    }
+    let _ = writeln!(out, "--- a/{shown}\n+++ b/{shown}");
+    let _ = patch.print(&mut |_, _, line| {
+        let text = String::from_utf8_lossy(line.content());
+        let text = text.trim_end_matches(['\r', '\n']);
+        let _ = match line.origin() {
+            'H' => writeln!(out, "{}", text.cyan()),
+            '+' => writeln!(out, "{}", format!("+{text}").green()),
+            '-' => writeln!(out, "{}", format!("-{text}").red()),
+            ' ' => writeln!(out, " {text}"),
+            // file headers, which `shown` replaces, and end-of-file markers
+            _ => Ok(()),
+        };
+        true
+    });
+    out
+}
+
+/// Writes `contents` to `path`, shown as `shown`, or with `--dry-run` prints
+/// the diff the write would make. Returns whether the file was written.
+pub fn write(path: &Path, shown: &str, contents: &str) -> io::Result<bool> {
+    if !enabled() {
+        fs::write(path, contents)?;
+        return Ok(true);
+    }
+    let old = match fs::read_to_string(path) {
+        Ok(old) => old,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let diff = diff(shown, &old, contents);
+    if diff.is_empty() {
+        println!("[dry run] {shown} would be unchanged");
+    } else {
+        print!("{diff}");
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::strip_ansi;
+
+    #[test]
+    fn diffs_changed_lines() {
+        assert_eq!(
+            strip_ansi(&diff("a.txt", "one\ntwo\nthree\n", "one\n2\nthree\n")),
+            "--- a/a.txt\n+++ b/a.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+        );
+        assert_eq!(diff("a.txt", "same\n", "same\n"), "");
+    }
+}
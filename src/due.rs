@@ -0,0 +1,66 @@
+//! Due dates, for temporary hacks that must not outlive a milestone: a
+//! `TODO(by 2025-03-01)` or `TODO(due=2025-03-01)` field gives the TODO a due
+//! date, shown as the days left in the tree, and `--fail-expired` fails the
+//! run once any reported TODO is past it.
+
+use colored::Colorize;
+use git2::Repository;
+
+use crate::{dates, repo_relative_path, Todo};
+
+/// Days from today until the TODO is due, negative once it is overdue.
+pub fn days_left(todo: &Todo) -> Option<i64> {
+    todo.fields.due.map(|due| (due - dates::today()).num_days())
+}
+
+/// The TODOs past their due date.
+pub fn expired(todos: &[Todo]) -> Vec<&Todo> {
+    todos
+        .iter()
+        .filter(|todo| days_left(todo).is_some_and(|days| days < 0))
+        .collect()
+}
+
+/// The days left, e.g. `due in 3 days`, `due today` or `overdue by 2 days`.
+pub fn describe(days: i64) -> String {
+    let plural = |count: i64| if count == 1 { "" } else { "s" };
+    match days {
+        0 => "due today".to_string(),
+        1.. => format!("due in {days} day{}", plural(days)),
+        _ => format!("overdue by {} day{}", -days, plural(-days)),
+    }
+}
+
+/// Lists the TODOs `--fail-expired` fails on, if any.
+pub fn report_expired(repo: &Repository, expired: &[&Todo]) {
+    if expired.is_empty() {
+        return;
+    }
+    eprintln!(
+        "\n{} {} TODO{} past the due date:",
+        "error:".red().bold(),
+        expired.len(),
+        if expired.len() == 1 { "" } else { "s" },
+    );
+    for todo in expired {
+        eprintln!(
+            "  {}:{}: {} ({})",
+            repo_relative_path(repo, &todo.file_path),
+            todo.line,
+            todo.normalized,
+            describe(days_left(todo).unwrap_or_default())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_days_left() {
+        assert_eq!(describe(0), "due today");
+        assert_eq!(describe(1), "due in 1 day");
+        assert_eq!(describe(-3), "overdue by 3 days");
+    }
+}
@@ -0,0 +1,203 @@
+//! Plain machine-readable outputs for scripts and dashboards. Each TODO carries
+//! both the raw source line and its normalized text, so consumers never have
+//! to parse the colored display string.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::github::strip_ansi;
+use crate::metadata::Metadata;
+use crate::severity::Severity;
+use crate::{dates, redact, repo_relative_path, stale_todos, Grouped, Grouping, Position, Todo};
+
+const CSV_HEADER: &str = "path,line,raw,statement,tags,author,commit,date";
+
+/// One TODO as JSON, as the `json` format and the serve API list it.
+pub fn record(repo: &Repository, todo: &Todo) -> serde_json::Value {
+    json!({
+                "id": todo.id,
+                "path": repo_relative_path(repo, &todo.file_path),
+                "line": todo.line,
+                "raw": redact::shared(&todo.raw),
+                "statement": redact::shared(&todo.normalized),
+                "tags": todo.tags,
+                "assignee": todo.assignee(),
+                "issue": todo.fields.issue,
+                "priority": todo.fields.priority,
+                "fields": todo.fields.other,
+                "author": todo.author,
+                "commit": todo.commit_hash,
+                "date": dates::rfc3339(todo.author_date),
+                "due": todo.fields.due.map(|due| due.to_string()),
+                "milestone": todo.milestone,
+                "owner": todo.owner,
+                "org": todo.org,
+                "language": todo.language,
+                "test": todo.test,
+                "branches": todo.branches,
+                "on_base": todo.position.as_ref().map(|position| matches!(position, Position::OnBase(_))),
+                "orphaned": todo.orphaned,
+                "issue_resolved": todo.issue_resolved,
+                "content_unavailable": todo.content_unavailable,
+                "severity": todo.severity.to_string(),
+                "stale": todo.stale,
+    })
+}
+
+pub fn json(repo: &Repository, todos: &[Todo]) -> serde_json::Value {
+    todos.iter().map(|todo| record(repo, todo)).collect()
+}
+
+/// The array a level's groups are listed in, and the field naming each group.
+const fn level_fields(level: Grouping) -> (&'static str, &'static str) {
+    match level {
+        Grouping::Tag => ("tags", "tag"),
+        Grouping::Author => ("authors", "author"),
+        Grouping::Org => ("orgs", "org"),
+        Grouping::File => ("files", "file"),
+        Grouping::Dir => ("dirs", "dir"),
+        _ => ("groups", "key"),
+    }
+}
+
+/// The field listing what is below a level, and the list.
+fn grouped_level(repo: &Repository, grouped: &Grouped) -> (&'static str, serde_json::Value) {
+    let (level, groups) = match grouped {
+        Grouped::Groups(level, groups) => (*level, groups),
+        Grouped::Todos(todos) => {
+            return (
+                "todos",
+                todos.iter().map(|todo| record(repo, todo)).collect(),
+            );
+        }
+    };
+    let (list, field) = level_fields(level);
+    let groups = groups
+        .iter()
+        .map(|(key, below)| {
+            let name = match level {
+                Grouping::Tag if key.id.is_empty() => serde_json::Value::Null,
+                Grouping::File | Grouping::Dir => {
+                    let path = repo_relative_path(repo, Path::new(&key.id));
+                    if path.is_empty() {
+                        ".".into()
+                    } else {
+                        path.into()
+                    }
+                }
+                _ => key.id.clone().into(),
+            };
+            let mut group = json!({ field: name, "count": below.count() });
+            if field == "key" {
+                group["title"] = strip_ansi(&key.display).into();
+            }
+            let (below_list, below) = grouped_level(repo, below);
+            group[below_list] = below;
+            group
+        })
+        .collect();
+    (list, groups)
+}
+
+/// The grouped tree as nested JSON: by default commits (or milestones), then
+/// tags, then authors, each level with the number of TODOs below it.
+/// Untagged TODOs have a `null` tag. TODOs escalated by `--max-age` are also
+/// listed under `stale`, and `severities` counts the TODOs of each severity.
+pub fn grouped_json(repo: &Repository, todos: &[Todo], grouped: &Grouped) -> serde_json::Value {
+    let stale: Vec<_> = stale_todos(todos)
+        .into_iter()
+        .map(|todo| record(repo, todo))
+        .collect();
+
+    let mut severities = serde_json::Map::new();
+    for severity in [Severity::High, Severity::Medium, Severity::Low] {
+        let count = todos
+            .iter()
+            .filter(|todo| todo.severity == severity)
+            .count();
+        severities.insert(severity.to_string(), count.into());
+    }
+
+    let mut json = json!({ "count": grouped.count(), "severities": severities, "stale": stale });
+    let (list, groups) = grouped_level(repo, grouped);
+    json[list] = groups;
+    json
+}
+
+/// Quotes a field if it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The TODOs as CSV, preceded by the scan's metadata as `#` comment lines,
+/// which readers that don't skip comments should be told to skip.
+pub fn csv(repo: &Repository, todos: &[Todo], metadata: &Metadata) -> String {
+    let mut out = String::new();
+    for (label, value) in metadata.fields() {
+        writeln!(out, "# {label}: {value}").unwrap();
+    }
+    writeln!(out, "{CSV_HEADER}").unwrap();
+    for todo in todos {
+        let fields = [
+            repo_relative_path(repo, &todo.file_path),
+            todo.line.to_string(),
+            redact::shared(&todo.raw),
+            redact::shared(&todo.normalized),
+            todo.tags.join(";"),
+            todo.author.clone(),
+            todo.commit_hash.clone(),
+            dates::rfc3339(todo.author_date),
+        ];
+        let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(out, "{}", row.join(",")).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+    use crate::{group_todos, GroupBy};
+
+    #[test]
+    fn nests_groups_with_counts_per_level() {
+        let test = TestRepo::new("grouped");
+        test.write("a.rs", "// TODO(perf): one\n// TODO: two\n");
+        test.commit("Alice", "a");
+        test.write("b.rs", "// TODO(perf): three\n");
+        test.commit("Bob", "b");
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let grouped = group_todos(
+            &todos,
+            &GroupBy(vec![Grouping::Tag, Grouping::Author]),
+            "untagged",
+            false,
+        );
+
+        let json = grouped_json(&test.repo, &todos, &grouped);
+        assert_eq!(json["count"], 3);
+        assert_eq!(json["severities"]["low"], 3);
+        let perf = &json["tags"][0];
+        assert_eq!((&perf["tag"], &perf["count"]), (&json!("perf"), &json!(2)));
+        assert_eq!(perf["authors"][1]["author"], "Bob");
+        assert_eq!(perf["authors"][1]["todos"][0]["path"], "b.rs");
+        // untagged TODOs have a null tag
+        let untagged = &json["tags"][1];
+        assert!(untagged["tag"].is_null());
+        assert_eq!(untagged["authors"][0]["todos"][0]["statement"], "two");
+    }
+}
@@ -0,0 +1,137 @@
+//! `key=value` fields in a TODO's parentheses, such as
+//! `TODO(perf, owner=alice, issue=#123, p=1)`, which say what a free-form tag
+//! can only hint at. Known keys become typed fields; the rest are kept as
+//! written. A `by 2025-03-01` entry is the due date too, and a bare `#456`,
+//! as `todo export github-issues` writes it, or Jira key such as `PROJ-42` the
+//! issue.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fields {
+    /// From `owner=alice`, without any `@`: who the TODO is assigned to, like
+    /// an `@alice` tag.
+    pub owner: Option<String>,
+    /// From `issue=#123`, `#123` or `PROJ-42`, as written.
+    pub issue: Option<String>,
+    /// From `priority=1` or `p=1`, where 1 is more urgent than 2.
+    pub priority: Option<u32>,
+    /// From `due=2025-03-01` or `by 2025-03-01`.
+    pub due: Option<NaiveDate>,
+    /// Any other fields, by lower-cased key.
+    pub other: BTreeMap<String, String>,
+}
+
+/// Whether `entry` names an issue by itself: `#123` on GitHub, or a Jira key
+/// such as `PROJ-42`.
+pub fn is_issue(entry: &str) -> bool {
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+    if let Some(number) = entry.strip_prefix('#') {
+        return digits(number);
+    }
+    entry.split_once('-').is_some_and(|(key, number)| {
+        key.len() >= 2
+            && key.starts_with(|c: char| c.is_ascii_uppercase())
+            && key
+                .bytes()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_')
+            && digits(number)
+    })
+}
+
+impl Fields {
+    /// Takes the fields out of a TODO's `tags`, leaving the tags proper.
+    pub fn extract(tags: &mut Vec<String>) -> Self {
+        let mut fields = Self::default();
+        tags.retain(|tag| !fields.add(tag));
+        fields
+    }
+
+    /// Records `entry` if it is a field, returning whether it was. The first
+    /// of a key counts.
+    fn add(&mut self, entry: &str) -> bool {
+        if is_issue(entry) {
+            self.issue.get_or_insert_with(|| entry.to_string());
+            return true;
+        }
+        if let Some(due) = entry
+            .strip_prefix("by ")
+            .and_then(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok())
+        {
+            self.due.get_or_insert(due);
+            return true;
+        }
+        let Some((key, value)) = entry.split_once('=') else {
+            return false;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim());
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return false;
+        }
+
+        let typed = match key.as_str() {
+            "owner" if !value.trim_start_matches('@').is_empty() => {
+                let owner = value.trim_start_matches('@').to_string();
+                self.owner.get_or_insert(owner);
+                true
+            }
+            "issue" if !value.is_empty() => {
+                self.issue.get_or_insert_with(|| value.to_string());
+                true
+            }
+            "priority" | "p" => value
+                .parse()
+                .map(|priority| self.priority.get_or_insert(priority))
+                .is_ok(),
+            "due" => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|due| self.due.get_or_insert(due))
+                .is_ok(),
+            _ => false,
+        };
+        if !typed {
+            self.other.entry(key).or_insert_with(|| value.to_string());
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_typed_and_other_fields() {
+        let mut tags: Vec<String> = [
+            "perf",
+            "owner=@alice",
+            "issue=#123",
+            "p=1",
+            "by 2025-03-01",
+            "Team = storage",
+            "priority=high",
+            "@bob",
+            "#7",
+            "#x",
+            "PROJ-42",
+            "Proj-1",
+        ]
+        .map(String::from)
+        .to_vec();
+        let fields = Fields::extract(&mut tags);
+        assert_eq!(tags, ["perf", "@bob", "#x", "Proj-1"]);
+        assert_eq!(fields.owner.as_deref(), Some("alice"));
+        assert_eq!(fields.issue.as_deref(), Some("#123"));
+        assert!(is_issue("PROJ-42") && is_issue("#7") && !is_issue("P-1"));
+        assert_eq!(fields.priority, Some(1));
+        assert_eq!(fields.due, NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!(
+            fields.other,
+            [("team", "storage"), ("priority", "high")]
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .into()
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! `--type`: scanning only some kinds of files, named like ripgrep's types,
+//! such as `rust`, `ts` or `py`, with more defined by `--type-add` or
+//! `type-add` in `.todo.toml`.
+
+use ignore::types::{Types, TypesBuilder};
+
+use crate::cli::Options;
+
+/// Short names for built-in types that ripgrep only knows by a longer one.
+const ALIASES: [(&str, &str); 4] = [
+    ("rs", "rust"),
+    ("rb", "ruby"),
+    ("kt", "kotlin"),
+    ("cs", "csharp"),
+];
+
+/// The file types `options` select from the built-in and added definitions,
+/// matching every file when none are selected.
+pub fn types(options: &Options) -> Result<Types, String> {
+    let mut builder = TypesBuilder::new();
+    builder.add_defaults();
+    for def in builder.definitions() {
+        for (alias, _) in ALIASES.iter().filter(|(_, name)| *name == def.name()) {
+            for glob in def.globs() {
+                // the built-in globs and names are valid
+                let _ = builder.add(alias, glob);
+            }
+        }
+    }
+    for def in &options.type_defs {
+        builder
+            .add_def(def)
+            .map_err(|e| format!("invalid type definition '{def}': {e}"))?;
+    }
+    for name in &options.types {
+        builder.select(name);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_built_in_aliased_and_added_types() {
+        let types = types(&Options {
+            types: vec!["rs".to_string(), "ts".to_string(), "proto".to_string()],
+            type_defs: vec!["proto:*.proto".to_string()],
+            ..Options::default()
+        })
+        .unwrap();
+        let scanned = |path| !types.matched(path, false).is_ignore();
+        assert!(scanned("src/main.rs"));
+        assert!(scanned("web/app.tsx"));
+        assert!(scanned("api/todo.proto"));
+        assert!(!scanned("scripts/build.py"));
+
+        let unknown = Options {
+            types: vec!["nope".to_string()],
+            ..Options::default()
+        };
+        assert!(super::types(&unknown).is_err());
+        assert!(!super::types(&Options::default())
+            .unwrap()
+            .matched("x.py", false)
+            .is_ignore());
+    }
+}
@@ -0,0 +1,85 @@
+//! `--fs-markers`: files and directories whose names say they were meant to
+//! go, such as `hack_todo.rs` or `TODO_delete_me/`. No comment points at
+//! them, so they outlive the TODOs around them unless something does.
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+use colored::Colorize;
+use ptree::{write_tree, TreeBuilder};
+
+/// Words that mark a name, matched whole and in any case.
+const WORDS: [&str; 2] = ["todo", "wip"];
+
+/// A name marks its file or directory if one of the words separated by
+/// punctuation in it is a marker word.
+fn is_marked(name: &str) -> bool {
+    name.split(|c: char| !c.is_alphanumeric())
+        .any(|word| WORDS.iter().any(|marker| word.eq_ignore_ascii_case(marker)))
+}
+
+/// The marked files and directories among `paths`, relative to the
+/// repository root, with `/` separators and directories ending in `/`.
+pub fn find<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Vec<String> {
+    let mut markers = BTreeSet::new();
+    for path in paths {
+        let mut prefix = String::new();
+        let components: Vec<_> = path.iter().map(|c| c.to_string_lossy()).collect();
+        for (index, name) in components.iter().enumerate() {
+            prefix.push_str(name);
+            let is_dir = index + 1 < components.len();
+            if is_dir {
+                prefix.push('/');
+            }
+            if is_marked(name) {
+                markers.insert(prefix.clone());
+            }
+        }
+    }
+    markers.into_iter().collect()
+}
+
+/// The "filesystem markers" section of the tree.
+pub fn write(out: &mut impl Write, markers: &[String]) -> io::Result<()> {
+    if markers.is_empty() {
+        return Ok(());
+    }
+    let title = format!(
+        "{} filesystem marker{}",
+        markers.len(),
+        if markers.len() == 1 { "" } else { "s" }
+    );
+    let mut tree = TreeBuilder::new(format!("{}", title.yellow().bold()));
+    for marker in markers {
+        tree.add_empty_child(marker.clone());
+    }
+    write_tree(&tree.build(), &mut *out)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_marked_names() {
+        let paths = [
+            Path::new("src/hack_todo.rs"),
+            Path::new("TODO_delete_me/a.rs"),
+            Path::new("TODO_delete_me/b.rs"),
+            Path::new("src/wipe.rs"),
+            Path::new("src/todos.rs"),
+            Path::new("wip/Wip-notes.md"),
+        ];
+        assert_eq!(
+            find(paths),
+            [
+                "TODO_delete_me/",
+                "src/hack_todo.rs",
+                "wip/",
+                "wip/Wip-notes.md"
+            ]
+        );
+    }
+}
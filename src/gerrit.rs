@@ -0,0 +1,82 @@
+//! Output for Gerrit, which attaches robot comments to the lines of a change
+//! when a CI verifier posts them as part of a review.
+//!
+//! The output is a `ReviewInput` document: pass it to
+//! `POST /changes/{change}/revisions/{revision}/review` or to
+//! `ssh <host> gerrit review --json`.
+
+use std::collections::BTreeMap;
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::github::strip_ansi;
+use crate::metadata::Metadata;
+use crate::{redact, repo_relative_path, Todo};
+
+const ROBOT_ID: &str = "todo";
+
+pub fn review_input(repo: &Repository, todos: &[Todo], metadata: &Metadata) -> serde_json::Value {
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default();
+
+    let mut robot_comments: BTreeMap<String, Vec<serde_json::Value>> = BTreeMap::new();
+
+    for todo in todos {
+        robot_comments
+            .entry(repo_relative_path(repo, &todo.file_path))
+            .or_default()
+            .push(json!({
+                "robot_id": ROBOT_ID,
+                "robot_run_id": head,
+                "line": todo.line,
+                "message": redact::shared(&strip_ansi(todo.statement.trim())),
+                "properties": {
+                    "author": todo.author,
+                    "commit": todo.commit_hash,
+                    "tags": todo.tags.join(", "),
+                },
+            }));
+    }
+
+    json!({
+        "tag": format!("autogenerated:{ROBOT_ID}"),
+        "message": metadata.summary(),
+        "robot_comments": robot_comments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn comments_on_each_file() {
+        let test = TestRepo::new("gerrit");
+        test.write("a.rs", "// TODO(perf): one\n// TODO: two\n");
+        test.write("src/b.rs", "// TODO: three\n");
+        let head = test.commit("Alice", "a");
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+
+        let review = review_input(&test.repo, &todos, &Metadata::new(&test.repo, &options));
+        assert_eq!(review["tag"], "autogenerated:todo");
+        let comments = &review["robot_comments"];
+        assert_eq!(comments["a.rs"].as_array().unwrap().len(), 2);
+        assert_eq!(comments["a.rs"][0]["line"], 1);
+        assert_eq!(comments["a.rs"][0]["robot_run_id"], head.to_string());
+        assert_eq!(comments["a.rs"][0]["properties"]["author"], "Alice");
+        assert_eq!(comments["a.rs"][0]["properties"]["tags"], "perf");
+        assert_eq!(comments["src/b.rs"][0]["line"], 1);
+    }
+}
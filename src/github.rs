@@ -0,0 +1,194 @@
+//! Integration with GitHub Actions workflow runs.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+
+use git2::Repository;
+use regex::Regex;
+
+use crate::matcher::Matcher;
+use crate::metadata::Metadata;
+use crate::severity::Severity;
+use crate::trend::Trend;
+use crate::{
+    dates, get_relative_or_absolute_path, markdown, redact, repo_relative_path, stale_todos,
+    Grouped, Todo,
+};
+
+/// Whether we are running as a step of a GitHub Actions workflow.
+pub fn is_actions() -> bool {
+    env::var_os("GITHUB_ACTIONS").is_some_and(|value| value == "true")
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command, such as its file or title.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// One workflow command per TODO, which Actions turns into an annotation on
+/// its line of the pull request diff: an error for high severity and a
+/// warning otherwise.
+pub fn annotations(repo: &Repository, matcher: &Matcher, todos: &[Todo]) -> String {
+    let mut out = String::new();
+    for todo in todos {
+        let level = match todo.severity {
+            Severity::High => "error",
+            Severity::Medium | Severity::Low => "warning",
+        };
+        let mut title = matcher.keyword_in(&todo.raw);
+        if !todo.author.is_empty() {
+            write!(title, " by {}", todo.author).unwrap();
+        }
+        writeln!(
+            out,
+            "::{level} file={},line={},title={}::{}",
+            escape_property(&repo_relative_path(repo, &todo.file_path)),
+            todo.line,
+            escape_property(&title),
+            escape_data(&redact::shared(&strip_ansi(todo.statement.trim()))),
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Removes terminal color codes, which GitHub renders literally outside of logs.
+pub fn strip_ansi(text: &str) -> String {
+    let re = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    re.replace_all(text, "").into_owned()
+}
+
+/// Appends a Markdown rendering of the grouped TODOs to the file named by
+/// `$GITHUB_STEP_SUMMARY`, which Actions displays on the workflow run page.
+pub fn write_step_summary(
+    todos: &[Todo],
+    grouped: &Grouped,
+    summary: &str,
+    trend: Option<Trend>,
+    metadata: &Metadata,
+) -> std::io::Result<()> {
+    let Some(path) = env::var_os("GITHUB_STEP_SUMMARY") else {
+        return Ok(());
+    };
+    let markdown = step_summary(todos, grouped, summary, trend, metadata)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(markdown.as_bytes())
+}
+
+/// The Markdown of the step summary.
+fn step_summary(
+    todos: &[Todo],
+    grouped: &Grouped,
+    summary: &str,
+    trend: Option<Trend>,
+    metadata: &Metadata,
+) -> std::io::Result<String> {
+    let mut markdown = format!("## TODOs\n\n**{summary}**");
+    if let Some(trend) = trend {
+        write!(
+            markdown,
+            " · +{} / −{} vs `{}`",
+            trend.added,
+            trend.removed,
+            metadata.base()
+        )
+        .unwrap();
+    }
+    markdown.push_str("\n\n");
+
+    let stale = stale_todos(todos);
+    if !stale.is_empty() {
+        markdown.push_str("### Stale\n\n");
+        for todo in stale {
+            let file_link = get_relative_or_absolute_path(&todo.file_path)?;
+            let written = todo.introduced_date.unwrap_or(todo.author_date);
+            writeln!(
+                markdown,
+                "- `{}:{}` — {} ({}, {})",
+                file_link.display(),
+                todo.line,
+                redact::shared(&strip_ansi(todo.statement.trim())).replace('`', "'"),
+                todo.author,
+                dates::ago(written),
+            )
+            .unwrap();
+        }
+        markdown.push('\n');
+    }
+
+    let item = |todo: &Todo| {
+        let file_link = get_relative_or_absolute_path(&todo.file_path)
+            .unwrap_or_else(|_| todo.file_path.clone());
+        format!(
+            "`{}:{}` — {}",
+            file_link.display(),
+            todo.line,
+            redact::shared(&strip_ansi(todo.statement.trim())).replace('`', "'"),
+        )
+    };
+    match grouped {
+        Grouped::Groups(_, groups) => {
+            for (key, below) in groups {
+                writeln!(markdown, "### {}\n", strip_ansi(&key.display)).unwrap();
+                markdown::list(&mut markdown, below, 0, &item);
+                markdown.push('\n');
+            }
+        }
+        Grouped::Todos(_) => {
+            markdown::list(&mut markdown, grouped, 0, &item);
+            markdown.push('\n');
+        }
+    }
+    writeln!(markdown, "<sub>{}</sub>\n", metadata.summary()).unwrap();
+    Ok(markdown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_workflow_commands() {
+        assert_eq!(escape_data("50% done\nnext"), "50%25 done%0Anext");
+        assert_eq!(escape_property("src/a,b:c.rs"), "src/a%2Cb%3Ac.rs");
+    }
+
+    #[test]
+    fn folds_groups_and_summarizes_them() {
+        let test = crate::test_repo::TestRepo::new("actions");
+        test.write("a.rs", "// TODO(perf): faster\n// TODO: later\n");
+        test.commit("Alice", "a");
+        let options = crate::cli::Options {
+            all_files: true,
+            no_cache: true,
+            ..crate::cli::Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let group_by = crate::GroupBy(vec![crate::Grouping::Tag]);
+        let grouped = crate::group_todos(&todos, &group_by, "untagged", false);
+
+        let mut log = Vec::new();
+        crate::write_grouped_todos(&mut log, &grouped, true).unwrap();
+        let log = strip_ansi(&String::from_utf8(log).unwrap());
+        assert!(log.contains("::group::perf\n") && log.contains("::group::untagged\n"));
+        assert_eq!(log.matches("::endgroup::").count(), 2);
+
+        let metadata = Metadata::new(&test.repo, &options);
+        let trend = Trend {
+            added: 2,
+            removed: 0,
+        };
+        let summary = step_summary(&todos, &grouped, "2 TODOs", Some(trend), &metadata).unwrap();
+        assert!(summary.starts_with("## TODOs\n\n**2 TODOs** · +2 / −0 vs `main`\n"));
+        assert!(summary.contains("### perf\n") && summary.contains("### untagged\n"));
+    }
+}
@@ -0,0 +1,253 @@
+//! `todo export github-issues`: a GitHub issue for each selected TODO, with
+//! the issue's number written back into the comment, as `TODO(#456): ...`, so
+//! the code links to the issue as the issue links to the code. TODOs that
+//! already name an issue are skipped, so exporting again only files new ones.
+
+use std::fmt::Write;
+use std::fs;
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::credentials::{self, Provider};
+use crate::handover::permalink;
+use crate::matcher::Matcher;
+use crate::{dry_run, http, pull_request, redact, repo_relative_path, Todo};
+
+/// The longest title GitHub accepts, in characters.
+const TITLE_LIMIT: usize = 256;
+
+/// The REST API endpoint for issues of the repository at `web`, such as
+/// `https://github.com/owner/repo`: on api.github.com, or under `/api/v3` of a
+/// GitHub Enterprise Server. `$GITHUB_API_URL`, which Actions sets, wins.
+fn issues_url(web: &str, api_override: Option<&str>) -> Option<String> {
+    let (host, slug) = web.strip_prefix("https://")?.split_once('/')?;
+    let api = match api_override {
+        Some(api) => api.trim_end_matches('/').to_string(),
+        None if host == "github.com" => "https://api.github.com".to_string(),
+        None => format!("https://{host}/api/v3"),
+    };
+    Some(format!("{api}/repos/{slug}/issues"))
+}
+
+/// The issues endpoint of the `origin` remote's repository.
+pub fn endpoint(repo: &Repository) -> Result<String, String> {
+    let web = pull_request::web_url(repo).ok_or("the origin remote has no web URL")?;
+    let api_override = std::env::var("GITHUB_API_URL").ok();
+    issues_url(&web, api_override.as_deref())
+        .ok_or_else(|| format!("can't tell the GitHub API of {web}"))
+}
+
+/// The headers of every GitHub API request, with the token if there is one.
+pub fn headers(request: http::Request, token: Option<&str>) -> http::Request {
+    let request = request
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28");
+    match token {
+        Some(token) => request.header("Authorization", format!("Bearer {token}")),
+        None => request,
+    }
+}
+
+/// The issue to create for `todo`, linking to its line at `head`.
+fn issue(repo: &Repository, todo: &Todo, web: &str, head: Option<&str>) -> serde_json::Value {
+    let path = repo_relative_path(repo, &todo.file_path);
+    let title: String = redact::shared(&todo.normalized)
+        .chars()
+        .take(TITLE_LIMIT)
+        .collect();
+
+    let location = head.map_or_else(
+        || format!("`{path}:{}`", todo.line),
+        |head| permalink(web, head, &path, todo.line),
+    );
+    let mut body = format!(
+        "{location}\n\n```\n{}\n```\n",
+        redact::shared(todo.raw.trim())
+    );
+    if !todo.commit_hash.is_empty() {
+        let _ = write!(
+            body,
+            "\nWritten by {} in {} {}.\n",
+            todo.author, todo.short_hash, todo.commit_title
+        );
+    }
+
+    let labels: Vec<&String> = todo
+        .tags
+        .iter()
+        .filter(|tag| !tag.starts_with('@'))
+        .collect();
+    // default owners are often teams, which can't be assigned
+    let assignee = todo.assignee().or_else(|| {
+        todo.owner
+            .as_deref()
+            .and_then(|owner| owner.strip_prefix('@'))
+            .filter(|handle| !handle.contains(['/', '@']))
+    });
+    json!({
+        "title": title,
+        "body": body,
+        "labels": labels,
+        "assignees": assignee.into_iter().collect::<Vec<_>>(),
+    })
+}
+
+/// The TODO's line with `#number` first in its parentheses, which are added
+/// if it has none.
+fn link(matcher: &Matcher, line: &str, number: u64) -> Option<String> {
+    let keyword = matcher.keyword().find(line)?;
+    let (before, after) = line.split_at(keyword.end());
+    Some(match after.strip_prefix('(') {
+        Some(inner) if inner.starts_with(')') => format!("{before}(#{number}{inner}"),
+        Some(inner) => format!("{before}(#{number}, {inner}"),
+        None => format!("{before}(#{number}){after}"),
+    })
+}
+
+/// Writes the issue's number into the TODO's comment, if its line is still
+/// as scanned.
+fn write_back(matcher: &Matcher, todo: &Todo, number: u64) -> Result<(), String> {
+    let path = &todo.file_path;
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("reading {}: {e}", path.display()))?;
+    let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let index = todo.line - 1;
+    let original = lines.get(index).copied().unwrap_or_default();
+    let text = original.trim_end_matches(['\r', '\n']);
+    if text != todo.raw.trim_end_matches('\r') {
+        return Err(format!("line {} of {} changed", todo.line, path.display()));
+    }
+    let linked = link(matcher, text, number).ok_or("the line has no TODO")?;
+    let linked = format!("{linked}{}", &original[text.len()..]);
+    lines[index] = &linked;
+    fs::write(path, lines.concat()).map_err(|e| format!("writing {}: {e}", path.display()))
+}
+
+/// Creates an issue for each of `todos` without one whose ID starts with one
+/// of `ids`, or for all of them without `ids`, or only prints the requests
+/// with `--dry-run`.
+pub fn run(
+    repo: &Repository,
+    matcher: &Matcher,
+    todos: &[Todo],
+    ids: &[String],
+) -> Result<(), String> {
+    let selected =
+        |todo: &Todo| ids.is_empty() || ids.iter().any(|id| todo.id.starts_with(id.as_str()));
+    if let Some(unknown) = ids
+        .iter()
+        .find(|id| !todos.iter().any(|todo| todo.id.starts_with(id.as_str())))
+    {
+        return Err(format!("no TODO has the ID '{unknown}'"));
+    }
+
+    let url = endpoint(repo)?;
+    let web = pull_request::web_url(repo).unwrap_or_default();
+    let token = match credentials::token(Provider::Github) {
+        Some(token) => Some(token),
+        None if dry_run::enabled() => None,
+        None => {
+            return Err(
+                "no GitHub token; set GITHUB_TOKEN or run 'todo auth login github'".to_string(),
+            )
+        }
+    };
+    // link to the checked-out commit so the line numbers stay valid
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|id| id.to_string());
+
+    let pending: Vec<&Todo> = todos
+        .iter()
+        .filter(|todo| selected(todo) && todo.fields.issue.is_none())
+        .collect();
+    if pending.is_empty() {
+        println!("Every TODO already has an issue");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for todo in &pending {
+        let location = format!(
+            "{}:{}",
+            repo_relative_path(repo, &todo.file_path),
+            todo.line
+        );
+        let request = headers(http::Request::new("POST", &url), token.as_deref()).json(&issue(
+            repo,
+            todo,
+            &web,
+            head.as_deref(),
+        ));
+        if dry_run::enabled() {
+            let _ = request.send();
+            continue;
+        }
+
+        let created = request
+            .send_ok()
+            .map_err(|e| e.to_string())
+            .and_then(|response| {
+                let issue: serde_json::Value =
+                    serde_json::from_str(&response.body).map_err(|e| e.to_string())?;
+                let number = issue["number"]
+                    .as_u64()
+                    .ok_or("the response has no issue number")?;
+                Ok((
+                    number,
+                    issue["html_url"].as_str().unwrap_or_default().to_string(),
+                ))
+            });
+        match created {
+            Ok((number, issue_url)) => match write_back(matcher, todo, number) {
+                Ok(()) => println!("{location}: created #{number} {issue_url}"),
+                Err(e) => {
+                    eprintln!("Error linking {location} to #{number} {issue_url}: {e}");
+                    failed += 1;
+                }
+            },
+            Err(e) => {
+                eprintln!("Error creating an issue for {location}: {e}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{failed} of {} TODOs failed", pending.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_issues_into_todos() {
+        let link = |line, number| link(&Matcher::default(), line, number);
+        assert_eq!(
+            link("    // TODO(perf): cache it", 456).unwrap(),
+            "    // TODO(#456, perf): cache it"
+        );
+        assert_eq!(link("# TODO: later", 7).unwrap(), "# TODO(#7): later");
+        assert_eq!(link("/* TODO() x */", 7).unwrap(), "/* TODO(#7) x */");
+        assert_eq!(link("nothing here", 7), None);
+
+        assert_eq!(
+            issues_url("https://github.com/o/r", None).unwrap(),
+            "https://api.github.com/repos/o/r/issues"
+        );
+        assert_eq!(
+            issues_url("https://git.corp.example/o/r", None).unwrap(),
+            "https://git.corp.example/api/v3/repos/o/r/issues"
+        );
+        assert_eq!(
+            issues_url("https://github.com/o/r", Some("https://api.example/")).unwrap(),
+            "https://api.example/repos/o/r/issues"
+        );
+    }
+}
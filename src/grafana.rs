@@ -0,0 +1,188 @@
+//! Endpoints for Grafana's JSON datasource plugin, serving the scan history
+//! as time series (`total`, `tag:<name>`, `author:<name>`) and marking scans
+//! of new commits as annotations.
+
+use std::collections::BTreeSet;
+
+use chrono::DateTime;
+use serde_json::{json, Value};
+
+use crate::history::{History, Record};
+
+fn value_of(record: &Record, metric: &str) -> Option<usize> {
+    if metric == "total" {
+        return Some(record.stats.total);
+    }
+    if let Some(tag) = metric.strip_prefix("tag:") {
+        return Some(record.stats.by_tag.get(tag).copied().unwrap_or(0));
+    }
+    if let Some(author) = metric.strip_prefix("author:") {
+        return Some(record.stats.by_author.get(author).copied().unwrap_or(0));
+    }
+    None
+}
+
+fn metric_names(history: &History) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for record in &history.records {
+        names.extend(record.stats.by_tag.keys().map(|tag| format!("tag:{tag}")));
+        names.extend(
+            record
+                .stats
+                .by_author
+                .keys()
+                .map(|author| format!("author:{author}")),
+        );
+    }
+    std::iter::once("total".to_string()).chain(names).collect()
+}
+
+/// The `[from, to]` range of a query in Unix milliseconds, unbounded if absent.
+fn range_millis(body: &Value) -> (i64, i64) {
+    let parse = |key: &str| {
+        body["range"][key]
+            .as_str()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.timestamp_millis())
+    };
+    (
+        parse("from").unwrap_or(i64::MIN),
+        parse("to").unwrap_or(i64::MAX),
+    )
+}
+
+fn records_in_range<'a>(history: &'a History, body: &Value) -> impl Iterator<Item = &'a Record> {
+    let (from, to) = range_millis(body);
+    history.records.iter().filter(move |record| {
+        let millis = record.timestamp * 1000;
+        millis >= from && millis <= to
+    })
+}
+
+/// `POST /search`: the metric names, as the plugin's older API expects.
+pub fn search(history: &History) -> Value {
+    json!(metric_names(history))
+}
+
+/// `POST /metrics`: the metric names, as the plugin's current API expects.
+pub fn metrics(history: &History) -> Value {
+    let metrics: Vec<_> = metric_names(history)
+        .into_iter()
+        .map(|name| json!({ "label": name, "value": name }))
+        .collect();
+    json!(metrics)
+}
+
+/// `POST /query`: one series of `[value, timestamp]` points per target.
+pub fn query(history: &History, body: &Value) -> Value {
+    let targets = body["targets"].as_array().cloned().unwrap_or_default();
+
+    let series: Vec<_> = targets
+        .iter()
+        .filter_map(|target| target["target"].as_str())
+        .map(|metric| {
+            let datapoints: Vec<_> = records_in_range(history, body)
+                .filter_map(|record| {
+                    value_of(record, metric).map(|value| json!([value, record.timestamp * 1000]))
+                })
+                .collect();
+            json!({ "target": metric, "datapoints": datapoints })
+        })
+        .collect();
+
+    json!(series)
+}
+
+/// `POST /annotations`: the first scan of each new `HEAD` commit.
+pub fn annotations(history: &History, body: &Value) -> Value {
+    let mut previous_head = None;
+    let mut annotations = Vec::new();
+
+    for record in &history.records {
+        if previous_head == Some(&record.head) {
+            continue;
+        }
+        previous_head = Some(&record.head);
+
+        let (from, to) = range_millis(body);
+        let millis = record.timestamp * 1000;
+        if millis < from || millis > to {
+            continue;
+        }
+
+        annotations.push(json!({
+            "annotation": body["annotation"],
+            "time": millis,
+            "title": record.head_summary,
+            "text": format!("{} · {} TODOs", record.head.get(..7).unwrap_or(&record.head), record.stats.total),
+            "tags": ["todo"],
+        }));
+    }
+
+    json!(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::stats::Stats;
+    use crate::test_repo::TestRepo;
+
+    fn stats(total: usize, perf: usize) -> Stats {
+        Stats {
+            total,
+            by_tag: BTreeMap::from([("perf".to_string(), perf)]),
+            by_author: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn serves_the_recorded_history() {
+        let test = TestRepo::new("grafana");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        let mut history = History::load(&test.repo).unwrap();
+        history.record(&test.repo, stats(2, 1)).unwrap();
+        history.record(&test.repo, stats(1, 1)).unwrap();
+        test.write("b.rs", "fn b() {}\n");
+        test.commit("Alice", "b");
+        history.record(&test.repo, stats(0, 0)).unwrap();
+
+        let mut history = History::load(&test.repo).unwrap();
+        for (record, timestamp) in history.records.iter_mut().zip([1000, 2000, 3000]) {
+            record.timestamp = timestamp;
+        }
+
+        assert_eq!(search(&history), json!(["total", "tag:perf"]));
+        let body = json!({
+            "range": { "from": "1970-01-01T00:25:00Z" },
+            "targets": [{ "target": "total" }, { "target": "tag:perf" }, { "target": "x" }],
+        });
+        assert_eq!(
+            query(&history, &body),
+            json!([
+                { "target": "total", "datapoints": [[1, 2_000_000], [0, 3_000_000]] },
+                { "target": "tag:perf", "datapoints": [[1, 2_000_000], [0, 3_000_000]] },
+                { "target": "x", "datapoints": [] },
+            ])
+        );
+
+        // one per new commit scanned
+        let annotations = annotations(&history, &json!({}));
+        let marked: Vec<_> = annotations
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|annotation| (annotation["title"].clone(), annotation["time"].clone()))
+            .collect();
+        assert_eq!(
+            marked,
+            [
+                (json!("a"), json!(1_000_000)),
+                (json!("b"), json!(3_000_000))
+            ]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! `todo handover`: a Markdown document of everything one person has left to
+//! do, for offboarding or before a vacation.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use git2::Repository;
+
+use crate::cli::Options;
+use crate::{dates, pull_request, redact, repo_relative_path, scanned_source, Todo};
+
+/// Lines of surrounding code shown on each side of a TODO.
+const CONTEXT_LINES: usize = 3;
+
+/// Link to the line at `revision` on the `origin` remote's web interface.
+pub fn permalink(base: &str, revision: &str, path: &str, line: usize) -> String {
+    if base.contains("gitlab") {
+        format!("{base}/-/blob/{revision}/{path}#L{line}")
+    } else if base.contains("bitbucket") {
+        format!("{base}/src/{revision}/{path}#lines-{line}")
+    } else {
+        format!("{base}/blob/{revision}/{path}#L{line}")
+    }
+}
+
+/// The lines around a TODO, or `None` if the file no longer has its line.
+fn snippet(lines: &[&str], line: usize) -> Option<String> {
+    let index = line.checked_sub(1)?;
+    if index >= lines.len() {
+        return None;
+    }
+    let start = index.saturating_sub(CONTEXT_LINES);
+    let end = (index + CONTEXT_LINES + 1).min(lines.len());
+    Some(lines[start..end].join("\n"))
+}
+
+/// Renders the handover document for the TODOs owned by `author`.
+pub fn report(repo: &Repository, options: &Options, todos: &[Todo], author: &str) -> String {
+    let mut by_file: BTreeMap<String, Vec<&Todo>> = BTreeMap::new();
+    for todo in todos.iter().filter(|todo| todo.is_owned_by(author)) {
+        by_file
+            .entry(repo_relative_path(repo, &todo.file_path))
+            .or_default()
+            .push(todo);
+    }
+
+    let count: usize = by_file.values().map(Vec::len).sum();
+    let mut out = format!("# TODO handover: {author}\n\n");
+    if count == 0 {
+        let _ = writeln!(out, "No TODOs belong to {author}.");
+        return out;
+    }
+    let _ = writeln!(
+        out,
+        "{count} TODO{} in {} file{}, as of {}.",
+        if count == 1 { "" } else { "s" },
+        by_file.len(),
+        if by_file.len() == 1 { "" } else { "s" },
+        dates::absolute(dates::now()),
+    );
+
+    // link to the checked-out commit so the line numbers stay valid
+    let base = pull_request::web_url(repo);
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|id| id.to_string());
+
+    for (path, mut todos) in by_file {
+        todos.sort_by_key(|todo| todo.line);
+        let _ = writeln!(out, "\n## `{path}`");
+
+        let source = scanned_source(repo, options, &todos[0].file_path).unwrap_or_default();
+        let lines: Vec<&str> = source.lines().collect();
+        let fence = todos[0]
+            .file_path
+            .extension()
+            .map_or_else(String::new, |ext| ext.to_string_lossy().into_owned());
+
+        for todo in todos {
+            let _ = writeln!(
+                out,
+                "\n### Line {}: `{}`\n",
+                todo.line,
+                redact::shared(todo.raw.trim())
+            );
+
+            if todo.commit_hash.is_empty() {
+                let _ = writeln!(out, "- Not committed yet");
+            } else {
+                let written = todo.introduced_date.unwrap_or(todo.author_date);
+                let _ = writeln!(
+                    out,
+                    "- Written {} in `{}` {}",
+                    dates::ago(written),
+                    todo.short_hash,
+                    todo.commit_title
+                );
+            }
+            if let Some(pull_request) = &todo.pull_request {
+                let _ = writeln!(out, "- Pull request: {pull_request}");
+            }
+            if let (Some(base), Some(head)) = (&base, &head) {
+                let _ = writeln!(out, "- {}", permalink(base, head, &path, todo.line));
+            }
+
+            if let Some(snippet) = snippet(&lines, todo.line) {
+                let _ = writeln!(out, "\n```{fence}\n{snippet}\n```");
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permalinks_follow_each_host() {
+        assert_eq!(
+            permalink("https://github.com/o/r", "abc", "src/main.rs", 7),
+            "https://github.com/o/r/blob/abc/src/main.rs#L7"
+        );
+        assert_eq!(
+            permalink("https://gitlab.com/o/r", "abc", "a.rs", 7),
+            "https://gitlab.com/o/r/-/blob/abc/a.rs#L7"
+        );
+        assert_eq!(
+            permalink("https://bitbucket.org/o/r", "abc", "a.rs", 7),
+            "https://bitbucket.org/o/r/src/abc/a.rs#lines-7"
+        );
+    }
+}
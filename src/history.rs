@@ -0,0 +1,78 @@
+//! A record of past scans, kept inside the repository's `.git` directory so
+//! trends can be charted without an external database.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use chrono::Utc;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::Stats;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// The commit `HEAD` pointed at during the scan.
+    pub head: String,
+    pub head_summary: String,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+pub struct History {
+    path: PathBuf,
+    pub records: Vec<Record>,
+}
+
+impl History {
+    /// Loads the history stored in `.git/todo/history.jsonl`, skipping lines
+    /// that do not parse rather than failing on a damaged file.
+    pub fn load(repo: &Repository) -> io::Result<Self> {
+        let path = repo.path().join("todo").join("history.jsonl");
+
+        let records = match File::open(&path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok())
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self { path, records })
+    }
+
+    /// Appends the result of a scan of the current `HEAD`.
+    pub fn record(&mut self, repo: &Repository, stats: Stats) -> io::Result<()> {
+        let head = repo.head().and_then(|head| head.peel_to_commit()).ok();
+
+        let record = Record {
+            timestamp: Utc::now().timestamp(),
+            head: head
+                .as_ref()
+                .map(|commit| commit.id().to_string())
+                .unwrap_or_default(),
+            head_summary: head
+                .as_ref()
+                .and_then(|commit| commit.summary().map(str::to_string))
+                .unwrap_or_default(),
+            stats,
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+
+        self.records.push(record);
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+//! `todo hotspots`: ranks files by TODOs per 1000 lines, since raw counts
+//! unfairly single out large files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use colored::Colorize;
+
+use crate::{get_relative_or_absolute_path, Todo};
+
+struct Hotspot<'a> {
+    path: &'a Path,
+    todos: usize,
+    lines: usize,
+}
+
+impl Hotspot<'_> {
+    /// TODOs per 1000 lines.
+    #[allow(clippy::cast_precision_loss)] // counts stay far below 2^52
+    fn score(&self) -> f64 {
+        self.todos as f64 * 1000.0 / self.lines.max(1) as f64
+    }
+}
+
+/// The `top` files with the highest TODO density, densest first.
+fn rank(todos: &[Todo], top: usize) -> io::Result<Vec<Hotspot<'_>>> {
+    let mut counts: HashMap<&Path, usize> = HashMap::new();
+    for todo in todos {
+        *counts.entry(&todo.file_path).or_default() += 1;
+    }
+
+    let mut hotspots = Vec::with_capacity(counts.len());
+    for (path, todos) in counts {
+        let lines = fs::read_to_string(path)?.lines().count();
+        hotspots.push(Hotspot { path, todos, lines });
+    }
+    hotspots.sort_by(|a, b| {
+        b.score()
+            .total_cmp(&a.score())
+            .then_with(|| b.todos.cmp(&a.todos))
+            .then_with(|| a.path.cmp(b.path))
+    });
+    hotspots.truncate(top);
+    Ok(hotspots)
+}
+
+/// Prints the `top` files with the highest TODO density, densest first.
+pub fn report(todos: &[Todo], top: usize) -> io::Result<()> {
+    let hotspots = rank(todos, top)?;
+    if hotspots.is_empty() {
+        println!("✅ No TODOs found in the repository");
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("{:>9}  {:>5}  {:>7}  file", "per 1k", "TODOs", "lines").bold()
+    );
+    for hotspot in hotspots {
+        println!(
+            "{:>9.2}  {:>5}  {:>7}  {}",
+            hotspot.score(),
+            hotspot.todos,
+            hotspot.lines,
+            get_relative_or_absolute_path(hotspot.path)?.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn ranks_files_by_todos_per_line() {
+        let test = TestRepo::new("hotspots");
+        test.write("big.rs", &"fn a() {}\n// TODO: one\n".repeat(3));
+        test.write("small.rs", "// TODO: one\nfn a() {}\n");
+        test.write("dense.rs", "// TODO: one\n");
+        test.commit("Alice", "a");
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+
+        let hotspots = rank(&todos, 10).unwrap();
+        let ranked: Vec<_> = hotspots
+            .iter()
+            .map(|hotspot| hotspot.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        // the file with the most TODOs wins ties on density
+        assert_eq!(ranked, ["dense.rs", "big.rs", "small.rs"]);
+        assert_eq!((hotspots[1].todos, hotspots[1].lines), (3, 6));
+        assert!((hotspots[0].score() - 1000.0).abs() < f64::EPSILON);
+        assert_eq!(rank(&todos, 1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,387 @@
+//! A self-contained HTML report. Charts of TODO ages, tags and, when
+//! `todo serve` has recorded a history, the trend over time are drawn
+//! client-side from data embedded in the page, so the one file doubles as a
+//! lightweight dashboard.
+
+use std::fmt::Write as _;
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::github::strip_ansi;
+use crate::history::History;
+use crate::metadata::Metadata;
+use crate::stats::Stats;
+use crate::{dates, redact, repo_relative_path, stale_todos, Todo};
+
+const STYLE: &str = r"
+body { font: 14px/1.4 system-ui, sans-serif; margin: 2em auto; max-width: 72em; color: #222; }
+h1 { font-size: 1.6em; }
+.charts { display: flex; flex-wrap: wrap; gap: 2em; margin: 1.5em 0; }
+.chart { flex: 1 1 20em; }
+.chart h2 { font-size: 1em; color: #555; }
+svg { width: 100%; height: auto; }
+svg text { font-size: 11px; fill: #444; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: .35em .6em; border-bottom: 1px solid #eee; vertical-align: top; }
+th { cursor: pointer; user-select: none; }
+code { font-size: .95em; }
+.muted { color: #888; }
+.stale { color: #c0392b; }
+";
+
+const SCRIPT: &str = r##"
+const data = JSON.parse(document.getElementById("data").textContent);
+const svgNs = "http://www.w3.org/2000/svg";
+const colors = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#bab0ac"];
+
+function el(name, attributes, parent) {
+  const node = document.createElementNS(svgNs, name);
+  for (const [key, value] of Object.entries(attributes)) node.setAttribute(key, value);
+  parent.appendChild(node);
+  return node;
+}
+
+function text(content, attributes, parent) {
+  el("text", attributes, parent).textContent = content;
+}
+
+function chart(title) {
+  const container = document.createElement("div");
+  container.className = "chart";
+  container.innerHTML = `<h2>${title}</h2>`;
+  document.getElementById("charts").appendChild(container);
+  return el("svg", { viewBox: "0 0 320 200" }, container);
+}
+
+function histogram() {
+  const buckets = [["< 1 week", 7], ["< 1 month", 30], ["< 3 months", 91], ["< 1 year", 365], ["older", Infinity]];
+  const counts = buckets.map(() => 0);
+  for (const todo of data.todos) counts[buckets.findIndex(([, days]) => todo.age_days < days)]++;
+  const max = Math.max(1, ...counts);
+  const svg = chart("Age");
+  counts.forEach((count, i) => {
+    const height = 150 * count / max;
+    el("rect", { x: 10 + i * 62, y: 170 - height, width: 50, height, fill: colors[0] }, svg).appendChild(document.createElementNS(svgNs, "title")).textContent = `${count} TODOs`;
+    text(count, { x: 35 + i * 62, y: 165 - height, "text-anchor": "middle" }, svg);
+    text(buckets[i][0], { x: 35 + i * 62, y: 188, "text-anchor": "middle" }, svg);
+  });
+}
+
+function pie() {
+  const tags = Object.entries(data.by_tag).sort((a, b) => b[1] - a[1]);
+  const total = tags.reduce((sum, [, count]) => sum + count, 0);
+  if (total === 0) return;
+  const svg = chart("Tags");
+  let angle = -Math.PI / 2;
+  tags.forEach(([tag, count], i) => {
+    const color = colors[i % colors.length];
+    const sweep = 2 * Math.PI * count / total;
+    const [x1, y1] = [100 + 80 * Math.cos(angle), 100 + 80 * Math.sin(angle)];
+    angle += sweep;
+    const [x2, y2] = [100 + 80 * Math.cos(angle), 100 + 80 * Math.sin(angle)];
+    const d = tags.length === 1
+      ? "M 100 20 A 80 80 0 1 1 99.99 20 Z"
+      : `M 100 100 L ${x1} ${y1} A 80 80 0 ${sweep > Math.PI ? 1 : 0} 1 ${x2} ${y2} Z`;
+    el("path", { d, fill: color }, svg).appendChild(document.createElementNS(svgNs, "title")).textContent = `${tag}: ${count}`;
+    if (i < 10) {
+      el("rect", { x: 200, y: 20 + i * 17, width: 10, height: 10, fill: color }, svg);
+      text(`${tag} (${count})`, { x: 215, y: 29 + i * 17 }, svg);
+    }
+  });
+}
+
+function trend() {
+  const points = data.history;
+  if (points.length < 2) return;
+  const svg = chart("Trend");
+  const [first, last] = [points[0].timestamp, points[points.length - 1].timestamp];
+  const max = Math.max(1, ...points.map((point) => point.total));
+  const x = (t) => 30 + 280 * (t - first) / Math.max(1, last - first);
+  const y = (total) => 170 - 150 * total / max;
+  el("polyline", {
+    points: points.map((point) => `${x(point.timestamp)},${y(point.total)}`).join(" "),
+    fill: "none", stroke: colors[2], "stroke-width": 2,
+  }, svg);
+  text(max, { x: 25, y: 24, "text-anchor": "end" }, svg);
+  text(0, { x: 25, y: 174, "text-anchor": "end" }, svg);
+  text(new Date(first * 1000).toLocaleDateString(), { x: 30, y: 188 }, svg);
+  text(new Date(last * 1000).toLocaleDateString(), { x: 310, y: 188, "text-anchor": "end" }, svg);
+}
+
+function sortable() {
+  document.querySelectorAll("th").forEach((th) => th.addEventListener("click", () => {
+    const column = th.cellIndex;
+    const body = th.closest("table").tBodies[0];
+    const ascending = th.dataset.order !== "asc";
+    th.dataset.order = ascending ? "asc" : "desc";
+    const key = (row) => row.cells[column].dataset.sort ?? row.cells[column].textContent;
+    [...body.rows]
+      .sort((a, b) => key(a).localeCompare(key(b), undefined, { numeric: true }) * (ascending ? 1 : -1))
+      .forEach((row) => body.appendChild(row));
+  }));
+}
+
+histogram();
+pie();
+trend();
+sortable();
+"##;
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn report(repo: &Repository, todos: &[Todo], metadata: &Metadata) -> String {
+    let stats = Stats::compute(todos);
+    let history: Vec<_> = History::load(repo)
+        .map(|history| history.records)
+        .unwrap_or_default()
+        .iter()
+        .map(|record| json!({ "timestamp": record.timestamp, "total": record.stats.total }))
+        .collect();
+    let data = json!({
+        "todos": todos
+            .iter()
+            .map(|todo| json!({ "age_days": (dates::now() - todo.author_date).num_days() }))
+            .collect::<Vec<_>>(),
+        "by_tag": stats.by_tag,
+        "history": history,
+    });
+
+    let mut rows = String::new();
+    for todo in todos {
+        row(&mut rows, repo, todo);
+    }
+
+    let stale = stale_todos(todos);
+    let stale = if stale.is_empty() {
+        String::new()
+    } else {
+        let mut stale_rows = String::new();
+        for todo in &stale {
+            row(&mut stale_rows, repo, todo);
+        }
+        format!(
+            "<h2 class=\"stale\">{} stale</h2>\n<table>\n{HEADER}\n<tbody>\n{stale_rows}</tbody>\n</table>\n",
+            stale.len()
+        )
+    };
+
+    let repo_name = repo
+        .workdir()
+        .and_then(|dir| dir.file_name())
+        .map_or_else(|| "repository".into(), |name| name.to_string_lossy());
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>TODOs in {name}</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<h1>{count} TODOs in {name}</h1>
+<p class=\"muted\">Generated {generated}</p>
+{metadata}{stale}<div class=\"charts\" id=\"charts\"></div>
+<table>
+{HEADER}
+<tbody>
+{rows}</tbody>
+</table>
+<script type=\"application/json\" id=\"data\">{data}</script>
+<script>{SCRIPT}</script>
+</body>
+</html>
+",
+        name = escape(&repo_name),
+        count = todos.len(),
+        generated = escape(&dates::absolute(dates::now())),
+        metadata = metadata.html(),
+        // keep `</script>` in the data from closing the element
+        data = data.to_string().replace("</", "<\\/"),
+    )
+}
+
+const HEADER: &str = "<thead><tr><th>Location</th><th>TODO</th><th>Tags</th><th>Author</th>\
+                      <th>Commit</th><th>Age</th></tr></thead>";
+
+fn row(rows: &mut String, repo: &Repository, todo: &Todo) {
+    let path = repo_relative_path(repo, &todo.file_path);
+    writeln!(
+        rows,
+        "<tr><td><code>{}:{}</code></td><td>{}</td><td>{}</td><td>{}</td>\
+             <td>{}</td><td data-sort=\"{}\">{}</td></tr>",
+        escape(&path),
+        todo.line,
+        escape(&redact::shared(&strip_ansi(todo.statement.trim()))),
+        escape(&todo.tags.join(", ")),
+        escape(&todo.author),
+        escape(&todo.commit_title),
+        todo.author_date.timestamp(),
+        escape(&dates::ago(todo.author_date)),
+    )
+    .unwrap();
+}
+
+const WORKSPACE_STYLE: &str = r"
+details { margin: .2em 0 .2em 1.2em; }
+body > details { margin-left: 0; border-bottom: 1px solid #eee; padding: .3em 0; }
+summary { cursor: pointer; }
+ul { margin: .2em 0; }
+";
+
+/// A count in parentheses, for `<summary>` lines.
+fn counted(label: &str, value: &serde_json::Value) -> String {
+    format!(
+        "{} <span class=\"muted\">({})</span>",
+        escape(label),
+        value["count"].as_u64().unwrap_or_default()
+    )
+}
+
+/// The levels below a node of grouped JSON as nested collapsible sections,
+/// with the TODOs listed at the bottom.
+fn write_levels(sections: &mut String, node: &serde_json::Value) {
+    if let Some(todos) = node["todos"].as_array() {
+        sections.push_str("<ul>\n");
+        for todo in todos {
+            writeln!(
+                sections,
+                "<li><code>{}:{}</code> {}</li>",
+                escape(todo["path"].as_str().unwrap_or_default()),
+                todo["line"],
+                escape(todo["raw"].as_str().unwrap_or_default().trim()),
+            )
+            .unwrap();
+        }
+        sections.push_str("</ul>\n");
+        return;
+    }
+    for (list, field) in [
+        ("groups", "title"),
+        ("tags", "tag"),
+        ("authors", "author"),
+        ("files", "file"),
+        ("dirs", "dir"),
+    ] {
+        for group in node[list].as_array().into_iter().flatten() {
+            // untagged TODOs have a null tag
+            let label = group[field].as_str().unwrap_or("untagged");
+            writeln!(
+                sections,
+                "<details><summary>{}</summary>",
+                counted(label, group)
+            )
+            .unwrap();
+            write_levels(sections, group);
+            sections.push_str("</details>\n");
+        }
+    }
+}
+
+/// The aggregate report of `todo workspace --format html`: a table of the
+/// repositories with their counts and trends, then each repository as
+/// collapsible levels following `--group-by`.
+pub fn workspace_report(report: &serde_json::Value) -> String {
+    let repositories = report["repositories"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut rows = String::new();
+    let mut sections = String::new();
+    for (index, repository) in repositories.iter().enumerate() {
+        let name = repository["repository"].as_str().unwrap_or_default();
+        let trend = &repository["trend"];
+        let trend = if trend.is_null() {
+            String::new()
+        } else {
+            format!("+{} / −{}", trend["added"], trend["removed"])
+        };
+        writeln!(
+            rows,
+            "<tr><td><a href=\"#repo-{index}\">{}</a></td><td>{}</td><td>{trend}</td>\
+             <td><code>{}</code></td></tr>",
+            escape(name),
+            repository["count"],
+            escape(repository["base"].as_str().unwrap_or_default()),
+        )
+        .unwrap();
+
+        writeln!(
+            sections,
+            "<details id=\"repo-{index}\"><summary><strong>{}</strong></summary>",
+            counted(name, repository)
+        )
+        .unwrap();
+        write_levels(&mut sections, repository);
+        sections.push_str("</details>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>TODOs across {repos} repositories</title>
+<style>{STYLE}{WORKSPACE_STYLE}</style>
+</head>
+<body>
+<h1>{count} TODOs across {repos} repositories</h1>
+<p class=\"muted\">Generated {generated}</p>
+<table>
+<thead><tr><th>Repository</th><th>TODOs</th><th>Trend</th><th>Base</th></tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+{sections}</body>
+</html>
+",
+        repos = repositories.len(),
+        count = report["count"],
+        generated = escape(&dates::absolute(dates::now())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn embeds_chart_data_and_escapes_the_table() {
+        let test = TestRepo::new("html");
+        test.write(
+            "a.rs",
+            "// TODO(perf): <b>bold</b>\n// TODO(perf): end </script>\n",
+        );
+        test.commit("Alice", "a");
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let page = report(&test.repo, &todos, &Metadata::new(&test.repo, &options));
+
+        assert!(page.contains("<h1>2 TODOs in "));
+        assert!(page.contains("&lt;b&gt;bold&lt;/b&gt;"));
+        // only the closing tag of the script itself ends it
+        assert_eq!(page.matches("</script>").count(), 2);
+
+        let start = page.find("id=\"data\">").unwrap() + "id=\"data\">".len();
+        let end = start + page[start..].find("</script>").unwrap();
+        let data: serde_json::Value =
+            serde_json::from_str(&page[start..end].replace("<\\/", "</")).unwrap();
+        assert_eq!(data["todos"].as_array().unwrap().len(), 2);
+        assert!(data["todos"][0]["age_days"].as_i64().unwrap() > 0);
+        assert_eq!(data["by_tag"]["perf"], 2);
+        assert_eq!(data["history"], json!([]));
+    }
+}
@@ -0,0 +1,512 @@
+//! Minimal HTTP client built on the system `curl`, shared by every
+//! integration that talks to an external API. Requests are retried with
+//! exponential backoff, rate limits are waited out, and `--dry-run` prints
+//! requests instead of sending them.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::credentials::{self, Provider};
+use crate::dry_run;
+
+/// Request bodies longer than this are cut short when printed by `--dry-run`.
+const DRY_RUN_BODY_LIMIT: usize = 4096;
+
+/// The URL without its query string and password, which hold the signature
+/// of presigned URLs and should not end up in CI logs.
+pub fn redact(url: &str) -> String {
+    Url::parse(url).map_or_else(
+        |_| url.to_string(),
+        |mut url| {
+            url.set_query(None);
+            if url.password().is_some() {
+                let _ = url.set_password(Some("***"));
+            }
+            url.to_string()
+        },
+    )
+}
+
+/// Headers whose values are credentials, hidden by `--dry-run`.
+fn is_secret_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["auth", "token", "key", "cookie", "secret"]
+        .iter()
+        .any(|secret| name.contains(secret))
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    /// Headers of the final response, after redirects.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub const fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// How long the server asks clients to wait before trying again, from
+    /// `Retry-After` (in seconds or as a date) or, as GitHub sends them, an
+    /// exhausted `X-RateLimit-Remaining` with its `X-RateLimit-Reset` time.
+    fn retry_after(&self) -> Option<Duration> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+
+        if let Some(value) = self.header("Retry-After") {
+            if let Ok(seconds) = value.trim().parse() {
+                return Some(Duration::from_secs(seconds));
+            }
+            let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+            let at = Duration::from_secs(u64::try_from(date.timestamp()).ok()?);
+            return Some(at.saturating_sub(now));
+        }
+
+        if self.header("X-RateLimit-Remaining")?.trim() == "0" {
+            let reset = self.header("X-RateLimit-Reset")?.trim().parse().ok()?;
+            return Some(Duration::from_secs(reset).saturating_sub(now));
+        }
+        None
+    }
+}
+
+/// How often and how patiently a request is retried.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    /// Attempts in total, including the first.
+    pub attempts: u32,
+    /// Wait before the second attempt, doubling before each further one.
+    pub initial_delay: Duration,
+    /// Longest wait between attempts. Rate limits that reset later than this
+    /// fail the request instead.
+    pub max_delay: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_mins(1),
+        }
+    }
+}
+
+impl Retry {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay)
+    }
+}
+
+/// A line of a curl config file setting `option`, quoted so any value is
+/// read back as is.
+fn config_line(option: &str, value: &str) -> String {
+    let value = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("{option} = \"{value}\"\n")
+}
+
+static BODY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A request body in a file only its owner can read, removed when dropped.
+struct BodyFile(PathBuf);
+
+impl BodyFile {
+    fn new(body: &[u8]) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "todo-body-{}-{}",
+            std::process::id(),
+            BODY_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        options.open(&path)?.write_all(body)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for BodyFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// An `Authorization` header with a stored token, looked up each time the
+/// request is sent so queued requests don't keep it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Authorization {
+    scheme: String,
+    provider: Provider,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    authorization: Option<Authorization>,
+    proxy: Option<String>,
+    body: Option<Vec<u8>>,
+    #[serde(skip)]
+    retry: Retry,
+}
+
+impl Request {
+    pub fn new(method: &str, url: impl Into<String>) -> Self {
+        Self {
+            method: method.to_string(),
+            url: url.into(),
+            headers: Vec::new(),
+            authorization: None,
+            proxy: None,
+            body: None,
+            retry: Retry::default(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Authorizes the request as `{scheme} {token}` with `provider`'s token,
+    /// if it has one when the request is sent.
+    pub fn authorization(mut self, scheme: &str, provider: Provider) -> Self {
+        self.authorization = Some(Authorization {
+            scheme: scheme.to_string(),
+            provider,
+        });
+        self
+    }
+
+    /// The headers to send, with the token looked up.
+    fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        if let Some(authorization) = &self.authorization {
+            if let Some(token) = credentials::token(authorization.provider) {
+                let value = format!("{} {token}", authorization.scheme);
+                headers.push(("Authorization".to_string(), value));
+            }
+        }
+        headers
+    }
+
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn json(self, json: &serde_json::Value) -> Self {
+        self.header("Content-Type", "application/json")
+            .body(json.to_string().into_bytes())
+    }
+
+    pub const fn retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Methods that can be repeated without repeating their effect, so they
+    /// are safe to retry after failures that may have reached the server.
+    fn is_idempotent(&self) -> bool {
+        matches!(
+            self.method.as_str(),
+            "GET" | "HEAD" | "PUT" | "DELETE" | "OPTIONS"
+        )
+    }
+
+    /// How long to wait before retrying after this attempt's `result`, or
+    /// `None` to return it as is.
+    fn retry_delay(&self, result: &io::Result<Response>, attempt: u32) -> Option<Duration> {
+        if attempt >= self.retry.attempts {
+            return None;
+        }
+        let backoff = self.retry.backoff(attempt);
+
+        match result {
+            Err(_) => self.is_idempotent().then_some(backoff),
+            // rejected before being processed, so safe to retry for any method
+            Ok(response) if matches!(response.status, 403 | 429 | 503) => {
+                let Some(wait) = response.retry_after() else {
+                    return (response.status != 403).then_some(backoff);
+                };
+                (wait <= self.retry.max_delay).then_some(wait)
+            }
+            Ok(response) if matches!(response.status, 500 | 502 | 504) => {
+                self.is_idempotent().then_some(backoff)
+            }
+            Ok(_) => None,
+        }
+    }
+
+    /// Sends the request and returns the response, whatever its status,
+    /// retrying connection failures, server errors and rate limiting.
+    ///
+    /// Fails only if `curl` cannot be run or the request never completes.
+    pub fn send(&self) -> io::Result<Response> {
+        if dry_run::enabled() {
+            self.print();
+            return Ok(Response {
+                status: 200,
+                headers: Vec::new(),
+                body: String::new(),
+            });
+        }
+
+        let headers = self.headers();
+        let mut attempt = 1;
+        loop {
+            let result = self.send_once(&headers);
+            let Some(delay) = self.retry_delay(&result, attempt) else {
+                return result;
+            };
+
+            let reason = match &result {
+                Ok(response) => response.status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            eprintln!(
+                "{} {} failed ({reason}), retrying in {:.1}s",
+                self.method,
+                redact(&self.url),
+                delay.as_secs_f64()
+            );
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Prints the request for `--dry-run`, with credentials hidden.
+    fn print(&self) {
+        eprintln!("[dry run] {} {}", self.method, redact(&self.url));
+        for (name, value) in &self.headers {
+            let value = if is_secret_header(name) { "***" } else { value };
+            eprintln!("  {name}: {value}");
+        }
+        if self.authorization.is_some() {
+            eprintln!("  Authorization: ***");
+        }
+        if let Some(body) = &self.body {
+            let text = String::from_utf8_lossy(body);
+            if text.len() > DRY_RUN_BODY_LIMIT {
+                let end = (0..=DRY_RUN_BODY_LIMIT)
+                    .rev()
+                    .find(|&end| text.is_char_boundary(end))
+                    .unwrap_or_default();
+                eprintln!("{}… ({} bytes)", &text[..end], body.len());
+            } else {
+                eprintln!("{text}");
+            }
+        }
+    }
+
+    /// The curl options that may hold credentials, as a config file for
+    /// `--config -`, so they never show up in the process list.
+    fn curl_config(&self, headers: &[(String, String)], body: Option<&Path>) -> String {
+        let mut config = config_line("url", &self.url);
+        for (name, value) in headers {
+            config.push_str(&config_line("header", &format!("{name}: {value}")));
+        }
+        if let Some(proxy) = &self.proxy {
+            config.push_str(&config_line("proxy", proxy));
+        }
+        if let Some(body) = body {
+            config.push_str(&config_line("data-binary", &format!("@{}", body.display())));
+        }
+        config
+    }
+
+    fn send_once(&self, headers: &[(String, String)]) -> io::Result<Response> {
+        // stdin carries the config, so the body goes through a file
+        let body = self.body.as_deref().map(BodyFile::new).transpose()?;
+
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--location"])
+            .args(["--dump-header", "-"])
+            .args(["--request", &self.method])
+            .args(["--write-out", "\n%{http_code}"])
+            .args(["--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let config = self.curl_config(headers, body.as_ref().map(|body| body.0.as_path()));
+        child.stdin.take().unwrap().write_all(config.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (mut rest, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+
+        // a header block precedes the body for every response, including
+        // redirects and `100 Continue`, so keep the last one
+        let mut headers = Vec::new();
+        while rest.starts_with("HTTP/") {
+            let Some((block, body)) = rest.split_once("\r\n\r\n") else {
+                break;
+            };
+            headers = block
+                .lines()
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .collect();
+            rest = body;
+        }
+
+        Ok(Response {
+            status: status.trim().parse().unwrap_or(0),
+            headers,
+            body: rest.to_string(),
+        })
+    }
+
+    /// Like [`Request::send`], but also fails unless the server answers with
+    /// a 2xx status.
+    pub fn send_ok(&self) -> io::Result<Response> {
+        let response = self.send()?;
+        if !response.is_success() {
+            return Err(self.status_error(&response));
+        }
+        Ok(response)
+    }
+
+    /// The error for a response with an unsuccessful status.
+    pub fn status_error(&self, response: &Response) -> io::Error {
+        io::Error::other(format!(
+            "{} {} returned {}: {}",
+            self.method,
+            redact(&self.url),
+            response.status,
+            response.body
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    #[test]
+    fn retries_until_the_server_recovers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let responses = [
+                "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\n",
+                "HTTP/1.1 502 Bad Gateway\r\n",
+                "HTTP/1.1 200 OK\r\nX-Attempt: 3\r\n",
+            ];
+            for head in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                write!(
+                    stream,
+                    "{head}Content-Length: 2\r\nConnection: close\r\n\r\nok"
+                )
+                .unwrap();
+            }
+        });
+
+        let response = Request::new("GET", url)
+            .retry(Retry {
+                initial_delay: Duration::from_millis(1),
+                ..Retry::default()
+            })
+            .send()
+            .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("x-attempt"), Some("3"));
+        assert_eq!(response.body, "ok");
+    }
+
+    #[test]
+    fn sends_headers_and_body_through_curl_config() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/write?db=a%20b", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut head = Vec::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                head.push(line.trim_end().to_string());
+                line.clear();
+            }
+            let length: usize = head
+                .iter()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; length];
+            io::Read::read_exact(&mut reader, &mut body).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            (head, body)
+        });
+
+        let body = b"line \"one\"\n\\two\0".to_vec();
+        let response = Request::new("POST", url)
+            .header("Authorization", "Bearer \"secret\\\"")
+            .body(body.clone())
+            .send()
+            .unwrap();
+        let (head, received) = server.join().unwrap();
+
+        assert_eq!(response.status, 204);
+        assert_eq!(head[0], "POST /write?db=a%20b HTTP/1.1");
+        assert!(head.contains(&"Authorization: Bearer \"secret\\\"".to_string()));
+        assert_eq!(received, body);
+    }
+}
@@ -0,0 +1,80 @@
+//! Stable identifiers for TODOs, such as `3f9a1c2e`, for referring to one
+//! across commits, as `todo done 3f9a1c2e` does. An ID hashes the TODO's path
+//! and normalized text, so it survives lines shifting above it, re-indenting
+//! and changes to comment syntax, but not moving the TODO to another file or
+//! rewording it. Identical TODOs in one file are told apart by their order.
+
+use std::fmt::Write;
+
+use git2::{ObjectType, Oid};
+
+use crate::matcher::Matcher;
+use crate::{normalize_statement, Todo};
+
+/// Hex digits in an ID.
+const LENGTH: usize = 8;
+
+/// The ID of the `occurrence`th (from 0) TODO reading `normalized` in the file
+/// at `path`, relative to the repository root.
+fn id(path: &str, normalized: &str, occurrence: usize) -> String {
+    let mut key = format!("{path}\0{normalized}");
+    if occurrence > 0 {
+        let _ = write!(key, "\0{occurrence}");
+    }
+    // git's own hashing, which stays the same from one build to the next
+    let oid = Oid::hash_object(ObjectType::Blob, key.as_bytes()).unwrap_or_else(|_| Oid::zero());
+    oid.to_string()[..LENGTH].to_string()
+}
+
+/// How many TODOs before `line` in `lines` read `normalized`.
+fn occurrence(matcher: &Matcher, lines: &[&str], line: usize, normalized: &str) -> usize {
+    lines
+        .iter()
+        .take(line.saturating_sub(1))
+        .filter(|text| matcher.is_match(text) && normalize_statement(matcher, text) == normalized)
+        .count()
+}
+
+/// Sets the ID of each of `todos`, all found in `lines` of the file at
+/// `path`, counting identical TODOs in those lines, so the same ID comes out
+/// whichever of them were reported.
+pub fn assign(matcher: &Matcher, path: &str, lines: &[&str], todos: &mut [Todo]) {
+    for todo in todos {
+        let occurrence = occurrence(matcher, lines, todo.line, &todo.normalized);
+        todo.id = id(path, &todo.normalized, occurrence);
+    }
+}
+
+/// Whether `target` looks like an ID or a prefix of one, of at least four
+/// digits like git's abbreviated hashes.
+pub fn is_id(target: &str) -> bool {
+    (4..=LENGTH).contains(&target.len()) && target.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_follow_text_not_position() {
+        let first = id("src/a.rs", "handle errors", 0);
+        assert_eq!(first.len(), LENGTH);
+        assert_eq!(first, id("src/a.rs", "handle errors", 0));
+        assert_ne!(first, id("src/a.rs", "handle errors", 1));
+        assert_ne!(first, id("src/b.rs", "handle errors", 0));
+
+        let lines = [
+            "// TODO: handle errors",
+            "fn a() {}",
+            "    /* TODO: handle errors */",
+            "// TODO: other",
+        ];
+        let matcher = Matcher::default();
+        assert_eq!(occurrence(&matcher, &lines, 1, "handle errors"), 0);
+        assert_eq!(occurrence(&matcher, &lines, 3, "handle errors"), 1);
+        assert_eq!(occurrence(&matcher, &lines, 4, "other"), 0);
+
+        assert!(is_id("3f9a1c2e") && is_id("3f9a"));
+        assert!(!is_id("3f9") && !is_id("src/a.rs:3"));
+    }
+}
@@ -0,0 +1,238 @@
+//! `todo init`: writes a starter `.todo.toml` and `.todoignore` for a
+//! repository, asking about what it detects: the base branch, the languages
+//! in use and the vendored and generated code not worth scanning.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use git2::Repository;
+use walkdir::WalkDir;
+
+use crate::cli::Options;
+use crate::config::{FILE, IGNORE_FILE};
+use crate::matcher::Preset;
+use crate::severity::Severity;
+use crate::{base, dry_run, language};
+
+/// Directories that usually hold someone else's code.
+const VENDORED_DIRS: [&str; 12] = [
+    "vendor",
+    "vendored",
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "external",
+    "extern",
+    "deps",
+    "node_modules",
+    "bower_components",
+    "Pods",
+    "Carthage",
+];
+
+/// Suggested besides TODO, each with its usual severity.
+const DEFAULT_KEYWORDS: [&str; 4] = ["FIXME", "BUG", "HACK", "XXX"];
+
+/// How deep to look for vendored directories.
+const VENDORED_MAX_DEPTH: usize = 3;
+
+/// Generated files, by the language they are typical of.
+const GENERATED: [(&str, &[&str]); 6] = [
+    ("Go", &["*.pb.go", "zz_generated*.go"]),
+    ("Python", &["*_pb2.py", "*_pb2_grpc.py"]),
+    ("JavaScript", &["*.min.js", "dist/"]),
+    ("TypeScript", &["*.d.ts", "dist/"]),
+    ("C#", &["*.Designer.cs", "*.g.cs"]),
+    ("CSS", &["*.min.css"]),
+];
+
+/// Languages in the working tree by file count, most used first, skipping
+/// what git ignores.
+fn languages(root: &Path) -> Vec<&'static str> {
+    let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let walk = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    for entry in walk.flatten() {
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            *counts.entry(language::language(entry.path())).or_default() += 1;
+        }
+    }
+    counts.remove("Other");
+
+    let mut languages: Vec<_> = counts.into_iter().collect();
+    languages.sort_by_key(|(_, count)| Reverse(*count));
+    languages
+        .into_iter()
+        .map(|(language, _)| language)
+        .collect()
+}
+
+/// Vendored directories near the top of the tree, as `.todoignore` patterns.
+/// They are often committed, so unlike languages this ignores `.gitignore`.
+fn vendored(root: &Path) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut entries = WalkDir::new(root)
+        .max_depth(VENDORED_MAX_DEPTH)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != ".git");
+    while let Some(entry) = entries.next() {
+        let Ok(entry) = entry else { continue };
+        let name = entry.file_name().to_string_lossy();
+        if entry.file_type().is_dir() && VENDORED_DIRS.contains(&name.as_ref()) {
+            let path = entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or_else(|_| entry.path());
+            dirs.push(format!("/{}/", path.to_string_lossy().replace('\\', "/")));
+            entries.skip_current_dir();
+        }
+    }
+    dirs
+}
+
+/// Asks a question on stderr, returning `default` for an empty answer.
+fn ask(question: &str, default: &str) -> io::Result<String> {
+    eprint!("{question} [{default}]: ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+fn toml_string(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+/// A key, quoted unless it is a bare key.
+fn toml_key(s: &str) -> String {
+    let bare = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        s.to_string()
+    } else {
+        toml_string(s)
+    }
+}
+
+/// Writes both files, asking before each choice unless `assume_yes` or stdin
+/// is not a terminal. Existing files are kept unless `force`.
+pub fn run(repo: &Repository, options: &Options, assume_yes: bool, force: bool) -> io::Result<()> {
+    let root = repo.workdir().unwrap();
+    if !force {
+        for file in [FILE, IGNORE_FILE] {
+            if root.join(file).exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{file} already exists; pass --force to overwrite it"),
+                ));
+            }
+        }
+    }
+    let interactive = !assume_yes && io::stdin().is_terminal();
+
+    let languages = languages(root);
+    let mut ignored = vendored(root);
+    for (language, patterns) in GENERATED {
+        if languages.contains(&language) {
+            for pattern in patterns {
+                if !ignored.iter().any(|ignored| ignored == pattern) {
+                    ignored.push((*pattern).to_string());
+                }
+            }
+        }
+    }
+    if !languages.is_empty() {
+        eprintln!("Detected {}", languages.join(", "));
+    }
+
+    let mut base = base::name(repo, options);
+    let mut keywords = DEFAULT_KEYWORDS.join(", ");
+    let mut preset = "default".to_string();
+    if interactive {
+        base = ask("Base branch", &base)?;
+        keywords = ask(
+            "Keywords to match besides TODO, comma-separated, as WORD or WORD=SEVERITY",
+            &keywords,
+        )?;
+        preset = ask("How strictly to match: loose, default or strict", &preset)?;
+        preset.parse::<Preset>().map_err(io::Error::other)?;
+        if !ignored.is_empty() {
+            eprintln!("Not worth scanning:");
+            for pattern in &ignored {
+                eprintln!("  {pattern}");
+            }
+            if !ask("Ignore these? (y/n)", "y")?
+                .to_lowercase()
+                .starts_with('y')
+            {
+                ignored.clear();
+            }
+        }
+    }
+    let mut severities = Options::default();
+    for keyword in keywords
+        .split(',')
+        .filter(|keyword| !keyword.trim().is_empty())
+    {
+        severities
+            .add_keyword(keyword)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+    let mut keywords = String::new();
+    for (index, keyword) in severities.keywords.iter().enumerate() {
+        if severities.keywords[..index].contains(keyword) {
+            continue;
+        }
+        let severity = severities
+            .severities
+            .iter()
+            .rev()
+            .find(|(configured, _)| configured == keyword)
+            .map_or_else(|| Severity::of_keyword(keyword), |(_, severity)| *severity);
+        let _ = writeln!(keywords, "{} = \"{severity}\"", toml_key(keyword));
+    }
+
+    let config = format!(
+        "\
+# Settings for `todo`, shared by everyone working on this repository.
+# Flags given on the command line win.
+
+# What branches are compared against.
+base = {}
+
+# How strictly lines must look like TODOs: loose, default or strict.
+match = {}
+
+# Keywords matched besides TODO, with their severity: low, medium or high.
+[keywords]
+{keywords}",
+        toml_string(&base),
+        toml_string(&preset),
+    );
+
+    let mut ignore = String::from("# Paths `todo` never scans, in .gitignore syntax.\n");
+    if !languages.is_empty() {
+        let _ = writeln!(ignore, "# Detected languages: {}", languages.join(", "));
+    }
+    for pattern in &ignored {
+        let _ = writeln!(ignore, "{pattern}");
+    }
+
+    let written = dry_run::write(&root.join(FILE), FILE, &config)?;
+    dry_run::write(&root.join(IGNORE_FILE), IGNORE_FILE, &ignore)?;
+    if written {
+        eprintln!("Wrote {FILE} and {IGNORE_FILE}");
+    }
+    Ok(())
+}
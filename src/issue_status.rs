@@ -0,0 +1,132 @@
+//! `--check-issues`: asks the tracker about each issue TODOs reference, as
+//! `TODO(#123)` on GitHub or `TODO(PROJ-42)` on Jira, and flags the TODOs whose
+//! issue is already resolved. Those are usually dead comments: the work was
+//! done, or declined, and nobody came back to delete them.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+
+use git2::Repository;
+
+use crate::credentials::{self, Provider};
+use crate::http::Request;
+use crate::{fields, github_issues, Todo};
+
+/// The environment variable holding the Jira site, e.g.
+/// `https://acme.atlassian.net`.
+const JIRA_URL_VAR: &str = "JIRA_URL";
+/// With a Jira Cloud API token, the email address it belongs to.
+const JIRA_EMAIL_VAR: &str = "JIRA_EMAIL";
+
+/// Base64 with padding, for HTTP basic authentication.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Asks the tracker whether `issue` is resolved: closed on GitHub, or in a
+/// status of the done category on Jira. `None` for references to nothing
+/// this can check.
+fn resolved(github: Option<&str>, jira: Option<&str>, issue: &str) -> Result<Option<bool>, String> {
+    if let Some(number) = issue.strip_prefix('#') {
+        let Some(endpoint) = github else {
+            return Ok(None);
+        };
+        let token = credentials::token(Provider::Github);
+        let request = github_issues::headers(
+            Request::new("GET", format!("{endpoint}/{number}")),
+            token.as_deref(),
+        );
+        let body = get(&request)?;
+        return Ok(body["state"].as_str().map(|state| state == "closed"));
+    }
+
+    let Some(site) = jira.filter(|_| fields::is_issue(issue)) else {
+        return Ok(None);
+    };
+    let mut request = Request::new(
+        "GET",
+        format!("{site}/rest/api/2/issue/{issue}?fields=status"),
+    )
+    .header("Accept", "application/json");
+    if let Some(token) = credentials::token(Provider::Jira) {
+        // Jira Cloud takes API tokens as a password, Data Center as a bearer
+        let authorization = env::var(JIRA_EMAIL_VAR).map_or_else(
+            |_| format!("Bearer {token}"),
+            |email| format!("Basic {}", base64(format!("{email}:{token}").as_bytes())),
+        );
+        request = request.header("Authorization", authorization);
+    }
+    let body = get(&request)?;
+    Ok(body["fields"]["status"]["statusCategory"]["key"]
+        .as_str()
+        .map(|category| category == "done"))
+}
+
+fn get(request: &Request) -> Result<serde_json::Value, String> {
+    let response = request.send_ok().map_err(|e| e.to_string())?;
+    if response.body.is_empty() {
+        // --dry-run
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_str(&response.body).map_err(|e| e.to_string())
+}
+
+/// Marks the TODOs whose issue is resolved, asking about each issue once.
+pub fn mark_resolved(repo: &Repository, todos: &mut [Todo]) {
+    let github = github_issues::endpoint(repo).ok();
+    let jira = env::var(JIRA_URL_VAR)
+        .ok()
+        .map(|site| site.trim_end_matches('/').to_string());
+
+    let mut answers: HashMap<String, Option<bool>> = HashMap::new();
+    let mut failures = String::new();
+    for todo in todos.iter_mut() {
+        let Some(issue) = &todo.fields.issue else {
+            continue;
+        };
+        if !answers.contains_key(issue) {
+            let answer = resolved(github.as_deref(), jira.as_deref(), issue).unwrap_or_else(|e| {
+                let _ = write!(failures, "\n  {issue}: {e}");
+                None
+            });
+            answers.insert(issue.clone(), answer);
+        }
+        todo.issue_resolved = answers[issue] == Some(true);
+    }
+    if !failures.is_empty() {
+        eprintln!("Error checking issues:{failures}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_basic_credentials() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(
+            base64(b"ann@example.com:t0ken"),
+            "YW5uQGV4YW1wbGUuY29tOnQwa2Vu"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Per-language breakdown of TODOs, so polyglot repositories can see which
+//! parts of the stack carry the most debt.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use colored::Colorize;
+use git2::Repository;
+
+use crate::cli::Options;
+use crate::{get_diff_with_main, Todo};
+
+const OTHER: &str = "Other";
+
+/// Names the language of a file from its extension, or its name for files
+/// like `Dockerfile` that conventionally have none.
+pub fn language(path: &Path) -> &'static str {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match name.as_str() {
+        "dockerfile" | "containerfile" => return "Dockerfile",
+        "makefile" | "gnumakefile" => return "Makefile",
+        "cmakelists.txt" => return "CMake",
+        _ => {}
+    }
+
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rs" => "Rust",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "C++",
+        "cs" => "C#",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "scala" | "sc" => "Scala",
+        "swift" => "Swift",
+        "m" | "mm" => "Objective-C",
+        "py" | "pyi" => "Python",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "pl" | "pm" => "Perl",
+        "lua" => "Lua",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "mts" | "cts" | "tsx" => "TypeScript",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" | "less" => "CSS",
+        "sh" | "bash" | "zsh" | "fish" => "Shell",
+        "ps1" | "psm1" => "PowerShell",
+        "sql" => "SQL",
+        "proto" => "Protobuf",
+        "hs" => "Haskell",
+        "ml" | "mli" => "OCaml",
+        "ex" | "exs" => "Elixir",
+        "erl" | "hrl" => "Erlang",
+        "clj" | "cljs" | "cljc" => "Clojure",
+        "dart" => "Dart",
+        "zig" => "Zig",
+        "nix" => "Nix",
+        "tf" | "hcl" => "HCL",
+        "toml" => "TOML",
+        "yaml" | "yml" => "YAML",
+        "json" => "JSON",
+        "xml" => "XML",
+        "md" | "markdown" => "Markdown",
+        _ => OTHER,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LanguageStats {
+    pub todos: usize,
+    /// Lines in the scanned files of the language.
+    pub lines: usize,
+}
+
+impl LanguageStats {
+    /// TODOs per 1000 lines.
+    #[allow(clippy::cast_precision_loss)] // counts stay far below 2^52
+    pub fn density(&self) -> f64 {
+        if self.lines == 0 {
+            return 0.0;
+        }
+        self.todos as f64 * 1000.0 / self.lines as f64
+    }
+}
+
+/// Counts TODOs and lines per language over the files that differ from the
+/// base branch, the same files the scan looks at.
+pub fn breakdown(
+    repo: &Repository,
+    options: &Options,
+    todos: &[Todo],
+) -> Result<BTreeMap<&'static str, LanguageStats>, git2::Error> {
+    let mut breakdown: BTreeMap<&'static str, LanguageStats> = BTreeMap::new();
+
+    for delta in get_diff_with_main(repo, options)?.deltas() {
+        let new_file = delta.new_file();
+        let Some(relative_file_path) = new_file.path() else {
+            continue;
+        };
+        // files the branch deletes have no lines left
+        let Ok(blob) = repo.find_blob(new_file.id()) else {
+            continue;
+        };
+        if blob.is_binary() {
+            continue;
+        }
+        breakdown
+            .entry(language(relative_file_path))
+            .or_default()
+            .lines += String::from_utf8_lossy(blob.content()).lines().count();
+    }
+
+    for todo in todos {
+        breakdown
+            .entry(language(&todo.file_path))
+            .or_default()
+            .todos += 1;
+    }
+
+    breakdown.retain(|_, stats| stats.todos > 0);
+    Ok(breakdown)
+}
+
+/// Prints the breakdown as an aligned table, most TODOs first.
+pub fn print(breakdown: &BTreeMap<&'static str, LanguageStats>) {
+    if breakdown.is_empty() {
+        return;
+    }
+
+    let mut sorted: Vec<_> = breakdown.iter().collect();
+    sorted.sort_by(|a, b| b.1.todos.cmp(&a.1.todos).then_with(|| a.0.cmp(b.0)));
+
+    let width = sorted
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    println!("\n{}", "By language:".bold());
+    for (name, stats) in sorted {
+        println!(
+            "  {name:<width$}  {:>5}  {:>8} lines  {:>6.2} per 1k lines",
+            stats.todos,
+            stats.lines,
+            stats.density()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn counts_todos_and_lines_per_language() {
+        let test = TestRepo::new("language");
+        test.write("old.rs", "// TODO: on main\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("a.rs", "fn a() {}\n// TODO: one\nfn b() {}\n// TODO: two\n");
+        test.write("b.py", "# TODO: three\n");
+        test.write("Dockerfile", "FROM scratch\n");
+        test.commit("Bob", "b");
+
+        let options = Options {
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let breakdown = breakdown(&test.repo, &options, &todos).unwrap();
+        // files without TODOs, and those only on the base, are left out
+        assert_eq!(
+            breakdown.keys().copied().collect::<Vec<_>>(),
+            ["Python", "Rust"]
+        );
+        assert_eq!(breakdown["Rust"], LanguageStats { todos: 2, lines: 4 });
+        assert!((breakdown["Rust"].density() - 500.0).abs() < f64::EPSILON);
+        assert_eq!(breakdown["Python"].todos, 1);
+    }
+}
@@ -0,0 +1,2857 @@
+//! Finds TODO comments in a git repository and attributes each to the commit,
+//! author and pull request that introduced it.
+//!
+//! [`Scanner`] is the entry point for embedding the scan in other tools; the
+//! `todo` binary is a thin command-line wrapper around [`run`].
+//!
+//! ```no_run
+//! let repo = git2::Repository::open(".")?;
+//! let todos = todo::Scanner::new(&repo).base("origin/main").scan();
+//! for todo in &todos {
+//!     println!("{}:{} {} ({})", todo.file_path.display(), todo.line, todo.raw.trim(), todo.author);
+//! }
+//! # Ok::<(), git2::Error>(())
+//! ```
+
+use chrono::{DateTime, NaiveTime, Utc};
+use colored::Colorize;
+use git2::{Oid, Repository};
+use ignore::types::Types;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use ptree::{write_tree, TreeBuilder};
+use serde::{Deserialize, Serialize};
+
+mod annotations;
+mod api;
+mod automation;
+mod base;
+mod baseline;
+mod bench;
+mod bitbucket;
+mod blob_cache;
+mod branch_commits;
+mod branches;
+mod cache;
+#[doc(hidden)]
+pub mod cli;
+mod commit_info;
+mod config;
+mod containing;
+mod credentials;
+mod dates;
+mod done;
+mod dry_run;
+mod due;
+mod export;
+mod fields;
+mod file_types;
+mod fs_markers;
+mod gerrit;
+mod github;
+mod github_issues;
+mod grafana;
+mod handover;
+mod history;
+mod hotspots;
+mod html;
+mod http;
+mod ids;
+mod init;
+mod issue_status;
+mod language;
+mod limits;
+mod lint;
+mod markdown;
+mod matcher;
+mod metadata;
+mod milestones;
+mod orgs;
+mod owners;
+mod partial;
+mod position;
+mod pull_request;
+mod queue;
+mod redact;
+mod rotate;
+mod sarif;
+mod serve;
+mod server;
+mod severity;
+mod snapshot;
+mod sonar;
+mod squash;
+mod stats;
+mod team;
+mod telemetry;
+mod test_code;
+#[cfg(test)]
+mod test_repo;
+mod timings;
+mod trend;
+mod tui;
+mod upload;
+mod webhook;
+mod workspace;
+
+use blob_cache::BlobCache;
+use cache::Cache;
+use cli::{Command, Format, MergeAttribution, Options, Output, SnapshotAction};
+use commit_info::{CommitInfo, CommitInfos};
+
+pub use cli::{GroupBy, Grouping, Sort};
+pub use fields::Fields;
+pub use matcher::{Matcher, Preset};
+pub use position::Position;
+pub use pull_request::PullRequest;
+pub use severity::Severity;
+
+/// A TODO comment and the commit it is attributed to.
+#[allow(clippy::struct_excessive_bools)] // independent facts about each TODO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Todo {
+    /// Stable across commits while the TODO keeps its file and text.
+    pub id: String,
+    /// Absolute path of the file.
+    pub file_path: PathBuf,
+    /// 1-based line number.
+    pub line: usize,
+    /// Tags from `TODO(tag, @assignee):`.
+    pub tags: Vec<String>,
+    /// `key=value` fields from the same parentheses.
+    pub fields: Fields,
+    /// The line for display, with the keyword highlighted.
+    pub statement: String,
+    /// The source line exactly as written.
+    pub raw: String,
+    /// The TODO's text without the keyword, tags or comment syntax, with
+    /// whitespace collapsed, for machine-readable outputs.
+    pub normalized: String,
+    /// Empty for lines that are not committed yet.
+    pub author: String,
+    pub author_email: String,
+    /// Authors credited through `Co-authored-by:` trailers on the commit.
+    pub co_authors: Vec<String>,
+    /// Empty for lines that are not committed yet.
+    pub commit_hash: String,
+    /// `commit_hash` abbreviated like git does, or `(uncommitted)`.
+    pub short_hash: String,
+    pub author_date: DateTime<Utc>,
+    pub commit_title: String,
+    /// The commit has a GPG or SSH signature that verifies.
+    pub verified: bool,
+    pub pull_request: Option<PullRequest>,
+    /// When the TODO was first written, if that predates `author_date`
+    /// because the blamed commit squashed earlier history.
+    pub introduced_date: Option<DateTime<Utc>>,
+    /// The milestone the first of its tags with one is mapped to.
+    pub milestone: Option<String>,
+    /// The default owner of the first of its tags with one, when no
+    /// `@assignee` tag names who owns it.
+    pub owner: Option<String>,
+    /// The organization of the author's email domain.
+    pub org: Option<String>,
+    /// Local branches that contain the blamed commit, with `--show-branches`.
+    pub branches: Vec<String>,
+    /// Whether the blamed commit is already on the base branch, if the base
+    /// can be found and the line is committed.
+    pub position: Option<Position>,
+    /// Neither the author nor an `@assignee` tag names a current team member.
+    pub orphaned: bool,
+    /// Its issue is closed, with `--check-issues`.
+    pub issue_resolved: bool,
+    /// Its file's history is missing from a partial clone and could not be
+    /// fetched, so it is not attributed to a commit.
+    pub content_unavailable: bool,
+    pub severity: Severity,
+    /// Older than `--max-age`, so escalated.
+    pub stale: bool,
+    /// The language of its file, e.g. `Rust`.
+    pub language: String,
+    /// Its file is a test rather than production code.
+    pub test: bool,
+}
+
+impl Todo {
+    /// A TODO found on a line, not yet attributed to any commit.
+    fn new(matcher: &Matcher, file_path: PathBuf, line: usize, raw: &str) -> Self {
+        Self {
+            id: String::new(),
+            file_path,
+            line,
+            tags: Vec::new(),
+            fields: Fields::default(),
+            statement: String::new(),
+            raw: raw.to_string(),
+            normalized: normalize_statement(matcher, raw),
+            author: String::new(),
+            author_email: String::new(),
+            co_authors: Vec::new(),
+            commit_hash: String::new(),
+            short_hash: UNCOMMITTED.to_string(),
+            author_date: dates::now(),
+            commit_title: String::new(),
+            verified: false,
+            pull_request: None,
+            introduced_date: None,
+            milestone: None,
+            owner: None,
+            org: None,
+            branches: Vec::new(),
+            position: None,
+            orphaned: false,
+            issue_resolved: false,
+            content_unavailable: false,
+            severity: matcher.severity(raw),
+            stale: false,
+            language: String::new(),
+            test: false,
+        }
+    }
+
+    /// The `@assignee` tags without their `@`, then the `owner=` field.
+    pub fn assignees(&self) -> impl Iterator<Item = &str> {
+        self.tags
+            .iter()
+            .filter_map(|tag| tag.strip_prefix('@'))
+            .chain(self.fields.owner.as_deref())
+    }
+
+    /// The first of the [`assignees`](Self::assignees).
+    #[must_use]
+    pub fn assignee(&self) -> Option<&str> {
+        self.assignees().next()
+    }
+
+    /// Whether `author` names the TODO's owner: its author by name, email or
+    /// the local part of the email, an `@assignee` tag or the `owner=` field.
+    #[must_use]
+    pub fn is_owned_by(&self, author: &str) -> bool {
+        let author = author.trim_start_matches('@');
+        let local_part = self.author_email.split('@').next().unwrap_or_default();
+        [self.author.as_str(), &self.author_email, local_part]
+            .iter()
+            .any(|identity| !identity.is_empty() && identity.eq_ignore_ascii_case(author))
+            || self
+                .assignees()
+                .any(|assignee| assignee.eq_ignore_ascii_case(author))
+    }
+
+    /// Attributes the TODO to the given commit.
+    fn set_commit(&mut self, info: &CommitInfo) {
+        self.author.clone_from(&info.author);
+        self.author_email.clone_from(&info.author_email);
+        self.co_authors.clone_from(&info.co_authors);
+        self.commit_hash.clone_from(&info.hash);
+        self.short_hash.clone_from(&info.short_hash);
+        self.author_date = info.author_date;
+        self.commit_title.clone_from(&info.title);
+        self.verified = info.verified;
+        self.pull_request.clone_from(&info.pull_request);
+    }
+}
+
+fn get_commits_since_main(
+    repo: &Repository,
+    options: &Options,
+) -> Result<HashSet<Oid>, git2::Error> {
+    let main_commit = base::commit(repo, options)?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head.id())?;
+    revwalk.hide(main_commit.id())?;
+
+    revwalk.collect()
+}
+
+fn highlight_todo(matcher: &Matcher, line: &str) -> String {
+    let re = matcher.keyword();
+    let mut result = String::new();
+    let mut last_match = 0;
+
+    for mat in re.find_iter(line) {
+        let start = mat.start();
+        let end = mat.end();
+
+        // Add the text before the match
+        result.push_str(&line[last_match..start]);
+
+        // Add the highlighted TODO
+        result.push_str(&line[start..end].red().to_string());
+
+        last_match = end;
+    }
+
+    // Add any remaining text after the last match
+    result.push_str(&line[last_match..]);
+
+    result
+}
+
+fn get_diff_with_main<'repo>(
+    repo: &'repo Repository,
+    options: &Options,
+) -> Result<git2::Diff<'repo>, git2::Error> {
+    let main_tree = base::merge_base(repo, options)?.tree()?;
+
+    let head = repo.head()?;
+    let head_tree = head.peel_to_tree()?;
+
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+
+    repo.diff_tree_to_tree(Some(&main_tree), Some(&head_tree), Some(&mut opts))
+}
+
+/// What is staged for the next commit: the index against `HEAD`, or against
+/// nothing before the first commit.
+fn get_staged_diff(repo: &Repository) -> Result<git2::Diff<'_>, git2::Error> {
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    repo.diff_tree_to_index(head_tree.as_ref(), None, None)
+}
+
+/// What is not committed yet: the working tree, with untracked files,
+/// against `HEAD`.
+fn get_dirty_diff(repo: &Repository) -> Result<git2::Diff<'_>, git2::Error> {
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut opts))
+}
+
+/// The changes scanned: the branch's, or what is staged or not committed
+/// with `--staged` or `--dirty`.
+fn get_changes<'repo>(
+    repo: &'repo Repository,
+    options: &Options,
+) -> Result<git2::Diff<'repo>, git2::Error> {
+    if options.staged {
+        get_staged_diff(repo)
+    } else if options.dirty {
+        get_dirty_diff(repo)
+    } else {
+        get_diff_with_main(repo, options)
+    }
+}
+
+/// What the changes scanned are compared against in the trend.
+fn compared_to(repo: &Repository, options: &Options) -> String {
+    if options.staged || options.dirty {
+        "HEAD".to_string()
+    } else {
+        base::name(repo, options)
+    }
+}
+
+/// Comment syntax that closes a TODO's comment, which is noise in the report.
+const COMMENT_TERMINATORS: [&str; 3] = ["*/", "-->", "#}"];
+
+/// Strips comment terminators, trailing quotes and excess punctuation from the
+/// end of a TODO line, e.g. `<!-- TODO: fix this!! -->` becomes
+/// `<!-- TODO: fix this!`.
+fn clean_statement(line: &str) -> &str {
+    let mut statement = line.trim_end();
+    loop {
+        let before = statement.len();
+        for terminator in COMMENT_TERMINATORS {
+            if let Some(rest) = statement.strip_suffix(terminator) {
+                statement = rest.trim_end();
+            }
+        }
+        statement = statement
+            .trim_end_matches(['"', '\'', '`', ',', ';'])
+            .trim_end();
+        if statement.len() == before {
+            break;
+        }
+    }
+
+    // "why???" keeps a single question mark
+    let unpunctuated = statement.trim_end_matches(['!', '?']);
+    if statement.len() - unpunctuated.len() > 1 {
+        statement = &statement[..=unpunctuated.len()];
+    }
+    statement
+}
+
+/// Splits a TODO line into its tags, its `key=value` fields and the line for
+/// display, with the keyword `matcher` finds highlighted and trailing comment
+/// syntax removed.
+#[must_use]
+pub fn parse_todo(matcher: &Matcher, line: &str) -> (Vec<String>, Fields, String) {
+    let re = matcher.parts();
+    let line = clean_statement(line);
+    re.captures(line).map_or_else(
+        || (vec![], Fields::default(), line.to_string()),
+        |caps| {
+            let mut tags = caps.get(1).map_or(vec![], |m| {
+                m.as_str()
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .collect()
+            });
+            let fields = Fields::extract(&mut tags);
+
+            let colored_line = highlight_todo(matcher, line);
+
+            (tags, fields, colored_line)
+        },
+    )
+}
+
+/// The text of a TODO after its keyword and tags, e.g. `free the buffer` for
+/// `/* TODO(mem):   free the buffer */`.
+fn normalize_statement(matcher: &Matcher, line: &str) -> String {
+    let re = matcher.parts();
+    let line = clean_statement(line);
+    let text = re
+        .captures(line)
+        .and_then(|caps| caps.get(2))
+        .map_or(line, |m| m.as_str());
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The repository's working tree, which a bare repository has none of.
+fn workdir(repo: &Repository) -> Result<&Path, String> {
+    repo.workdir().ok_or_else(|| {
+        format!(
+            "{} is a bare repository, with no files to scan",
+            repo.path().display()
+        )
+    })
+}
+
+fn get_repo(path: &Path) -> Result<Repository, git2::Error> {
+    Repository::open(path)
+}
+
+/// Author of the commit that originally wrote `line` of `contents`, the file
+/// at `path`, looking through the merges a first-parent blame stops at.
+/// `originals` keeps each line's commit from one full blame of the file.
+fn original_author(
+    repo: &Repository,
+    path: &Path,
+    contents: &[u8],
+    line: usize,
+    originals: &mut Option<HashMap<usize, Oid>>,
+) -> Option<String> {
+    let originals = originals.get_or_insert_with(|| {
+        let committed = repo.blame_file(path, None).ok();
+        let blame = committed
+            .as_ref()
+            .and_then(|blame| blame.blame_buffer(contents).ok());
+        blame.as_ref().map_or_else(HashMap::new, get_line_to_commit)
+    });
+    let commit = repo.find_commit(*originals.get(&line)?).ok()?;
+    let author = commit.author().name().map(str::to_string);
+    author
+}
+
+/// Maps each 1-based line number of the blamed file to its commit, leaving
+/// out lines not committed yet.
+fn get_line_to_commit(blame: &git2::Blame) -> HashMap<usize, Oid> {
+    let mut line_to_commit = HashMap::new();
+
+    for hunk in blame.iter() {
+        // a buffer's changes are blamed on a zero ID
+        if hunk.final_commit_id().is_zero() {
+            continue;
+        }
+        let start = hunk.final_start_line();
+        for line in start..start + hunk.lines_in_hunk() {
+            line_to_commit.insert(line, hunk.final_commit_id());
+        }
+    }
+
+    line_to_commit
+}
+
+fn is_text_file(file_path: &Path) -> bool {
+    File::open(file_path).is_ok_and(|mut file| {
+        let mut buffer = [0; 1024];
+        file.read(&mut buffer[..])
+            .is_ok_and(|size| !buffer[..size].contains(&0))
+    })
+}
+
+/// The new-side line numbers each file's hunks add or modify.
+fn added_lines(diff: &git2::Diff<'_>) -> Result<HashMap<PathBuf, HashSet<usize>>, git2::Error> {
+    let mut added: HashMap<PathBuf, HashSet<usize>> = HashMap::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            if let (Some(path), Some(line_number), '+') =
+                (delta.new_file().path(), line.new_lineno(), line.origin())
+            {
+                added
+                    .entry(path.to_path_buf())
+                    .or_default()
+                    .insert(line_number as usize);
+            }
+            true
+        }),
+    )?;
+    Ok(added)
+}
+
+/// The blob each file has on the new side of `diff`, leaving out deleted
+/// files.
+fn new_blobs(diff: &git2::Diff<'_>) -> HashMap<PathBuf, Oid> {
+    diff.deltas()
+        .filter_map(|delta| {
+            let new_file = delta.new_file();
+            let path = new_file.path().filter(|_| !new_file.id().is_zero())?;
+            Some((path.to_path_buf(), new_file.id()))
+        })
+        .collect()
+}
+
+/// Which files are scanned, and which of their lines may be reported.
+struct Scope {
+    /// Paths relative to the repository root, each with the line numbers to
+    /// report, or `None` for every line.
+    files: Vec<(PathBuf, Option<HashSet<usize>>)>,
+    /// Only lines blamed on these commits are reported, if set.
+    commits: Option<HashSet<Oid>>,
+    /// Files whose history is missing from a partial clone, which can't be
+    /// blamed.
+    unavailable: HashSet<PathBuf>,
+    /// How the lines to report are attributed to commits.
+    attribution: Attribution,
+    /// The blob to read each file from instead of the working tree: its
+    /// version at `HEAD`, whose history is blamed, or with `--staged` the
+    /// index's, so that edits not yet committed or staged don't shift lines.
+    blobs: Option<HashMap<PathBuf, Oid>>,
+}
+
+enum Attribution {
+    /// Blaming each file.
+    Blame,
+    /// The branch commit that added each line, found from the branch's diffs
+    /// instead of blaming each file.
+    Added(branch_commits::AddedBy),
+    /// None, since no line in scope is committed yet.
+    Uncommitted,
+}
+
+/// How lines not committed yet are attributed: whole files also hold
+/// committed lines, which are blamed.
+const fn uncommitted(options: &Options) -> Attribution {
+    if options.whole_files {
+        Attribution::Blame
+    } else {
+        Attribution::Uncommitted
+    }
+}
+
+impl Scope {
+    /// The files the branch changes relative to the base branch.
+    fn branch(repo: &Repository, options: &Options) -> Result<Self, git2::Error> {
+        let diff = get_diff_with_main(repo, options)?;
+        if let Err(e) = partial::fetch_diff(repo, &diff) {
+            eprintln!("Error fetching the branch's changes: {e}");
+        }
+
+        // only lines the branch adds or modifies, unless asked for whole files
+        let mut added = match (!options.whole_files).then(|| added_lines(&diff)) {
+            Some(Err(e)) if partial::is_missing(&e) => {
+                eprintln!(
+                    "Some of the branch's changes are unavailable, so whole files are scanned"
+                );
+                None
+            }
+            added => added.transpose()?,
+        };
+
+        // attributing only the added lines, the branch's hunks are enough
+        let attribution = match added
+            .as_ref()
+            .map(|_| branch_commits::added_lines(repo, options))
+        {
+            Some(Err(e)) if !partial::is_missing(&e) => {
+                eprintln!("Error following the branch's changes, so files are blamed: {e}");
+                Attribution::Blame
+            }
+            Some(Ok(Some(added_by))) => Attribution::Added(added_by),
+            _ => Attribution::Blame,
+        };
+
+        let files = diff
+            .deltas()
+            .filter_map(|delta| {
+                let path = delta.new_file().path()?;
+                match &mut added {
+                    Some(added) => Some((path.to_path_buf(), Some(added.remove(path)?))),
+                    None => Some((path.to_path_buf(), None)),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            files,
+            commits: Some(get_commits_since_main(repo, options)?),
+            unavailable: HashSet::new(),
+            attribution,
+            blobs: Some(new_blobs(&diff)),
+        })
+    }
+
+    /// The files with changes not committed yet, staged or not, and untracked
+    /// files.
+    fn dirty(repo: &Repository, options: &Options) -> Result<Self, git2::Error> {
+        let diff = get_dirty_diff(repo)?;
+        let mut added = if options.whole_files {
+            None
+        } else {
+            Some(added_lines(&diff)?)
+        };
+        let files = diff
+            .deltas()
+            .filter(|delta| delta.status() != git2::Delta::Deleted)
+            .filter_map(|delta| {
+                let path = delta.new_file().path()?;
+                match &mut added {
+                    Some(added) => Some((path.to_path_buf(), Some(added.remove(path)?))),
+                    None => Some((path.to_path_buf(), None)),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            files,
+            commits: None,
+            unavailable: HashSet::new(),
+            attribution: uncommitted(options),
+            blobs: None,
+        })
+    }
+
+    /// The files staged for the next commit, read from the index.
+    fn staged(repo: &Repository, options: &Options) -> Result<Self, git2::Error> {
+        let diff = get_staged_diff(repo)?;
+        let mut added = if options.whole_files {
+            None
+        } else {
+            Some(added_lines(&diff)?)
+        };
+
+        let blobs = new_blobs(&diff);
+        let mut files: Vec<_> = blobs
+            .keys()
+            .filter_map(|path| match &mut added {
+                Some(added) => Some((path.clone(), Some(added.remove(path)?))),
+                None => Some((path.clone(), None)),
+            })
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Self {
+            files,
+            commits: None,
+            unavailable: HashSet::new(),
+            attribution: uncommitted(options),
+            blobs: Some(blobs),
+        })
+    }
+
+    /// Every file in the working tree, skipping what `.gitignore`,
+    /// `.git/info/exclude` and the global `core.excludesFile` exclude.
+    fn working_tree(root_dir: &Path) -> Result<Self, ignore::Error> {
+        let mut files = Vec::new();
+
+        let walk = ignore::WalkBuilder::new(root_dir)
+            .hidden(false)
+            .git_ignore(true)
+            .git_exclude(true)
+            .git_global(true)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walk {
+            let entry = entry?;
+            if entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                let path = entry
+                    .path()
+                    .strip_prefix(root_dir)
+                    .unwrap_or_else(|_| entry.path());
+                files.push((path.to_path_buf(), None));
+            }
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Self {
+            files,
+            commits: None,
+            unavailable: HashSet::new(),
+            attribution: Attribution::Blame,
+            blobs: None,
+        })
+    }
+}
+
+/// The TODO on line `number` of a file, attributed to the commit of `info`,
+/// or else as uncommitted.
+fn todo_on_line(
+    matcher: &Matcher,
+    file_path: &Path,
+    line: &str,
+    number: usize,
+    info: Option<&CommitInfo>,
+) -> Todo {
+    let (tags, fields, statement) = parse_todo(matcher, line);
+    let mut todo = Todo::new(matcher, file_path.to_path_buf(), number, line);
+    todo.tags = tags;
+    todo.fields = fields;
+    todo.statement = statement;
+    match info {
+        Some(info) => todo.set_commit(info),
+        // an uncommitted line is no older than the last change to its file
+        None => {
+            if let Ok(modified) = fs::metadata(file_path).and_then(|meta| meta.modified()) {
+                todo.author_date = DateTime::<Utc>::from(modified).min(dates::now());
+            }
+        }
+    }
+    todo
+}
+
+struct FileScanner<'repo> {
+    repo: &'repo Repository,
+    /// The repository's working tree.
+    root: &'repo Path,
+    options: &'repo Options,
+    matcher: &'repo Matcher,
+    commit_infos: CommitInfos<'repo>,
+    blobs: Option<&'repo BlobCache>,
+}
+
+impl FileScanner<'_> {
+    /// A text file's contents, from its blob in the scope if it has one and
+    /// from the working tree otherwise, as when the blob is missing from a
+    /// partial clone.
+    fn contents(
+        &self,
+        scope: &Scope,
+        relative_file_path: &Path,
+        file_path: &Path,
+    ) -> Option<Vec<u8>> {
+        let id = scope
+            .blobs
+            .as_ref()
+            .and_then(|blobs| blobs.get(relative_file_path));
+        if let Some(blob) = id.and_then(|id| self.repo.find_blob(*id).ok()) {
+            return (!blob.is_binary()).then(|| blob.content().to_vec());
+        }
+        if !file_path.is_file() || !is_text_file(file_path) {
+            return None;
+        }
+        fs::read(file_path).ok()
+    }
+
+    /// The TODO on line `number`, attributed without blame, or `None` if no
+    /// branch commit added the line.
+    fn unblamed(
+        &mut self,
+        scope: &Scope,
+        relative_file_path: &Path,
+        line: &str,
+        number: usize,
+    ) -> Option<Todo> {
+        let info = match &scope.attribution {
+            Attribution::Added(added_by) => {
+                // lines no branch commit added aren't the branch's
+                let id = added_by.get(relative_file_path)?.get(&number)?;
+                let commit = self.repo.find_commit(*id).ok();
+                commit.map(|commit| self.commit_infos.get(&commit))
+            }
+            Attribution::Blame | Attribution::Uncommitted => None,
+        };
+        let file_path = self.root.join(relative_file_path);
+        Some(todo_on_line(
+            self.matcher,
+            &file_path,
+            line,
+            number,
+            info.as_deref(),
+        ))
+    }
+
+    fn scan(
+        &mut self,
+        scope: &Scope,
+        relative_file_path: &Path,
+        lines_to_report: Option<&HashSet<usize>>,
+        todos: &mut Vec<Todo>,
+    ) {
+        let _scan_span =
+            telemetry::span("scan").attribute("file.path", relative_file_path.display());
+
+        let file_path = self.root.join(relative_file_path);
+        let Some(contents) = self.contents(scope, relative_file_path, &file_path) else {
+            return;
+        };
+        let lines: Vec<_> = contents.lines().map_while(Result::ok).collect();
+
+        let first = todos.len();
+        self.scan_lines(
+            scope,
+            relative_file_path,
+            &contents,
+            &lines,
+            lines_to_report,
+            todos,
+        );
+        // identical TODOs are told apart in the contents scanned, not in the
+        // working tree, which may have moved on
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let path = repo_relative_path(self.repo, &file_path);
+        ids::assign(self.matcher, &path, &lines, &mut todos[first..]);
+    }
+
+    /// The TODOs on `lines`, the contents of a file as read from the scope.
+    fn scan_lines(
+        &mut self,
+        scope: &Scope,
+        relative_file_path: &Path,
+        contents: &[u8],
+        lines: &[String],
+        lines_to_report: Option<&HashSet<usize>>,
+        todos: &mut Vec<Todo>,
+    ) {
+        let (repo, matcher) = (self.repo, self.matcher);
+        let file_path = self.root.join(relative_file_path);
+
+        let find = || {
+            lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| {
+                    matcher.is_match(line) && !parse_todo(matcher, line).2.is_empty()
+                })
+                .map(|(idx, _)| idx + 1)
+                .collect()
+        };
+        let todo_lines: Vec<usize> = self.blobs.map_or_else(find, |blobs| {
+            blobs.lines(&blob_cache::blob_id(contents), find)
+        });
+        let todo_lines: Vec<usize> = todo_lines
+            .into_iter()
+            .filter(|number| {
+                *number <= lines.len()
+                    && lines_to_report.is_none_or(|report| report.contains(number))
+            })
+            .collect();
+        if todo_lines.is_empty() {
+            timings::count("files not blamed", 1);
+            return;
+        }
+        if !matches!(scope.attribution, Attribution::Blame) {
+            timings::count("files not blamed", 1);
+            let line = |number: usize| lines[number - 1].as_str();
+            todos.extend(todo_lines.into_iter().filter_map(|number| {
+                self.unblamed(scope, relative_file_path, line(number), number)
+            }));
+            return;
+        }
+
+        let blame_span = telemetry::span("blame");
+        let blame_phase = timings::phase("blame");
+        let mut blame_options = git2::BlameOptions::new();
+        blame_options.first_parent(self.options.first_parent);
+
+        let unavailable = scope.unavailable.contains(relative_file_path);
+        let committed = if unavailable {
+            Ok(None)
+        } else {
+            repo.blame_file(relative_file_path, Some(&mut blame_options))
+                .map(Some)
+        };
+        let committed = match committed {
+            Ok(committed) => committed,
+            // untracked files are all uncommitted lines
+            Err(_) if scope.commits.is_none() => None,
+            Err(e) => {
+                eprintln!("Failed to get blame for file: {}: {e}", file_path.display());
+                return;
+            }
+        };
+        // lines changed since HEAD in what was read, such as the working
+        // tree, are blamed on no commit
+        let blame = match committed.as_ref().map(|blame| blame.blame_buffer(contents)) {
+            Some(Ok(blame)) => Some(blame),
+            Some(Err(e)) => {
+                eprintln!("Failed to get blame for file: {}: {e}", file_path.display());
+                return;
+            }
+            None => None,
+        };
+
+        // only computed when a merge commit needs looking through
+        let mut originals = None;
+
+        let line_to_commit = blame.as_ref().map_or_else(HashMap::new, get_line_to_commit);
+        drop(blame_span);
+        drop(blame_phase);
+
+        for number in todo_lines {
+            let idx = number - 1;
+            let line = &lines[idx];
+
+            let commit = line_to_commit.get(&(idx + 1));
+
+            if let (Some(commit), Some(commits)) = (commit, &scope.commits) {
+                if !commits.contains(commit) {
+                    // line has not been modified since main
+                    continue;
+                }
+            }
+
+            let commit = commit.and_then(|commit| repo.find_commit(*commit).ok());
+            let info = commit.map(|commit| self.commit_infos.get(&commit));
+
+            let mut todo = todo_on_line(matcher, &file_path, line, idx + 1, info.as_deref());
+            if unavailable {
+                todo.content_unavailable = true;
+                partial::UNAVAILABLE.clone_into(&mut todo.short_hash);
+            }
+            // without --first-parent, blame already looks through merges to
+            // the commits that wrote each line
+            let is_merge = info.is_some_and(|info| info.is_merge) && self.options.first_parent;
+            if is_merge && self.options.merge_attribution == MergeAttribution::Original {
+                if let Some(original) =
+                    original_author(repo, relative_file_path, contents, idx + 1, &mut originals)
+                {
+                    todo.author = original;
+                }
+            }
+
+            todos.push(todo);
+        }
+    }
+
+    /// The TODOs in a file, from `cache` while the file is unchanged, with the
+    /// key to cache them under.
+    fn scan_cached(
+        &mut self,
+        cache: Option<&Cache>,
+        scope: &Scope,
+        relative_file_path: &Path,
+        lines_to_report: Option<&HashSet<usize>>,
+    ) -> (Option<String>, Vec<Todo>) {
+        let blob = scope
+            .blobs
+            .as_ref()
+            .and_then(|blobs| blobs.get(relative_file_path));
+        let key = cache.and_then(|_| match blob {
+            Some(id) => Some(Cache::blob_key(*id, lines_to_report)),
+            None => Cache::key(&self.root.join(relative_file_path), lines_to_report),
+        });
+        if let (Some(cache), Some(key)) = (cache, &key) {
+            if let Some(todos) = cache.get(relative_file_path, key) {
+                timings::count("run cache hits", 1);
+                return (Some(key.clone()), todos);
+            }
+        }
+        let mut todos = Vec::new();
+        self.scan(scope, relative_file_path, lines_to_report, &mut todos);
+        (key, todos)
+    }
+}
+
+/// A file's index in the scope, the key its TODOs are cached under, if any,
+/// and its TODOs.
+type Scanned = (usize, Option<String>, Vec<Todo>);
+
+/// The files to scan and lines to report, leaving out the files
+/// `.todoignore`, `exclude` and `--type` rule out.
+fn scope(repo: &Repository, options: &Options) -> Result<Scope, String> {
+    let diff_span = telemetry::span("diff");
+    let diff_phase = timings::phase("diff");
+    let mut scope = if options.all_files {
+        Scope::working_tree(workdir(repo)?).map_err(|e| e.to_string())?
+    } else if options.staged {
+        Scope::staged(repo, options).map_err(|e| e.to_string())?
+    } else if options.dirty {
+        Scope::dirty(repo, options).map_err(|e| e.to_string())?
+    } else {
+        Scope::branch(repo, options).map_err(|e| e.to_string())?
+    };
+    drop(diff_span);
+    drop(diff_phase);
+
+    let filter = PathFilter::new(repo, options);
+    scope.files.retain(|(path, _)| filter.includes(path));
+    Ok(scope)
+}
+
+/// Which files are scanned: those `--type`, `--path`, `--exclude` and the
+/// ignore files leave, inside a sparse checkout.
+struct PathFilter<'repo> {
+    ignored: config::Ignored<'repo>,
+    types: Types,
+    outside: HashSet<PathBuf>,
+}
+
+impl<'repo> PathFilter<'repo> {
+    fn new(repo: &'repo Repository, options: &Options) -> Self {
+        Self {
+            ignored: config::Ignored::new(repo, options),
+            // checked when the options were read
+            types: file_types::types(options).unwrap_or_else(|_| Types::empty()),
+            outside: partial::outside_checkout(repo),
+        }
+    }
+
+    /// Whether the file at `path`, relative to the repository, is scanned.
+    fn includes(&self, path: &Path) -> bool {
+        // the baseline quotes TODOs without being one
+        path != Path::new(baseline::FILE)
+            && !self.ignored.matches(path)
+            && !self.types.matched(path, false).is_ignore()
+            && !self.outside.contains(path)
+    }
+}
+
+/// The TODOs in scope, and whether they are attributed to the branch commits
+/// that added them rather than blamed.
+fn get_todos(repo: &Repository, options: &Options) -> (Vec<Todo>, bool) {
+    let mut todos = Vec::new();
+    let scope = workdir(repo).and_then(|root| Ok((root, scope(repo, options)?)));
+    let (root, mut scope) = match scope {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("Error finding the files to scan: {e}");
+            return (todos, false);
+        }
+    };
+    let matcher = Matcher::configured(options);
+    let paths: Vec<_> = scope.files.iter().map(|(path, _)| path.as_path()).collect();
+    scope.unavailable = partial::fetch_history(repo, &paths);
+    let files = &scope.files;
+
+    let _scan_phase = timings::phase("scan");
+    let mut cache = Cache::open(repo, options, scope.commits.as_ref());
+    let blobs = BlobCache::open(repo, options);
+    let jobs = options
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+        .min(files.len());
+    let scan_serially = || {
+        let mut scanner = FileScanner {
+            repo,
+            root,
+            options,
+            matcher: &matcher,
+            commit_infos: CommitInfos::new(repo, options),
+            blobs: blobs.as_ref(),
+        };
+        files
+            .iter()
+            .enumerate()
+            .map(|(index, (relative_file_path, lines_to_report))| {
+                let (key, found) = scanner.scan_cached(
+                    cache.as_ref(),
+                    &scope,
+                    relative_file_path,
+                    lines_to_report.as_ref(),
+                );
+                (index, key, found)
+            })
+            .collect()
+    };
+    let scanned: Vec<Scanned> = if jobs <= 1 {
+        scan_serially()
+    } else {
+        let caches = (cache.as_ref(), blobs.as_ref());
+        match scan_in_parallel(root, options, &matcher, caches, &scope, files, jobs) {
+            Ok(scanned) => scanned,
+            Err(e) => {
+                eprintln!("Error opening the repository for parallel scanning, so files are scanned one at a time: {e}");
+                scan_serially()
+            }
+        }
+    };
+
+    for (index, key, found) in scanned {
+        // content that becomes available later is worth another look
+        let complete = !found.iter().any(|todo| todo.content_unavailable);
+        if let (Some(cache), Some(key), true) = (&mut cache, key, complete) {
+            cache.insert(files[index].0.clone(), key, found.clone());
+        }
+        todos.extend(found);
+    }
+    if let Some(cache) = cache {
+        if let Err(e) = cache.save() {
+            eprintln!("Error writing the TODO cache: {e}");
+        }
+    }
+    if let Some(blobs) = blobs {
+        if let Err(e) = blobs.save() {
+            eprintln!("Error writing the blob cache: {e}");
+        }
+    }
+    (todos, matches!(scope.attribution, Attribution::Added(_)))
+}
+
+/// Scans `files` on `jobs` threads, returning what each file has with its
+/// index, in order.
+fn scan_in_parallel(
+    root: &Path,
+    options: &Options,
+    matcher: &Matcher,
+    (cache, blobs): (Option<&Cache>, Option<&BlobCache>),
+    scope: &Scope,
+    files: &[(PathBuf, Option<HashSet<usize>>)],
+    jobs: usize,
+) -> Result<Vec<Scanned>, git2::Error> {
+    // a repository handle can't be shared between threads
+    let repos = (0..jobs)
+        .map(|_| Repository::open(root))
+        .collect::<Result<Vec<_>, _>>()?;
+    // spans opened by workers belong to the one open here
+    let parent = telemetry::current();
+
+    // workers take the next file as they finish one, since blame times vary
+    // widely, and results are put back in file order afterwards
+    let next = AtomicUsize::new(0);
+    let mut scanned: Vec<Scanned> = thread::scope(|threads| {
+        let workers: Vec<_> = repos
+            .into_iter()
+            .map(|repo| {
+                let (next, parent) = (&next, parent.clone());
+                threads.spawn(move || {
+                    telemetry::within(parent, || {
+                        let mut scanner = FileScanner {
+                            repo: &repo,
+                            root,
+                            options,
+                            matcher,
+                            commit_infos: CommitInfos::new(&repo, options),
+                            blobs,
+                        };
+                        let mut found = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((relative_file_path, lines_to_report)) = files.get(index)
+                            else {
+                                break;
+                            };
+                            let (key, todos) = scanner.scan_cached(
+                                cache,
+                                scope,
+                                relative_file_path,
+                                lines_to_report.as_ref(),
+                            );
+                            found.push((index, key, todos));
+                        }
+                        found
+                    })
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    scanned.sort_by_key(|(index, _, _)| *index);
+    Ok(scanned)
+}
+
+/// A group a TODO falls in: its commit, its milestone, one of its tags and so
+/// on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    /// Orders groups, newest last.
+    pub timestamp_nanos: i64,
+    /// The commit's hash, the milestone's name, the tag, the author or the
+    /// path; empty for uncommitted lines and untagged TODOs.
+    pub id: String,
+    /// The header shown for the group, with terminal colors.
+    pub display: String,
+}
+
+/// TODOs grouped by each level of `--group-by` in turn.
+#[derive(Debug, Clone)]
+pub enum Grouped {
+    /// A level's groups, in order, each with what is below it.
+    Groups(Grouping, Vec<(Key, Self)>),
+    /// The TODOs below the last level.
+    Todos(Vec<Todo>),
+}
+
+impl Grouped {
+    /// The TODOs below, counting a TODO listed in several groups once in
+    /// each.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        match self {
+            Self::Groups(_, groups) => groups.iter().map(|(_, below)| below.count()).sum(),
+            Self::Todos(todos) => todos.len(),
+        }
+    }
+}
+
+/// Shown instead of a commit for lines that are not committed yet.
+const UNCOMMITTED: &str = "(uncommitted)";
+
+/// The header of the commit a TODO is attributed to.
+fn commit_key(todo: &Todo) -> Key {
+    if todo.commit_hash.is_empty() {
+        // uncommitted, or its content is unavailable, which lists first
+        // however old the files are
+        return Key {
+            timestamp_nanos: dates::now().timestamp_nanos_opt().unwrap(),
+            id: String::new(),
+            display: todo.short_hash.dimmed().to_string(),
+        };
+    }
+    let title = &todo.commit_title;
+    let mut commit_key = format!(
+        "{} {} {}",
+        title.underline(),
+        dates::ago(todo.author_date),
+        format!("({})", dates::absolute(todo.author_date)).dimmed()
+    );
+    if let Some(position) = &todo.position {
+        // the branch's own commits stand out from those it inherited
+        let label = format!("[{} · {position}]", todo.short_hash);
+        let label = if *position == Position::Branch {
+            label.yellow()
+        } else {
+            label.dimmed()
+        };
+        commit_key = format!("{commit_key} {label}");
+    }
+    if todo.verified {
+        commit_key = format!("{commit_key} {}", "✓ verified".green());
+    }
+    if let Some(pull_request) = &todo.pull_request {
+        commit_key = format!("{commit_key} {}", pull_request.to_string().dimmed());
+    }
+    if !todo.branches.is_empty() {
+        let branches = format!("[on {}]", todo.branches.join(", "));
+        commit_key = format!("{commit_key} {}", branches.cyan());
+    }
+
+    Key {
+        timestamp_nanos: todo.author_date.timestamp_nanos_opt().unwrap(),
+        id: todo.commit_hash.clone(),
+        display: commit_key,
+    }
+}
+
+/// The header of a TODO's milestone; milestones sort by name, with TODOs whose
+/// tags map to none last.
+fn milestone_key(todo: &Todo) -> Key {
+    todo.milestone.as_ref().map_or_else(
+        || Key {
+            timestamp_nanos: i64::MIN,
+            id: String::new(),
+            display: "Unscheduled".dimmed().to_string(),
+        },
+        |milestone| Key {
+            timestamp_nanos: 0,
+            id: milestone.clone(),
+            display: milestone.bold().to_string(),
+        },
+    )
+}
+
+/// The header of a TODO's priority; the most urgent come first and TODOs
+/// without one last.
+fn priority_key(todo: &Todo) -> Key {
+    todo.fields.priority.map_or_else(
+        || Key {
+            timestamp_nanos: i64::MIN,
+            id: String::new(),
+            display: "No priority".dimmed().to_string(),
+        },
+        |priority| Key {
+            timestamp_nanos: -i64::from(priority),
+            id: format!("P{priority}"),
+            display: format!("P{priority}").bold().to_string(),
+        },
+    )
+}
+
+/// The header of the week or month a TODO's commit was written in, which
+/// shows when debt piled up without a group for every commit. Uncommitted
+/// TODOs keep their own group.
+fn period_key(todo: &Todo, period: fn(DateTime<Utc>) -> dates::Period) -> Key {
+    if todo.commit_hash.is_empty() {
+        return commit_key(todo);
+    }
+    let period = period(todo.author_date);
+    Key {
+        timestamp_nanos: period
+            .start
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+            .timestamp_nanos_opt()
+            .unwrap(),
+        id: period.key,
+        display: period.title.bold().to_string(),
+    }
+}
+
+/// The header of each of a TODO's tags, with untagged TODOs last under
+/// `untagged`.
+fn tag_keys(todo: &Todo, untagged: &str) -> Vec<Key> {
+    if todo.tags.is_empty() {
+        return vec![Key {
+            timestamp_nanos: i64::MIN,
+            id: String::new(),
+            display: untagged.dimmed().to_string(),
+        }];
+    }
+    todo.tags
+        .iter()
+        .map(|tag| Key {
+            timestamp_nanos: 0,
+            id: tag.clone(),
+            display: tag.clone(),
+        })
+        .collect()
+}
+
+/// The header of each of a TODO's authors: pair-programmed TODOs are listed
+/// under every author.
+fn author_keys(todo: &Todo) -> Vec<Key> {
+    if todo.author.is_empty() {
+        // uncommitted, or its content is unavailable
+        return vec![Key {
+            timestamp_nanos: 0,
+            id: String::new(),
+            display: todo.short_hash.dimmed().to_string(),
+        }];
+    }
+    let mut authors = vec![&todo.author];
+    for co_author in &todo.co_authors {
+        if !authors.contains(&co_author) {
+            authors.push(co_author);
+        }
+    }
+    authors
+        .into_iter()
+        .map(|author| Key {
+            timestamp_nanos: 0,
+            id: author.clone(),
+            display: author.italic().to_string(),
+        })
+        .collect()
+}
+
+/// The header of a TODO's organization; organizations sort by name, with
+/// TODOs whose author has no email last.
+fn org_key(todo: &Todo) -> Key {
+    todo.org.as_ref().map_or_else(
+        || Key {
+            timestamp_nanos: i64::MIN,
+            id: String::new(),
+            display: if todo.commit_hash.is_empty() {
+                todo.short_hash.dimmed().to_string()
+            } else {
+                "Unknown".dimmed().to_string()
+            },
+        },
+        |org| Key {
+            timestamp_nanos: 0,
+            id: org.clone(),
+            display: org.bold().to_string(),
+        },
+    )
+}
+
+/// The header of a file or directory, shown relative to the current
+/// directory; they sort by path.
+fn path_key(path: &Path) -> Key {
+    let shown = get_relative_or_absolute_path(path).map_or_else(
+        |_| path.display().to_string(),
+        |shown| shown.display().to_string(),
+    );
+    Key {
+        timestamp_nanos: 0,
+        id: path.to_string_lossy().into_owned(),
+        display: if shown.is_empty() {
+            ".".to_string()
+        } else {
+            shown
+        },
+    }
+}
+
+/// The groups a TODO falls in at `level`.
+fn keys(todo: &Todo, level: Grouping, untagged: &str) -> Vec<Key> {
+    match level {
+        Grouping::Commit | Grouping::BranchCommit => vec![commit_key(todo)],
+        Grouping::Milestone => vec![milestone_key(todo)],
+        Grouping::Priority => vec![priority_key(todo)],
+        Grouping::Week => vec![period_key(todo, dates::week)],
+        Grouping::Month => vec![period_key(todo, dates::month)],
+        Grouping::Tag => tag_keys(todo, untagged),
+        Grouping::Author => author_keys(todo),
+        Grouping::Org => vec![org_key(todo)],
+        Grouping::File => vec![path_key(&todo.file_path)],
+        Grouping::Dir => vec![path_key(todo.file_path.parent().unwrap_or(&todo.file_path))],
+    }
+}
+
+/// Groups TODOs by each of `levels` in turn, newest or first in order first,
+/// or with `in_order` in the order of their first TODOs.
+fn group(todos: Vec<Todo>, levels: &[Grouping], untagged: &str, in_order: bool) -> Grouped {
+    let Some((&level, below)) = levels.split_first() else {
+        return Grouped::Todos(todos);
+    };
+    let mut groups: HashMap<Key, (usize, Vec<Todo>)> = HashMap::new();
+    for (index, todo) in todos.into_iter().enumerate() {
+        for key in keys(&todo, level, untagged) {
+            let (_, todos) = groups.entry(key).or_insert((index, Vec::new()));
+            todos.push(todo.clone());
+        }
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|(a, _), (b, _)| {
+        a.timestamp_nanos
+            .cmp(&b.timestamp_nanos)
+            .reverse()
+            .then_with(|| a.display.cmp(&b.display))
+    });
+    if in_order {
+        groups.sort_by_key(|(_, (first, _))| *first);
+    }
+    Grouped::Groups(
+        level,
+        groups
+            .into_iter()
+            .map(|(key, (_, todos))| (key, group(todos, below, untagged, in_order)))
+            .collect(),
+    )
+}
+
+/// Groups TODOs as `--group-by` asks, by default by commit, then tag, then
+/// author. Untagged TODOs are listed under `untagged`.
+///
+/// With `in_order`, for `--sort`, groups keep the order of their first TODOs.
+#[must_use]
+pub fn group_todos(todos: &[Todo], group_by: &GroupBy, untagged: &str, in_order: bool) -> Grouped {
+    group(todos.to_vec(), &group_by.0, untagged, in_order)
+}
+
+/// Orders TODOs as `--sort` asks, keeping their order where it doesn't
+/// tell them apart.
+fn sort_todos(todos: &mut [Todo], sort: Sort, reverse: bool) {
+    todos.sort_by(|a, b| {
+        let order = match sort {
+            Sort::Age => a
+                .introduced_date
+                .unwrap_or(a.author_date)
+                .cmp(&b.introduced_date.unwrap_or(b.author_date)),
+            Sort::File => (&a.file_path, a.line).cmp(&(&b.file_path, b.line)),
+            Sort::Author => a.author.cmp(&b.author),
+            Sort::Priority => {
+                let priority = |todo: &Todo| (todo.fields.priority.is_none(), todo.fields.priority);
+                priority(a).cmp(&priority(b))
+            }
+            Sort::Severity => b.severity.cmp(&a.severity),
+        };
+        if reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+}
+
+/// The TODOs in the order `--sort` asks, grouped as `--group-by` asks.
+fn sorted_and_grouped(todos: &mut [Todo], options: &Options) -> Grouped {
+    if let Some(sort) = options.sort {
+        sort_todos(todos, sort, options.reverse);
+    }
+    group_todos(
+        todos,
+        &options.group_by,
+        options.untagged_label(),
+        options.sort.is_some(),
+    )
+}
+
+/// TODOs escalated by `--max-age`, oldest first.
+fn stale_todos(todos: &[Todo]) -> Vec<&Todo> {
+    let mut stale: Vec<_> = todos.iter().filter(|todo| todo.stale).collect();
+    stale.sort_by_key(|todo| todo.introduced_date.unwrap_or(todo.author_date));
+    stale
+}
+
+/// Lists stale TODOs ahead of the tree, so old debt is the first thing seen.
+fn write_stale_todos(out: &mut impl Write, todos: &[Todo]) -> std::io::Result<()> {
+    let stale = stale_todos(todos);
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    let title = format!(
+        "{} stale TODO{}",
+        stale.len(),
+        if stale.len() == 1 { "" } else { "s" }
+    );
+    let mut tree = TreeBuilder::new(format!("{}", title.red().bold()));
+    for todo in stale {
+        let file_link = get_relative_or_absolute_path(&todo.file_path)?;
+        let written = todo.introduced_date.unwrap_or(todo.author_date);
+        let details = format!("({}, {})", todo.author, dates::ago(written));
+        tree.add_empty_child(format!(
+            "{}:{} - {} {}",
+            file_link.display(),
+            todo.line,
+            todo.statement.trim(),
+            details.dimmed()
+        ));
+    }
+    write_tree(&tree.build(), &mut *out)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// A TODO's line in the tree.
+fn todo_text(todo: &Todo) -> std::io::Result<String> {
+    // get path relative to CWD
+    let file_link = get_relative_or_absolute_path(&todo.file_path)?;
+    let file_link = file_link.display();
+
+    let file_link = format!("{}:{}", file_link, todo.line);
+    let mut todo_text = format!("{} - {}", file_link, todo.statement.trim());
+    if let Some(introduced) = todo.introduced_date {
+        let since = format!("(since {})", dates::ago(introduced));
+        todo_text = format!("{todo_text} {}", since.dimmed());
+    }
+    if let Some(days) = due::days_left(todo) {
+        let due = due::describe(days);
+        let due = if days < 0 { due.red() } else { due.dimmed() };
+        todo_text = format!("{todo_text} {due}");
+    }
+    if let Some(owner) = &todo.owner {
+        todo_text = format!("{todo_text} {}", format!("→ {owner}").cyan());
+    }
+    todo_text = format!("{todo_text} {}", format!("#{}", todo.id).dimmed());
+    if todo.test {
+        todo_text = format!("{todo_text} {}", "test".dimmed());
+    }
+    if todo.orphaned {
+        todo_text = format!("{todo_text} {}", "⚠ orphaned".yellow());
+    }
+    if let Some(issue) = todo.fields.issue.as_ref().filter(|_| todo.issue_resolved) {
+        let resolved = format!("⚠ {issue} resolved");
+        todo_text = format!("{todo_text} {}", resolved.yellow());
+    }
+    match todo.severity {
+        Severity::High => todo_text = format!("{todo_text} {}", "high".red()),
+        Severity::Medium => {
+            todo_text = format!("{todo_text} {}", "medium".yellow());
+        }
+        Severity::Low => {}
+    }
+    Ok(todo_text)
+}
+
+/// Adds the groups below a node of the tree, and the TODOs below them.
+fn add_grouped_todos(tree: &mut TreeBuilder, grouped: &Grouped) -> std::io::Result<()> {
+    match grouped {
+        Grouped::Groups(_, groups) => {
+            for (key, below) in groups {
+                tree.begin_child(key.display.clone());
+                add_grouped_todos(tree, below)?;
+                tree.end_child();
+            }
+        }
+        Grouped::Todos(todos) => {
+            for todo in todos {
+                tree.add_empty_child(todo_text(todo)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A tree for each top-level group, each folded into a log group when
+/// `actions` is set, or with `--group-by none` a flat list.
+fn write_grouped_todos(
+    out: &mut impl Write,
+    grouped: &Grouped,
+    actions: bool,
+) -> std::io::Result<()> {
+    let groups = match grouped {
+        Grouped::Groups(_, groups) => groups,
+        Grouped::Todos(todos) => {
+            for todo in todos {
+                writeln!(out, "{}", todo_text(todo)?)?;
+            }
+            return writeln!(out);
+        }
+    };
+    for (key, below) in groups {
+        let mut tree = TreeBuilder::new(key.display.clone());
+        add_grouped_todos(&mut tree, below)?;
+
+        let tree = tree.build();
+        if actions {
+            writeln!(out, "::group::{}", github::strip_ansi(&key.display))?;
+        }
+        write_tree(&tree, &mut *out)?;
+        if actions {
+            writeln!(out, "::endgroup::")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn get_relative_or_absolute_path(file_path: &Path) -> std::io::Result<PathBuf> {
+    // Get the current working directory
+    let current_dir = std::env::current_dir()?;
+
+    // Attempt to get the relative path
+    file_path.strip_prefix(&current_dir).map_or_else(
+        |_| file_path.canonicalize().map(|path| strip_verbatim(&path)),
+        |relative_path| Ok(relative_path.to_path_buf()),
+    )
+}
+
+/// Turns the `\\?\C:\...` and `\\?\UNC\server\...` paths that canonicalizing
+/// produces on Windows back into the familiar `C:\...` and `\\server\...`.
+fn strip_verbatim(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{unc}"));
+    }
+    text.strip_prefix(r"\\?\")
+        .map_or_else(|| path.to_path_buf(), PathBuf::from)
+}
+
+/// Path of a scanned file relative to the repository root, with `/` separators
+/// as expected by code-review APIs.
+fn repo_relative_path(repo: &Repository, file_path: &Path) -> String {
+    let path = repo
+        .workdir()
+        .and_then(|root| file_path.strip_prefix(root).ok())
+        .unwrap_or(file_path);
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// A scanned file's contents as the scan read them: the working tree's with
+/// `--all` or `--dirty`, the index's with `--staged` and `HEAD`'s otherwise,
+/// falling back to the working tree's like the scan does.
+fn scanned_source(repo: &Repository, options: &Options, file_path: &Path) -> Option<String> {
+    let relative = repo
+        .workdir()
+        .and_then(|root| file_path.strip_prefix(root).ok());
+    let id = if options.all_files || (options.dirty && !options.staged) {
+        None
+    } else if options.staged {
+        let index = repo.index().ok();
+        relative.and_then(|path| Some(index?.get_path(path, 0)?.id))
+    } else {
+        let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        relative.and_then(|path| Some(head?.get_path(path).ok()?.id()))
+    };
+    let blob = id.and_then(|id| repo.find_blob(id).ok());
+    blob.map_or_else(
+        || fs::read_to_string(file_path).ok(),
+        |blob| Some(String::from_utf8_lossy(blob.content()).into_owned()),
+    )
+}
+
+/// Finds the TODOs to report, attributed as the options ask.
+fn scan(repo: &Repository, options: &Options) -> Vec<Todo> {
+    let root = match workdir(repo) {
+        Ok(root) => root,
+        Err(e) => {
+            eprintln!("Error finding the files to scan: {e}");
+            return Vec::new();
+        }
+    };
+    let (mut todos, by_branch_commit) = get_todos(repo, options);
+    if !options.no_baseline {
+        baseline::suppress(repo, &mut todos);
+    }
+
+    if options.squash_aware {
+        if let Err(e) = squash::restore_introduction_dates(repo, options, &mut todos) {
+            eprintln!("Error looking for pre-squash history: {e}");
+        }
+    }
+
+    // the rules were checked with the rest of the configuration
+    if let Ok(rules) = severity::PathRules::new(root, &options.path_severities) {
+        rules.raise(repo, &mut todos);
+    }
+    if let Some(max_age_days) = options.max_age_days {
+        severity::escalate(&mut todos, max_age_days);
+    }
+
+    if options.group_by.contains(Grouping::BranchCommit) && !by_branch_commit {
+        if let Err(e) = branch_commits::attribute(repo, options, &mut todos) {
+            eprintln!("Error attributing TODOs to branch commits: {e}");
+        }
+    }
+
+    // without a base branch, commits have no position to show
+    let _ = position::annotate(repo, options, &mut todos);
+
+    if let Some(path) = &options.milestones {
+        match milestones::Milestones::load(path) {
+            Ok(milestones) => milestones.assign(&mut todos),
+            Err(e) => eprintln!("Error reading milestones from {}: {e}", path.display()),
+        }
+    }
+    owners::assign(&options.owners, &mut todos);
+
+    // the patterns were checked with the rest of the configuration
+    if let Ok(classifier) = test_code::Classifier::new(root, &options.test_paths) {
+        classifier.classify(repo, &mut todos);
+    }
+    if options.prod_only || options.test_only {
+        todos.retain(|todo| todo.test == options.test_only);
+    }
+    if !options.languages.is_empty() {
+        todos.retain(|todo| {
+            options
+                .languages
+                .iter()
+                .any(|language| language.eq_ignore_ascii_case(&todo.language))
+        });
+    }
+
+    if options.show_branches {
+        if let Err(e) = containing::annotate(repo, &mut todos) {
+            eprintln!("Error finding branches containing TODOs: {e}");
+        }
+    }
+
+    if !options.ignore_authors.is_empty() {
+        match automation::Automation::new(&options.ignore_authors) {
+            Ok(automation) => automation.reattribute(&mut todos),
+            Err(e) => eprintln!("Error in --ignore-author pattern: {e}"),
+        }
+    }
+    orgs::assign(&options.orgs, &mut todos);
+
+    if let Some(path) = &options.team {
+        match team::Team::load(path) {
+            Ok(team) => team.mark_orphaned(&mut todos),
+            Err(e) => eprintln!("Error reading team from {}: {e}", path.display()),
+        }
+    }
+    if options.orphaned {
+        todos.retain(|todo| todo.orphaned);
+    }
+    if options.check_issues {
+        issue_status::mark_resolved(repo, &mut todos);
+    }
+
+    let mut authors = options.authors.clone();
+    if options.mine {
+        let identity = git_identity(repo);
+        if identity.is_empty() {
+            eprintln!("Error: '--mine' needs user.name or user.email in the git config");
+        }
+        authors.extend(identity);
+    }
+    if options.mine || !authors.is_empty() {
+        todos.retain(|todo| authors.iter().any(|author| todo.is_owned_by(author)));
+    }
+
+    todos
+}
+
+/// `user.name` and `user.email` from the git config, for `--mine`.
+fn git_identity(repo: &Repository) -> Vec<String> {
+    let Ok(config) = repo.config() else {
+        return Vec::new();
+    };
+    ["user.name", "user.email"]
+        .iter()
+        .filter_map(|key| config.get_string(key).ok())
+        .filter(|value| !value.trim().is_empty())
+        .collect()
+}
+
+/// Scans a repository for TODOs, configured builder-style.
+///
+/// By default it reports the TODOs the checked-out branch adds relative to
+/// its base branch, found the way the `todo` command finds it.
+#[derive(Clone)]
+pub struct Scanner<'repo> {
+    repo: &'repo Repository,
+    options: Options,
+}
+
+impl<'repo> Scanner<'repo> {
+    #[must_use]
+    pub fn new(repo: &'repo Repository) -> Self {
+        Self {
+            repo,
+            options: Options::default(),
+        }
+    }
+
+    /// The ref to compare against, such as `origin/main`.
+    #[must_use]
+    pub fn base(mut self, base: impl Into<String>) -> Self {
+        self.options.base = Some(base.into());
+        self
+    }
+
+    /// Also match `keyword`, besides `TODO`.
+    #[must_use]
+    pub fn keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.options.keywords.push(keyword.into());
+        self
+    }
+
+    /// How strictly lines must look like TODOs.
+    #[must_use]
+    pub const fn match_preset(mut self, preset: Preset) -> Self {
+        self.options.match_preset = preset;
+        self
+    }
+
+    /// Scan every file in the working tree, not only the branch's changes.
+    #[must_use]
+    pub const fn all_files(mut self, all_files: bool) -> Self {
+        self.options.all_files = all_files;
+        self
+    }
+
+    /// Scan what is staged for the next commit instead of the branch's
+    /// changes.
+    #[must_use]
+    pub const fn staged(mut self, staged: bool) -> Self {
+        self.options.staged = staged;
+        self
+    }
+
+    /// Scan what is not committed yet instead of the branch's changes.
+    #[must_use]
+    pub const fn dirty(mut self, dirty: bool) -> Self {
+        self.options.dirty = dirty;
+        self
+    }
+
+    /// Report every branch TODO in the files the branch changes, not only
+    /// those on lines its diff touches.
+    #[must_use]
+    pub const fn whole_files(mut self, whole_files: bool) -> Self {
+        self.options.whole_files = whole_files;
+        self
+    }
+
+    /// Only report TODOs owned by `author`, by name, email or `@handle`;
+    /// repeatable.
+    #[must_use]
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.options.authors.push(author.into());
+        self
+    }
+
+    /// Escalate TODOs older than this many days.
+    #[must_use]
+    pub const fn max_age_days(mut self, days: u32) -> Self {
+        self.options.max_age_days = Some(days);
+        self
+    }
+
+    /// Finds and attributes the TODOs. Errors reading the repository are
+    /// reported on stderr and leave out what could not be read.
+    #[must_use]
+    pub fn scan(&self) -> Vec<Todo> {
+        scan(self.repo, &self.options)
+    }
+
+    /// The matcher scans use, for [`parse_todo`].
+    #[must_use]
+    pub fn matcher(&self) -> Matcher {
+        Matcher::configured(&self.options)
+    }
+}
+
+fn print_stats(
+    repo: &Repository,
+    options: &Options,
+    push: Option<&str>,
+    by_language: bool,
+) -> bool {
+    let todos = scan(repo, options);
+    let stats = stats::Stats::compute(&todos);
+    stats.print();
+
+    if by_language {
+        match language::breakdown(repo, options, &todos) {
+            Ok(breakdown) => language::print(&breakdown),
+            Err(e) => {
+                eprintln!("Error counting lines by language: {e}");
+                return false;
+            }
+        }
+    }
+
+    if let Some(target) = push {
+        if let Err(e) = stats::push(repo, &stats, target) {
+            eprintln!("Error pushing stats to {target}: {e}");
+            return false;
+        }
+    }
+    true
+}
+
+/// What a run found, ready to render in each output.
+struct Report<'a> {
+    todos: &'a [Todo],
+    grouped: &'a Grouped,
+    metadata: &'a metadata::Metadata,
+    /// The tree's last line.
+    totals: &'a str,
+    /// With `--fs-markers`, marked files and directories.
+    markers: Option<&'a [String]>,
+}
+
+fn render(repo: &Repository, output: &Output, options: &Options, report: &Report) -> String {
+    let Report {
+        todos,
+        grouped,
+        metadata,
+        totals,
+        markers,
+    } = *report;
+    let _render_phase = timings::phase("render");
+    let _render = telemetry::span("render").attribute("format", format!("{:?}", output.format));
+    match output.format {
+        Format::Tree => {
+            let mut out = format!("{}\n\n", metadata.summary().dimmed()).into_bytes();
+            if !todos.is_empty() {
+                // log groups only fold in the job's own log
+                let actions = github::is_actions() && output.path.is_none();
+                write_stale_todos(&mut out, todos).unwrap();
+                write_grouped_todos(&mut out, grouped, actions).unwrap();
+            }
+            fs_markers::write(&mut out, markers.unwrap_or_default()).unwrap();
+            format!("{}{totals}\n", String::from_utf8_lossy(&out))
+        }
+        Format::Gerrit => format!("{:#}\n", gerrit::review_input(repo, todos, metadata)),
+        Format::Sonar => format!(
+            "{:#}\n",
+            metadata.attach(sonar::generic_issues(repo, todos))
+        ),
+        Format::Json => {
+            let json = if options.grouped {
+                export::grouped_json(repo, todos, grouped)
+            } else {
+                export::json(repo, todos)
+            };
+            let mut json = metadata.attach(json);
+            json["summary"] = stats::Summary::compute(todos).json();
+            if let Some(markers) = markers {
+                json["fs_markers"] = markers.into();
+            }
+            format!("{json:#}\n")
+        }
+        Format::Csv => export::csv(repo, todos, metadata),
+        Format::Html => html::report(repo, todos, metadata),
+        Format::Github => github::annotations(repo, &Matcher::configured(options), todos),
+        Format::Sarif => {
+            let log = sarif::log(repo, &Matcher::configured(options), todos, metadata);
+            format!("{log:#}\n")
+        }
+        Format::Markdown => markdown::report(repo, todos, grouped, metadata, totals),
+    }
+}
+
+/// The marked files and directories among those scanned, for `--fs-markers`.
+fn find_markers(repo: &Repository, options: &Options) -> Vec<String> {
+    match scope(repo, options) {
+        Ok(scope) => fs_markers::find(scope.files.iter().map(|(path, _)| path.as_path())),
+        Err(e) => {
+            eprintln!("Error finding the files to scan: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Renders `report` in each output. Returns `false` if a file could not be
+/// written.
+fn write_outputs(repo: &Repository, options: &Options, report: &Report) -> bool {
+    let mut written = true;
+    for output in options.outputs() {
+        if options.quiet && output.path.is_none() {
+            continue;
+        }
+        let rendered = render(repo, &output, options, report);
+
+        match &output.path {
+            None => print!("{rendered}"),
+            // colors are for terminals
+            Some(path) => {
+                if let Err(e) = std::fs::write(path, github::strip_ansi(&rendered)) {
+                    eprintln!("Error writing {}: {e}", path.display());
+                    written = false;
+                }
+            }
+        }
+    }
+    written
+}
+
+/// Reports the branch's TODOs in each requested output. Returns `false` if
+/// `--fail-on-new` finds new ones.
+fn list(repo: &Repository, options: &Options) -> bool {
+    let mut todos = scan(repo, options);
+    let comparison = trend::Comparison::compute(repo, options);
+    let grouped = sorted_and_grouped(&mut todos, options);
+    let base = compared_to(repo, options);
+
+    let summary = if todos.is_empty() {
+        "✅ No TODOs found in the repository".to_string()
+    } else {
+        stats::Summary::compute(&todos).to_string()
+    };
+
+    // machine-readable formats have no room for it, so only the tree ends
+    // with the count and trend
+    let totals = comparison.as_ref().map_or_else(
+        |_| summary.clone(),
+        |comparison| format!("{summary} · {} vs {base}", comparison.trend()),
+    );
+
+    let markers = options.fs_markers.then(|| find_markers(repo, options));
+
+    let metadata = metadata::Metadata::new(repo, options);
+    let found = Report {
+        todos: &todos,
+        grouped: &grouped,
+        metadata: &metadata,
+        totals: &totals,
+        markers: markers.as_deref(),
+    };
+    let written = write_outputs(repo, options, &found);
+
+    if github::is_actions() {
+        let trend = comparison.as_ref().ok().map(trend::Comparison::trend);
+        if let Err(e) = github::write_step_summary(&todos, &grouped, &summary, trend, &metadata) {
+            eprintln!("Error writing GitHub step summary: {e}");
+        }
+    }
+
+    let new = match &comparison {
+        Ok(comparison) => comparison.new_fingerprints(),
+        Err(e) => {
+            eprintln!("Error comparing with {base}: {e}");
+            Vec::new()
+        }
+    };
+
+    let severe = options.fail_on_severity.map_or(0, |level| {
+        todos.iter().filter(|todo| todo.severity >= level).count()
+    });
+    let exceeded = options.limits.exceeded(&todos);
+    let expired = if options.fail_expired {
+        due::expired(&todos)
+    } else {
+        Vec::new()
+    };
+    let mut summary = stats::Summary::compute(&todos).json();
+    summary["new"] = comparison.as_ref().ok().map(|_| new.len()).into();
+    timings::summarize(summary);
+
+    let failed = (options.fail_on_new && (comparison.is_err() || !new.is_empty()))
+        || severe > 0
+        || !exceeded.is_empty()
+        || !expired.is_empty();
+
+    if options.bitbucket {
+        match bitbucket::report(repo, &todos, new.len(), failed) {
+            Ok(()) if options.quiet => {}
+            Ok(()) => eprintln!("Posted Code Insights report to Bitbucket"),
+            Err(e) => eprintln!("Error reporting to Bitbucket: {e}"),
+        }
+    }
+
+    let matcher = Matcher::configured(options);
+    let mut uploaded = true;
+    if let Some(url) = &options.upload {
+        let (body, content_type) =
+            upload::report(repo, &matcher, &todos, options.format, &metadata);
+        match upload::put(repo, url, body, content_type) {
+            Ok(()) if options.quiet => {}
+            Ok(()) => eprintln!("Uploaded report to {}", http::redact(url)),
+            Err(e) => {
+                eprintln!("Error uploading report: {e}");
+                uploaded = false;
+            }
+        }
+    }
+
+    if options.fail_on_new && !new.is_empty() {
+        eprintln!(
+            "\n{} {} new TODO{} compared to {}:",
+            "error:".red().bold(),
+            new.len(),
+            if new.len() == 1 { "" } else { "s" },
+            base
+        );
+        for fingerprint in new {
+            eprintln!("  {}", highlight_todo(&matcher, fingerprint));
+        }
+    }
+
+    if let Some(level) = options.fail_on_severity.filter(|_| severe > 0) {
+        eprintln!(
+            "\n{} {severe} TODO{} of {level} severity or higher",
+            "error:".red().bold(),
+            if severe == 1 { "" } else { "s" },
+        );
+    }
+    for exceeded in exceeded {
+        eprintln!("\n{} {exceeded}", "error:".red().bold());
+    }
+    due::report_expired(repo, &expired);
+
+    !failed && uploaded && written
+}
+
+fn report_error<E: std::fmt::Display>(result: Result<(), E>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            false
+        }
+    }
+}
+
+/// Runs the command `options` describe, as the `todo` binary does, in the
+/// repository in the current directory. Returns `false` if it fails.
+#[doc(hidden)]
+#[must_use]
+pub fn run(options: &Options) -> bool {
+    dates::configure(options);
+    dry_run::configure(options);
+    timings::configure(options);
+
+    if let Some(endpoint) = &options.otel_endpoint {
+        telemetry::init(endpoint);
+    }
+    let run = telemetry::span("todo");
+    let total = timings::phase("total");
+
+    let success = match &options.command {
+        Command::Workspace { dir, repos } => {
+            let dir = dir.as_deref().unwrap_or_else(|| Path::new("."));
+            workspace::run(options, dir, repos.as_deref())
+        }
+        _ => match get_repo(Path::new(".")) {
+            Ok(repo) => match workdir(&repo).and_then(|root| config::options(root, options)) {
+                Ok(options) => {
+                    redact::configure(&options);
+                    run_command(&repo, &options)
+                }
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    false
+                }
+            },
+            Err(e) => {
+                eprintln!("Error: {e}");
+                false
+            }
+        },
+    };
+
+    drop(run);
+    drop(total);
+    timings::report(success);
+    if let Err(e) = telemetry::export() {
+        eprintln!("Error exporting traces: {e}");
+    }
+    success
+}
+
+/// Runs the command on one repository. Returns `false` if it fails.
+#[allow(clippy::too_many_lines)] // one arm per command
+fn run_command(repo: &Repository, options: &Options) -> bool {
+    match &options.command {
+        Command::List => list(repo, options),
+        Command::Check => list(
+            repo,
+            &Options {
+                fail_on_new: true,
+                ..options.clone()
+            },
+        ),
+        Command::Stats { push, by_language } => {
+            print_stats(repo, options, push.as_deref(), *by_language)
+        }
+        Command::Hotspots { top } => report_error(hotspots::report(&scan(repo, options), *top)),
+        Command::Serve {
+            bind,
+            port,
+            interval_secs,
+            allow_origin,
+        } => {
+            let interval = std::time::Duration::from_secs(*interval_secs);
+            let addr = std::net::SocketAddr::new(*bind, *port);
+            report_error(serve::run(
+                repo,
+                options,
+                addr,
+                interval,
+                allow_origin.as_deref(),
+            ))
+        }
+        Command::Snapshot(SnapshotAction::Record) => {
+            report_error(snapshot::record(repo, &scan(repo, options)))
+        }
+        Command::Snapshot(SnapshotAction::Log) => report_error(snapshot::log(repo)),
+        Command::Snapshot(SnapshotAction::Diff { from, to }) => {
+            report_error(snapshot::diff(repo, from.as_deref(), to.as_deref()))
+        }
+        Command::Handover { author } => {
+            // a handover covers everything the person owns, not just this branch
+            let options = Options {
+                all_files: true,
+                ..options.clone()
+            };
+            let todos = scan(repo, &options);
+            print!("{}", handover::report(repo, &options, &todos, author));
+            true
+        }
+        Command::Rotate { week, send } => {
+            let team = match team::Team::load(options.team.as_deref().unwrap()) {
+                Ok(team) => team,
+                Err(e) => {
+                    eprintln!("Error reading team: {e}");
+                    return false;
+                }
+            };
+            // duty covers all of the repository's debt, not just this branch
+            let todos = scan(
+                repo,
+                &Options {
+                    all_files: true,
+                    ..options.clone()
+                },
+            );
+            let root = repo.workdir().unwrap();
+            let week = week.unwrap_or_else(rotate::Week::current);
+            let report = rotate::report(&rotate::assign(&team, &todos, root, week), root, week);
+            print!("{report}");
+            !*send || report_error(rotate::send(repo, &team, &report))
+        }
+        Command::Init { yes, force } => report_error(init::run(repo, options, *yes, *force)),
+        Command::AuthLogin {
+            provider: Some(provider),
+        } => report_error(credentials::login(*provider)),
+        Command::Auth
+        | Command::AuthLogin { provider: None }
+        | Command::Baseline
+        | Command::Export => unreachable!(),
+        Command::Flush => report_error(queue::flush(repo)),
+        Command::Bench(bench) => report_error(bench::run(options, bench)),
+        Command::Done { target } => {
+            report_error(done::run(repo, options, target.as_deref().unwrap()))
+        }
+        Command::BaselineWrite => {
+            let options = Options {
+                all_files: true,
+                no_baseline: true,
+                ..options.clone()
+            };
+            report_error(baseline::write(repo, &scan(repo, &options)))
+        }
+        Command::BaselineMerge { files } => report_error(match files.as_slice() {
+            [base, ours, theirs] => baseline::merge_files(base, ours, theirs),
+            _ => baseline::merge_conflict(repo),
+        }),
+        Command::Lint => lint::run(repo, &scan(repo, options), &options.allowed_tags),
+        Command::ExportGithubIssues { ids } => {
+            let matcher = Matcher::configured(options);
+            report_error(github_issues::run(
+                repo,
+                &matcher,
+                &scan(repo, options),
+                ids,
+            ))
+        }
+        Command::Tui => report_error(tui::run(repo, options, &scan(repo, options))),
+        Command::Branches { remote } => report_error(branches::report(repo, options, *remote)),
+        Command::RebasePlan => report_error(branch_commits::print_rebase_plan(repo, options)),
+        Command::PerCommit => report_error(branch_commits::print_per_commit(repo, options)),
+        Command::Workspace { .. } => unreachable!("workspaces span repositories"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::strip_ansi;
+
+    fn statement(line: &str) -> String {
+        strip_ansi(&parse_todo(&Matcher::default(), line).2)
+    }
+
+    #[test]
+    fn strips_c_style_terminators() {
+        assert_eq!(
+            statement("/* TODO: free the buffer */"),
+            "/* TODO: free the buffer"
+        );
+        assert_eq!(
+            statement("  * TODO(perf): cache this  */  "),
+            "  * TODO(perf): cache this"
+        );
+    }
+
+    #[test]
+    fn strips_html_and_template_terminators() {
+        assert_eq!(
+            statement("<!-- TODO: add alt text -->"),
+            "<!-- TODO: add alt text"
+        );
+        assert_eq!(statement("{# TODO: translate #}"), "{# TODO: translate");
+    }
+
+    #[test]
+    fn strips_python_quotes() {
+        assert_eq!(statement("# TODO: handle None"), "# TODO: handle None");
+        assert_eq!(
+            statement(r#""""TODO: document this""""#),
+            r#""""TODO: document this"#
+        );
+        assert_eq!(statement("x = 'TODO: rename';"), "x = 'TODO: rename");
+    }
+
+    #[test]
+    fn collapses_excess_punctuation() {
+        assert_eq!(statement("// TODO: why???"), "// TODO: why?");
+        assert_eq!(statement("// TODO: fix this!! -->"), "// TODO: fix this!");
+        assert_eq!(
+            statement("// TODO: is this right?"),
+            "// TODO: is this right?"
+        );
+    }
+
+    #[test]
+    fn normalizes_statements() {
+        assert_eq!(
+            normalize_statement(&Matcher::default(), "/* TODO(mem):   free  the buffer */"),
+            "free the buffer"
+        );
+        assert_eq!(
+            normalize_statement(&Matcher::default(), "<!-- todo add alt text -->"),
+            "add alt text"
+        );
+        assert_eq!(
+            normalize_statement(&Matcher::default(), "# TODO: handle None"),
+            "handle None"
+        );
+    }
+
+    #[test]
+    fn ignores_carriage_returns() {
+        assert_eq!(
+            statement("// TODO: windows line endings\r"),
+            "// TODO: windows line endings"
+        );
+        assert_eq!(
+            normalize_statement(&Matcher::default(), "# TODO: mixed */\r"),
+            "mixed"
+        );
+    }
+
+    #[test]
+    fn strips_verbatim_prefixes() {
+        assert_eq!(
+            strip_verbatim(Path::new(r"\\?\C:\repo\src\main.rs")),
+            Path::new(r"C:\repo\src\main.rs")
+        );
+        assert_eq!(
+            strip_verbatim(Path::new(r"\\?\UNC\server\share\a.rs")),
+            Path::new(r"\\server\share\a.rs")
+        );
+        assert_eq!(strip_verbatim(Path::new("src/a.rs")), Path::new("src/a.rs"));
+    }
+
+    #[test]
+    fn keeps_tags() {
+        let (tags, _, _) = parse_todo(&Matcher::default(), "/* TODO(a, b): x */");
+        assert_eq!(tags, ["a", "b"]);
+        let (tags, fields, _) = parse_todo(&Matcher::default(), "// TODO(a, issue=#12): x");
+        assert_eq!(tags, ["a"]);
+        assert_eq!(fields.issue.as_deref(), Some("#12"));
+    }
+
+    #[test]
+    fn groups_by_chosen_levels() {
+        let todo = |path: &str, tags: &[&str]| {
+            let mut todo = Todo::new(&Matcher::default(), PathBuf::from(path), 1, "// TODO: x");
+            todo.tags = tags.iter().map(ToString::to_string).collect();
+            todo
+        };
+        let todos = [todo("/r/b/x.rs", &[]), todo("/r/a/y.rs", &["perf", "db"])];
+
+        let names = |grouped: &Grouped| match grouped {
+            Grouped::Groups(_, groups) => groups
+                .iter()
+                .map(|(key, below)| (strip_ansi(&key.display), below.count()))
+                .collect(),
+            Grouped::Todos(_) => Vec::new(),
+        };
+        let by_tag = group_todos(&todos, &"tag,file".parse().unwrap(), "none", false);
+        assert_eq!(
+            names(&by_tag),
+            [("db".into(), 1), ("perf".into(), 1), ("none".into(), 1)]
+        );
+        let Grouped::Groups(Grouping::Tag, tags) = &by_tag else {
+            panic!("not grouped by tag");
+        };
+        assert!(matches!(tags[0].1, Grouped::Groups(Grouping::File, _)));
+
+        let by_dir = group_todos(&todos, &"dir".parse().unwrap(), "none", false);
+        assert_eq!(names(&by_dir), [("/r/a".into(), 1), ("/r/b".into(), 1)]);
+        let flat = group_todos(&todos, &"none".parse().unwrap(), "none", false);
+        assert!(matches!(flat, Grouped::Todos(todos) if todos.len() == 2));
+
+        assert_eq!(
+            "milestone".parse::<GroupBy>().unwrap().0,
+            [Grouping::Milestone, Grouping::Tag, Grouping::Author]
+        );
+        assert!("tag,tag".parse::<GroupBy>().is_err());
+    }
+
+    #[test]
+    fn sorts_todos_and_their_groups() {
+        let todo = |path: &str, tag: &str, priority: Option<u32>| {
+            let mut todo = Todo::new(&Matcher::default(), PathBuf::from(path), 1, "// TODO: x");
+            todo.tags = vec![tag.to_string()];
+            todo.fields.priority = priority;
+            todo
+        };
+        let mut todos = [
+            todo("/r/a.rs", "a", None),
+            todo("/r/b.rs", "b", Some(2)),
+            todo("/r/c.rs", "b", Some(1)),
+        ];
+        let paths = |todos: &[Todo]| -> Vec<_> {
+            todos
+                .iter()
+                .map(|todo| todo.file_path.display().to_string())
+                .collect()
+        };
+
+        sort_todos(&mut todos, Sort::Priority, false);
+        assert_eq!(paths(&todos), ["/r/c.rs", "/r/b.rs", "/r/a.rs"]);
+        let Grouped::Groups(_, tags) = group_todos(&todos, &"tag".parse().unwrap(), "", true)
+        else {
+            panic!("not grouped");
+        };
+        let tags: Vec<_> = tags.iter().map(|(key, _)| key.id.as_str()).collect();
+        assert_eq!(tags, ["b", "a"]);
+
+        sort_todos(&mut todos, Sort::File, true);
+        assert_eq!(paths(&todos), ["/r/c.rs", "/r/b.rs", "/r/a.rs"]);
+        sort_todos(&mut todos, Sort::Priority, true);
+        assert_eq!(paths(&todos), ["/r/a.rs", "/r/b.rs", "/r/c.rs"]);
+    }
+
+    #[test]
+    fn scans_only_what_is_not_committed() {
+        let test = test_repo::TestRepo::new("staged");
+        test.write("a.rs", "// TODO: committed\n");
+        test.commit("Alice", "a");
+        test.write("a.rs", "// TODO: committed\n// TODO: staged\n");
+        let mut index = test.repo.index().unwrap();
+        index.add_path(Path::new("a.rs")).unwrap();
+        index.write().unwrap();
+        test.write(
+            "a.rs",
+            "// TODO: committed\n// TODO: staged\n// TODO: unstaged\n",
+        );
+        test.write("b.rs", "// TODO: untracked\n");
+
+        let lines = |staged: bool, dirty: bool, whole_files: bool| {
+            let options = Options {
+                staged,
+                dirty,
+                whole_files,
+                no_cache: true,
+                ..Options::default()
+            };
+            let (todos, _) = get_todos(&test.repo, &options);
+            todos
+                .iter()
+                .map(|todo| {
+                    let path = todo.file_path.display();
+                    format!("{path}:{}{}", todo.line, todo.author)
+                })
+                .collect::<Vec<_>>()
+        };
+        let path = |name: &str, line: usize| format!("{}:{line}", test.dir.join(name).display());
+        assert_eq!(lines(true, false, false), [path("a.rs", 2)]);
+        assert_eq!(
+            lines(true, false, true),
+            [path("a.rs", 1) + "Alice", path("a.rs", 2)]
+        );
+        assert_eq!(
+            lines(false, true, false),
+            [path("a.rs", 2), path("a.rs", 3), path("b.rs", 1)]
+        );
+    }
+
+    #[test]
+    fn blames_edits_in_the_working_tree() {
+        let test = test_repo::TestRepo::new("all");
+        test.write("a.rs", "// TODO: committed\n");
+        test.commit("Alice", "a");
+        test.write("a.rs", "// TODO: new\n\n// TODO: committed\n");
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = get_todos(&test.repo, &options);
+        let authors = todos
+            .iter()
+            .map(|todo| (todo.line, todo.author.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(authors, [(1, ""), (3, "Alice")]);
+    }
+
+    #[test]
+    fn blames_untouched_lines_of_whole_dirty_files() {
+        let test = test_repo::TestRepo::new("dirty");
+        test.write("a.rs", "// TODO: old committed\n");
+        test.commit("Alice", "a");
+        test.write("a.rs", "// TODO: old committed\n// TODO: new\n");
+        test.write("b.rs", "// TODO: untracked\n");
+
+        let authors = |options: Options| {
+            let (todos, _) = get_todos(&test.repo, &options);
+            todos
+                .iter()
+                .map(|todo| format!("{}:{}", todo.line, todo.author))
+                .collect::<Vec<_>>()
+        };
+        // a run with --all first, whose cache must not be reused
+        authors(Options {
+            all_files: true,
+            ..Options::default()
+        });
+        let dirty = |whole_files| Options {
+            dirty: true,
+            whole_files,
+            ..Options::default()
+        };
+        assert_eq!(authors(dirty(false)), ["2:", "1:"]);
+        assert_eq!(authors(dirty(true)), ["1:Alice", "2:", "1:"]);
+    }
+
+    #[test]
+    fn reads_the_branch_as_committed() {
+        let test = test_repo::TestRepo::new("head");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("a.rs", "// TODO: same\nfn a() {}\n// TODO: same\n");
+        test.commit("Bob", "b");
+        // not committed, so neither hides nor shifts the committed TODOs
+        test.write("a.rs", "fn a() {}\n// TODO: same\n");
+
+        let options = Options {
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = get_todos(&test.repo, &options);
+        let lines: Vec<_> = todos.iter().map(|todo| todo.line).collect();
+        assert_eq!(lines, [1, 3]);
+        assert!(todos.iter().all(|todo| todo.author == "Bob"));
+        assert_ne!(todos[0].id, todos[1].id);
+        assert_eq!(
+            scanned_source(&test.repo, &options, &todos[0].file_path).as_deref(),
+            Some("// TODO: same\nfn a() {}\n// TODO: same\n")
+        );
+    }
+
+    #[test]
+    fn configures_again() {
+        // each library call configures the run anew
+        for _ in 0..2 {
+            let options = Options::default();
+            dates::configure(&options);
+            dry_run::configure(&options);
+            timings::configure(&options);
+            redact::configure(&options);
+        }
+        assert!(!dry_run::enabled());
+    }
+
+    #[test]
+    fn scanners_keep_their_own_settings() {
+        let test = test_repo::TestRepo::new("scanners");
+        test.write("a.rs", "// TODO: one\n// FIXME: two\n");
+        test.commit("Alice", "a");
+
+        let lines = |keyword: Option<&str>| {
+            let repo = Repository::open(&test.dir).unwrap();
+            let mut scanner = Scanner::new(&repo).all_files(true);
+            if let Some(keyword) = keyword {
+                scanner = scanner.keyword(keyword);
+            }
+            let todos = scanner.scan();
+            todos.iter().map(|todo| todo.line).collect::<Vec<_>>()
+        };
+        thread::scope(|threads| {
+            let fixme = threads.spawn(|| lines(Some("FIXME")));
+            let todo = threads.spawn(|| lines(None));
+            assert_eq!(fixme.join().unwrap(), [1, 2]);
+            assert_eq!(todo.join().unwrap(), [1]);
+        });
+
+        let bare = Repository::init_bare(test.dir.join("bare.git")).unwrap();
+        assert!(Scanner::new(&bare).all_files(true).scan().is_empty());
+        assert!(Scanner::new(&bare).scan().is_empty());
+    }
+
+    #[test]
+    fn credits_merged_lines_to_their_author() {
+        let test = test_repo::TestRepo::new("merge");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("a.rs", "fn a() {}\n// TODO: from the branch\n");
+        test.commit("Bob", "b");
+        test.checkout("main");
+        test.write("b.rs", "fn b() {}\n");
+        test.commit("Alice", "c");
+        test.merge("feature", "Carol", "merge feature");
+
+        let author = |first_parent, merge_attribution| {
+            let options = Options {
+                all_files: true,
+                first_parent,
+                merge_attribution,
+                no_cache: true,
+                ..Options::default()
+            };
+            let (todos, _) = get_todos(&test.repo, &options);
+            todos[0].author.clone()
+        };
+        assert_eq!(author(false, MergeAttribution::Merge), "Bob");
+        assert_eq!(author(true, MergeAttribution::Merge), "Carol");
+        assert_eq!(author(true, MergeAttribution::Original), "Bob");
+    }
+
+    #[test]
+    fn first_parent_blames_merged_lines_on_the_merge() {
+        let test = test_repo::TestRepo::new("first-parent");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.branch("side");
+        test.write("a.rs", "fn a() {}\n// TODO: from the side\n");
+        let side = test.commit("Bob", "side");
+        test.checkout("feature");
+        test.write("b.rs", "fn b() {}\n");
+        test.commit("Carol", "b");
+        let merge = test.merge("side", "Carol", "merge side");
+
+        let blamed = |first_parent| {
+            let options = Options {
+                first_parent,
+                no_cache: true,
+                ..Options::default()
+            };
+            let (todos, _) = get_todos(&test.repo, &options);
+            assert_eq!(todos.len(), 1);
+            todos[0].commit_hash.clone()
+        };
+        assert_eq!(blamed(false), side.to_string());
+        assert_eq!(blamed(true), merge.to_string());
+    }
+
+    #[test]
+    fn scans_the_same_in_parallel() {
+        let test = test_repo::TestRepo::new("parallel");
+        for index in 0..8 {
+            test.write(
+                &format!("f{index}.rs"),
+                &format!("fn f() {{}}\n// TODO: {index}\n// TODO(x): {index} more\n"),
+            );
+        }
+        test.commit("Alice", "a");
+
+        let scan = |jobs| {
+            let options = Options {
+                all_files: true,
+                no_cache: true,
+                jobs: Some(jobs),
+                ..Options::default()
+            };
+            let (todos, _) = get_todos(&test.repo, &options);
+            todos
+                .into_iter()
+                .map(|todo| (todo.file_path, todo.line, todo.raw, todo.commit_hash))
+                .collect::<Vec<_>>()
+        };
+        let serial = scan(1);
+        assert_eq!(serial.len(), 16);
+        assert_eq!(scan(4), serial);
+    }
+
+    #[test]
+    fn lists_co_authored_todos_under_each_author() {
+        let test = test_repo::TestRepo::new("co-authors");
+        test.write("a.rs", "// TODO: pair\n");
+        test.commit("Alice", "a\n\nCo-authored-by: Dana <dana@example.com>\n");
+        test.write("b.rs", "// TODO: solo\n");
+        test.commit("Bob", "b");
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = get_todos(&test.repo, &options);
+        let co_authors: Vec<_> = todos.iter().map(|todo| todo.co_authors.clone()).collect();
+        assert_eq!(co_authors, [vec!["Dana".to_string()], vec![]]);
+
+        let Grouped::Groups(_, groups) =
+            group_todos(&todos, &GroupBy(vec![Grouping::Author]), "untagged", false)
+        else {
+            panic!("TODOs weren't grouped");
+        };
+        let mut authors: Vec<_> = groups.iter().map(|(key, _)| key.id.as_str()).collect();
+        authors.sort_unstable();
+        assert_eq!(authors, ["Alice", "Bob", "Dana"]);
+    }
+
+    #[test]
+    fn writes_each_output_from_one_scan() {
+        let test = test_repo::TestRepo::new("outputs");
+        test.write("a.rs", "// TODO(perf): one\n");
+        test.commit("Alice", "a");
+
+        let path = |name: &str| test.dir.join(name).display().to_string();
+        let out = |outputs: &[String]| -> Vec<Output> {
+            outputs
+                .iter()
+                .map(|output| output.parse().unwrap())
+                .collect()
+        };
+        let mut options = Options {
+            all_files: true,
+            no_cache: true,
+            quiet: true,
+            out: out(&[
+                format!("json={}", path("todos.json")),
+                format!("csv={}", path("todos.csv")),
+                format!("tree={}", path("todos.txt")),
+            ]),
+            ..Options::default()
+        };
+        let (mut todos, _) = get_todos(&test.repo, &options);
+        let grouped = sorted_and_grouped(&mut todos, &options);
+        let metadata = metadata::Metadata::new(&test.repo, &options);
+        let report = Report {
+            todos: &todos,
+            grouped: &grouped,
+            metadata: &metadata,
+            totals: "1 TODO",
+            markers: None,
+        };
+        assert!(write_outputs(&test.repo, &options, &report));
+
+        let read = |name: &str| std::fs::read_to_string(test.dir.join(name)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&read("todos.json")).unwrap();
+        assert_eq!(json["todos"][0]["statement"], "one");
+        assert!(read("todos.csv").contains("\npath,line,raw,"));
+        // files get no colors
+        let tree = read("todos.txt");
+        assert!(tree.contains("perf") && tree.ends_with("1 TODO\n"));
+        assert!(!tree.contains('\u{1b}'));
+
+        // the other outputs are still written when one can't be
+        options.out = out(&[
+            format!("json={}", path("missing/todos.json")),
+            format!("csv={}", path("again.csv")),
+        ]);
+        assert!(!write_outputs(&test.repo, &options, &report));
+        assert!(test.dir.join("again.csv").exists());
+    }
+
+    #[test]
+    fn filters_by_author_and_the_current_user() {
+        let test = test_repo::TestRepo::new("authors");
+        test.write(
+            "a.rs",
+            "// TODO: alice's\n// TODO(@carol): handed to carol\n",
+        );
+        test.commit("Alice", "a");
+        test.write("b.rs", "// TODO: bob's\n");
+        test.commit("Bob", "b");
+
+        let raws = |options: &Options| {
+            let mut raws: Vec<_> = scan(&test.repo, options)
+                .into_iter()
+                .map(|todo| todo.raw)
+                .collect();
+            raws.sort();
+            raws
+        };
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        // by name, email, its local part or an assignment
+        for (author, expected) in [
+            (
+                "alice",
+                vec!["// TODO(@carol): handed to carol", "// TODO: alice's"],
+            ),
+            ("bob@example.com", vec!["// TODO: bob's"]),
+            ("@carol", vec!["// TODO(@carol): handed to carol"]),
+        ] {
+            let options = Options {
+                authors: vec![author.to_string()],
+                ..options.clone()
+            };
+            assert_eq!(raws(&options), expected, "{author}");
+        }
+
+        let mine = Options {
+            mine: true,
+            ..options
+        };
+        test.repo
+            .config()
+            .unwrap()
+            .set_str("user.email", "bob@example.com")
+            .unwrap();
+        assert_eq!(raws(&mine), ["// TODO: bob's"]);
+    }
+}
@@ -0,0 +1,139 @@
+//! How many TODOs a run may report before it fails: in total, of each
+//! severity and with each tag, so CI can hold a branch to a budget, or to no
+//! untagged TODOs at all, with `todo check --max-untagged 0`.
+
+use crate::severity::Severity;
+use crate::Todo;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub total: Option<usize>,
+    /// Limits on TODOs of exactly each severity, later entries winning.
+    pub severities: Vec<(Severity, usize)>,
+    /// Limits on TODOs with each tag, later entries winning.
+    pub tags: Vec<(String, usize)>,
+    /// The limit on TODOs without tags.
+    pub untagged: Option<usize>,
+}
+
+/// Splits a `NAME=N` limit.
+fn split(flag: &str, spec: &str) -> Result<(String, usize), String> {
+    let invalid = || format!("invalid limit '{spec}' for '{flag}', expected NAME=N");
+    let (name, limit) = spec.split_once('=').ok_or_else(invalid)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(invalid());
+    }
+    let limit = limit.trim().parse().map_err(|_| invalid())?;
+    Ok((name.to_string(), limit))
+}
+
+impl Limits {
+    /// Adds a `SEVERITY=N` limit.
+    pub fn add_severity(&mut self, flag: &str, spec: &str) -> Result<(), String> {
+        let (severity, limit) = split(flag, spec)?;
+        self.severities.push((severity.parse()?, limit));
+        Ok(())
+    }
+
+    /// Adds a `TAG=N` limit.
+    pub fn add_tag(&mut self, flag: &str, spec: &str) -> Result<(), String> {
+        self.tags.push(split(flag, spec)?);
+        Ok(())
+    }
+
+    /// Fills in what `self` leaves unset from `other`.
+    pub fn fill(&mut self, other: Self) {
+        self.total = self.total.or(other.total);
+        self.untagged = self.untagged.or(other.untagged);
+        // later entries win, so the ones already set go last
+        let mut severities = other.severities;
+        severities.append(&mut self.severities);
+        self.severities = severities;
+        let mut tags = other.tags;
+        tags.append(&mut self.tags);
+        self.tags = tags;
+    }
+
+    /// One message for each limit `todos` exceed.
+    pub fn exceeded(&self, todos: &[Todo]) -> Vec<String> {
+        let mut exceeded = Vec::new();
+        let mut check = |what: &str, count: usize, limit: usize| {
+            if count > limit {
+                exceeded.push(format!("{what}: {count}, more than the limit of {limit}"));
+            }
+        };
+
+        if let Some(limit) = self.total {
+            check("TODOs", todos.len(), limit);
+        }
+        for (index, (severity, limit)) in self.severities.iter().enumerate() {
+            if self.severities[index + 1..]
+                .iter()
+                .any(|(s, _)| s == severity)
+            {
+                continue;
+            }
+            let count = todos
+                .iter()
+                .filter(|todo| todo.severity == *severity)
+                .count();
+            check(&format!("{severity} severity TODOs"), count, *limit);
+        }
+        for (index, (tag, limit)) in self.tags.iter().enumerate() {
+            if self.tags[index + 1..].iter().any(|(t, _)| t == tag) {
+                continue;
+            }
+            let count = todos.iter().filter(|todo| todo.tags.contains(tag)).count();
+            check(&format!("TODOs tagged '{tag}'"), count, *limit);
+        }
+        if let Some(limit) = self.untagged {
+            let count = todos.iter().filter(|todo| todo.tags.is_empty()).count();
+            check("untagged TODOs", count, limit);
+        }
+        exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use std::path::PathBuf;
+
+    #[test]
+    fn later_limits_win() {
+        let mut todo = Todo::new(&Matcher::default(), PathBuf::from("a.rs"), 1, "// TODO: x");
+        todo.severity = Severity::High;
+        let mut tagged = Todo::new(
+            &Matcher::default(),
+            PathBuf::from("a.rs"),
+            2,
+            "// TODO(untagged): x",
+        );
+        tagged.tags = vec!["untagged".to_string()];
+        let todos = [todo.clone(), todo, tagged];
+
+        let mut limits = Limits {
+            untagged: Some(0),
+            ..Limits::default()
+        };
+        limits.add_tag("--max-per-tag", "untagged=0").unwrap();
+        limits.add_severity("--max-per-severity", "high=5").unwrap();
+        limits.fill(Limits {
+            total: Some(1),
+            severities: vec![(Severity::High, 0)],
+            tags: vec![],
+            untagged: Some(5),
+        });
+        assert_eq!(
+            limits.exceeded(&todos),
+            [
+                "TODOs: 3, more than the limit of 1",
+                "TODOs tagged 'untagged': 1, more than the limit of 0",
+                "untagged TODOs: 2, more than the limit of 0",
+            ]
+        );
+        assert!(limits.add_tag("--max-per-tag", "untagged").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! `todo lint`: TODOs tagged with anything outside the `allowed-tags` of
+//! `.todo.toml`, so the tag taxonomy doesn't fragment into `perf`, `Perf`
+//! and `performance` over time. `@assignee` tags are always allowed.
+
+use std::fmt::Write;
+
+use git2::Repository;
+
+use crate::{repo_relative_path, Todo};
+
+/// The edit distance between `a` and `b`, in characters.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The allowed tag `tag` was most likely meant to be, ignoring case, if one
+/// is close enough: a third of its characters may differ, at least one.
+fn suggestion<'a>(tag: &str, allowed: &'a [String]) -> Option<&'a str> {
+    let tag = tag.to_lowercase();
+    let limit = (tag.chars().count() / 3).max(1);
+    allowed
+        .iter()
+        .map(|candidate| (distance(&tag, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// One message for each unknown tag on each of `todos`.
+pub fn problems(repo: &Repository, todos: &[Todo], allowed: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for todo in todos {
+        for tag in &todo.tags {
+            if tag.starts_with('@') || allowed.contains(tag) {
+                continue;
+            }
+            let mut problem = format!(
+                "{}:{}: unknown tag '{tag}'",
+                repo_relative_path(repo, &todo.file_path),
+                todo.line
+            );
+            if let Some(suggestion) = suggestion(tag, allowed) {
+                let _ = write!(problem, ", did you mean '{suggestion}'?");
+            }
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+/// Prints the problems with `todos`, returning whether there were none.
+pub fn run(repo: &Repository, todos: &[Todo], allowed: &[String]) -> bool {
+    let problems = problems(repo, todos, allowed);
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        eprintln!(
+            "{} unknown tag{}; allowed: {}",
+            problems.len(),
+            if problems.len() == 1 { "" } else { "s" },
+            allowed.join(", ")
+        );
+    }
+    problems.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_close_allowed_tags() {
+        let allowed: Vec<String> = ["perf", "security", "ui"].map(ToString::to_string).to_vec();
+        assert_eq!(suggestion("prf", &allowed), Some("perf"));
+        assert_eq!(suggestion("Perf", &allowed), Some("perf"));
+        assert_eq!(suggestion("secruity", &allowed), Some("security"));
+        assert_eq!(suggestion("UX", &allowed), Some("ui"));
+        assert_eq!(suggestion("performance", &allowed), None);
+        assert_eq!(suggestion("db", &allowed), None);
+    }
+}
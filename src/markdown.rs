@@ -0,0 +1,154 @@
+//! `--format markdown`: the tree as nested Markdown lists, each commit group
+//! folded into a `<details>` section, for bots to post as a pull request
+//! comment.
+
+use std::fmt::Write;
+
+use git2::Repository;
+
+use crate::github::strip_ansi;
+use crate::handover::permalink;
+use crate::metadata::Metadata;
+use crate::{
+    dates, pull_request, redact, repo_relative_path, stale_todos, Grouped, Grouping, Todo,
+};
+
+/// A TODO's text, safe to put in a list item.
+fn statement(todo: &Todo) -> String {
+    redact::shared(&strip_ansi(todo.statement.trim()))
+        .replace('`', "'")
+        .replace('<', "&lt;")
+}
+
+/// The groups below a top-level group as nested list items, from `depth`
+/// levels in, with each TODO shown by `item`.
+pub fn list(out: &mut String, grouped: &Grouped, depth: usize, item: &dyn Fn(&Todo) -> String) {
+    let indent = "  ".repeat(depth);
+    match grouped {
+        Grouped::Groups(level, groups) => {
+            for (key, below) in groups {
+                let title = strip_ansi(&key.display).replace('<', "&lt;");
+                let _ = match level {
+                    Grouping::Tag if key.id.is_empty() => writeln!(out, "{indent}- *{title}*"),
+                    Grouping::Tag => writeln!(out, "{indent}- **{title}**"),
+                    _ => writeln!(out, "{indent}- {title}"),
+                };
+                list(out, below, depth + 1, item);
+            }
+        }
+        Grouped::Todos(todos) => {
+            for todo in todos {
+                let _ = writeln!(out, "{indent}- {}", item(todo));
+            }
+        }
+    }
+}
+
+/// Renders the report, with `totals` in bold at the top.
+pub fn report(
+    repo: &Repository,
+    todos: &[Todo],
+    grouped: &Grouped,
+    metadata: &Metadata,
+    totals: &str,
+) -> String {
+    // link to the checked-out commit so the line numbers stay valid
+    let base = pull_request::web_url(repo);
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|id| id.to_string());
+    let link = |todo: &Todo| {
+        let path = repo_relative_path(repo, &todo.file_path);
+        let text = format!("`{path}:{}`", todo.line);
+        match (&base, &head) {
+            (Some(base), Some(head)) => {
+                format!("[{text}]({})", permalink(base, head, &path, todo.line))
+            }
+            _ => text,
+        }
+    };
+
+    let mut out = format!("## TODOs\n\n**{}**\n\n", strip_ansi(totals));
+
+    let stale = stale_todos(todos);
+    if !stale.is_empty() {
+        out.push_str("### Stale\n\n");
+        for todo in stale {
+            let written = todo.introduced_date.unwrap_or(todo.author_date);
+            let _ = writeln!(
+                out,
+                "- {} — {} ({}, {})",
+                link(todo),
+                statement(todo),
+                todo.author,
+                dates::ago(written),
+            );
+        }
+        out.push('\n');
+    }
+
+    let item = |todo: &Todo| format!("{} — {}", link(todo), statement(todo));
+    match grouped {
+        Grouped::Groups(_, groups) => {
+            for (key, below) in groups {
+                let count = below.count();
+                let _ = writeln!(
+                    out,
+                    "<details>\n<summary>{} · {count} TODO{}</summary>\n",
+                    strip_ansi(&key.display).replace('<', "&lt;"),
+                    if count == 1 { "" } else { "s" },
+                );
+                list(&mut out, below, 0, &item);
+                out.push_str("\n</details>\n\n");
+            }
+        }
+        Grouped::Todos(_) => {
+            list(&mut out, grouped, 0, &item);
+            out.push('\n');
+        }
+    }
+    let _ = writeln!(out, "<sub>{}</sub>", metadata.summary());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+    use crate::{group_todos, GroupBy};
+
+    #[test]
+    fn folds_groups_and_links_lines() {
+        let test = TestRepo::new("markdown");
+        test.write(
+            "src/a.rs",
+            "// TODO(perf): use `fast` <path>\n// TODO: later\n",
+        );
+        let head = test.commit("Alice", "a");
+        test.repo
+            .remote("origin", "git@github.com:acme/app.git")
+            .unwrap();
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let grouped = group_todos(&todos, &GroupBy(vec![Grouping::Tag]), "untagged", false);
+        let metadata = Metadata::new(&test.repo, &options);
+
+        let markdown = report(&test.repo, &todos, &grouped, &metadata, "2 TODOs");
+        assert!(markdown.starts_with("## TODOs\n\n**2 TODOs**\n\n"));
+        assert!(markdown.contains("<details>\n<summary>perf · 1 TODO</summary>\n"));
+        assert_eq!(markdown.matches("</details>").count(), 2);
+        // lines link to the commit scanned, and code and HTML are defused
+        assert!(markdown.contains(&format!(
+            "- [`src/a.rs:1`](https://github.com/acme/app/blob/{head}/src/a.rs#L1) \
+             — // TODO(perf): use 'fast' &lt;path>\n"
+        )));
+        assert!(markdown.trim_end().ends_with("</sub>"));
+    }
+}
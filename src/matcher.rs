@@ -0,0 +1,278 @@
+//! Decides which lines count as TODOs. Teams trade recall against false
+//! positives with `--match` presets and the individual strictness flags.
+
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::cli::Options;
+use crate::severity::Severity;
+
+const DEFAULT_KEYWORD: &str = "TODO";
+
+/// Comment openers a keyword may follow with `--comment-start`.
+pub const COMMENT_OPENERS: &str = r"(?://+!?|#+|/\*+!?|\*|<!--|--|;+|\{#|%+|'|REM\b)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preset {
+    /// Any line containing `todo`, even inside words like `todos`.
+    Loose,
+    /// `TODO` as a word in any case, anywhere on the line.
+    #[default]
+    Default,
+    /// Upper-case `TODO` opening a comment and followed by a colon.
+    Strict,
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "loose" => Ok(Self::Loose),
+            "default" => Ok(Self::Default),
+            "strict" => Ok(Self::Strict),
+            other => Err(format!("unknown match preset '{other}'")),
+        }
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)] // independent knobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strictness {
+    pub whole_word: bool,
+    pub case_sensitive: bool,
+    /// A colon must follow the keyword and its tags, as in `TODO(tag):`.
+    pub require_colon: bool,
+    /// The keyword must be the first thing in a comment.
+    pub comment_start: bool,
+}
+
+impl Preset {
+    #[must_use]
+    pub const fn strictness(self) -> Strictness {
+        match self {
+            Self::Loose => Strictness {
+                whole_word: false,
+                case_sensitive: false,
+                require_colon: false,
+                comment_start: false,
+            },
+            Self::Default => Strictness {
+                whole_word: true,
+                case_sensitive: false,
+                require_colon: false,
+                comment_start: false,
+            },
+            Self::Strict => Strictness {
+                whole_word: true,
+                case_sensitive: true,
+                require_colon: true,
+                comment_start: true,
+            },
+        }
+    }
+}
+
+/// Characters of scripts written without spaces between words, where a
+/// keyword has no word boundary to anchor on.
+fn is_unspaced_script(c: char) -> bool {
+    static SCRIPTS: OnceLock<Regex> = OnceLock::new();
+    let re = SCRIPTS.get_or_init(|| {
+        Regex::new(r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Thai}\p{Lao}\p{Khmer}]").unwrap()
+    });
+    re.is_match(c.encode_utf8(&mut [0; 4]))
+}
+
+/// One keyword, anchored on Unicode word boundaries where its ends are word
+/// characters, so `À-FAIRE` does not match inside `VOILÀ-FAIRE`.
+fn keyword_pattern(keyword: &str, whole_word: bool) -> String {
+    let boundary = |c: Option<char>| {
+        let anchored = c.is_some_and(|c| c.is_alphanumeric() && !is_unspaced_script(c));
+        if whole_word && anchored {
+            r"\b"
+        } else {
+            ""
+        }
+    };
+    format!(
+        "{}{}{}",
+        boundary(keyword.chars().next()),
+        regex::escape(keyword),
+        boundary(keyword.chars().last())
+    )
+}
+
+/// Decides which lines are TODOs and finds their keyword, tags and text, as
+/// configured by the matching options.
+pub struct Matcher {
+    /// Matches whole lines that count as TODOs.
+    line: Regex,
+    /// Matches just the keyword, for highlighting.
+    keyword: Regex,
+    /// Captures a TODO's tags and its text after the keyword.
+    parts: Regex,
+    /// Lowercased keywords with their severity.
+    severities: Vec<(String, Severity)>,
+}
+
+impl Matcher {
+    fn new(strictness: Strictness, keywords: &[String]) -> Self {
+        let flags = if strictness.case_sensitive {
+            ""
+        } else {
+            "(?i)"
+        };
+        let alternatives: Vec<_> = keywords
+            .iter()
+            .map(|keyword| keyword_pattern(keyword, strictness.whole_word))
+            .collect();
+        let keyword = format!("(?:{})", alternatives.join("|"));
+
+        let mut line = flags.to_string();
+        if strictness.comment_start {
+            line.push_str(r"(?:^|\s)");
+            line.push_str(COMMENT_OPENERS);
+            line.push_str(r"\s*");
+        }
+        line.push_str(&keyword);
+        if strictness.require_colon {
+            line.push_str(r"(?:\([^)]*\))?!?:");
+        }
+
+        Self {
+            line: Regex::new(&line).unwrap(),
+            keyword: Regex::new(&format!("{flags}{keyword}")).unwrap(),
+            parts: Regex::new(&format!(
+                r#"{flags}{keyword}(?:\((.*?)\))?(?:!|\:)?["'(]?(.*?)[)"']?$"#
+            ))
+            .unwrap(),
+            severities: keywords
+                .iter()
+                .map(|keyword| (keyword.to_lowercase(), Severity::of_keyword(keyword)))
+                .collect(),
+        }
+    }
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self::new(
+            Preset::default().strictness(),
+            &[DEFAULT_KEYWORD.to_string()],
+        )
+    }
+}
+
+impl Matcher {
+    /// The matcher the matching options ask for.
+    #[must_use]
+    pub fn configured(options: &Options) -> Self {
+        let mut strictness = options.match_preset.strictness();
+        strictness.case_sensitive |= options.case_sensitive;
+        strictness.require_colon |= options.require_colon;
+        strictness.comment_start |= options.comment_start;
+
+        let mut keywords = vec![DEFAULT_KEYWORD.to_string()];
+        keywords.extend(options.keywords.iter().cloned());
+
+        let mut matcher = Self::new(strictness, &keywords);
+        for (keyword, severity) in &options.severities {
+            let keyword = keyword.to_lowercase();
+            matcher.severities.retain(|(known, _)| *known != keyword);
+            matcher.severities.push((keyword, *severity));
+        }
+        matcher
+    }
+
+    /// Whether a line is a TODO.
+    #[must_use]
+    pub fn is_match(&self, line: &str) -> bool {
+        self.line.is_match(line)
+    }
+
+    /// Regex matching the keyword of a TODO.
+    pub(crate) const fn keyword(&self) -> &Regex {
+        &self.keyword
+    }
+
+    /// The first keyword on a TODO line, upper-cased, e.g. `FIXME`.
+    #[must_use]
+    pub fn keyword_in(&self, line: &str) -> String {
+        self.keyword
+            .find(line)
+            .map_or_else(|| "TODO".to_string(), |found| found.as_str().to_uppercase())
+    }
+
+    /// The severity of the first keyword on a TODO line.
+    #[must_use]
+    pub fn severity(&self, line: &str) -> Severity {
+        self.keyword
+            .find(line)
+            .and_then(|found| {
+                let found = found.as_str().to_lowercase();
+                self.severities
+                    .iter()
+                    .find(|(keyword, _)| *keyword == found)
+            })
+            .map_or_else(Severity::default, |(_, severity)| *severity)
+    }
+
+    /// Regex capturing a TODO's tags (group 1) and its text (group 2).
+    pub(crate) const fn parts(&self) -> &Regex {
+        &self.parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(preset: Preset, line: &str) -> bool {
+        Matcher::new(preset.strictness(), &["TODO".to_string()])
+            .line
+            .is_match(line)
+    }
+
+    fn matches_keywords(line: &str) -> bool {
+        let keywords = ["TODO", "PENDIENTE", "À-FAIRE", "要修正"].map(String::from);
+        Matcher::new(Preset::Default.strictness(), &keywords)
+            .line
+            .is_match(line)
+    }
+
+    #[test]
+    fn loose_matches_substrings() {
+        assert!(matches(Preset::Loose, "let todos = vec![];"));
+        assert!(!matches(Preset::Default, "let todos = vec![];"));
+    }
+
+    #[test]
+    fn default_matches_the_word_anywhere() {
+        assert!(matches(Preset::Default, "x = 1 # todo fix"));
+        assert!(matches(Preset::Default, "println!(\"TODO\");"));
+    }
+
+    #[test]
+    fn strict_requires_a_comment_and_colon() {
+        assert!(matches(Preset::Strict, "    // TODO: free"));
+        assert!(matches(Preset::Strict, "  /* TODO(mem): free */"));
+        assert!(matches(Preset::Strict, "<!-- TODO: alt text -->"));
+        assert!(!matches(Preset::Strict, "    // todo: free"));
+        assert!(!matches(Preset::Strict, "    // TODO free"));
+        assert!(!matches(Preset::Strict, "    // see the TODO: list"));
+        assert!(!matches(Preset::Strict, "println!(\"TODO: x\");"));
+    }
+
+    #[test]
+    fn matches_localized_keywords() {
+        assert!(matches_keywords("// pendiente: traducir"));
+        assert!(matches_keywords("# À-FAIRE : vérifier"));
+        assert!(matches_keywords("// à-faire vérifier"));
+        assert!(!matches_keywords("// VOILÀ-FAIRE"));
+        assert!(matches_keywords("// 要修正：境界値"));
+        assert!(matches_keywords("// これは要修正です"));
+        assert!(!matches_keywords("// pendientes"));
+    }
+}
@@ -0,0 +1,130 @@
+//! What a report was made from: the tool version, repository, base, `HEAD`,
+//! scan time and options, so an archived report can be interpreted and the
+//! scan reproduced later.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use serde_json::json;
+
+use crate::cli::Options;
+use crate::commit_info::short_hash;
+use crate::html::escape;
+use crate::http::redact;
+use crate::{base, dates, pull_request};
+
+pub struct Metadata {
+    repository: String,
+    base: String,
+    head: Option<String>,
+    /// `head` abbreviated like git does.
+    short_head: Option<String>,
+    scanned_at: DateTime<Utc>,
+    /// The command line, without the program name and with URLs redacted.
+    arguments: Vec<String>,
+    /// Keywords from the command line and `.todo.toml` together.
+    keywords: Vec<String>,
+}
+
+impl Metadata {
+    pub fn new(repo: &Repository, options: &Options) -> Self {
+        let repository = pull_request::web_url(repo).unwrap_or_else(|| {
+            repo.workdir()
+                .unwrap_or_else(|| repo.path())
+                .display()
+                .to_string()
+        });
+        let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        Self {
+            repository,
+            base: base::name(repo, options),
+            head: head.as_ref().map(|head| head.id().to_string()),
+            short_head: head.map(|head| short_hash(head.as_object())),
+            scanned_at: dates::now(),
+            arguments: std::env::args().skip(1).map(|arg| redact(&arg)).collect(),
+            keywords: options.keywords.clone(),
+        }
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        json!({
+            "tool": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "repository": self.repository,
+            "base": self.base,
+            "head": self.head,
+            "scanned_at": dates::rfc3339(self.scanned_at),
+            "options": {
+                "arguments": self.arguments,
+                "keywords": self.keywords,
+            },
+        })
+    }
+
+    /// Adds the metadata to a JSON report: as a `metadata` key of an object,
+    /// or by moving an array under `todos` next to it.
+    pub fn attach(&self, report: serde_json::Value) -> serde_json::Value {
+        match report {
+            serde_json::Value::Object(mut report) => {
+                report.insert("metadata".to_string(), self.json());
+                report.into()
+            }
+            todos => json!({ "metadata": self.json(), "todos": todos }),
+        }
+    }
+
+    /// Labelled values, for formats without structure.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut keywords = vec!["TODO".to_string()];
+        keywords.extend(self.keywords.iter().cloned());
+        vec![
+            (
+                "tool",
+                concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).to_string(),
+            ),
+            ("repository", self.repository.clone()),
+            ("base", self.base.clone()),
+            (
+                "head",
+                self.head.clone().unwrap_or_else(|| "(none)".to_string()),
+            ),
+            ("scanned at", dates::rfc3339(self.scanned_at)),
+            ("arguments", self.arguments.join(" ")),
+            ("keywords", keywords.join(", ")),
+        ]
+    }
+
+    /// A `<details>` block listing the fields, for HTML reports.
+    pub fn html(&self) -> String {
+        let mut out =
+            String::from("<details class=\"muted\"><summary>Scan details</summary><dl>\n");
+        for (label, value) in self.fields() {
+            let _ = writeln!(out, "<dt>{}</dt><dd>{}</dd>", escape(label), escape(&value));
+        }
+        out.push_str("</dl></details>\n");
+        out
+    }
+
+    /// One line for human-readable reports, e.g.
+    /// `todo 0.1.0 · github.com/o/r @ 1a2b3c4 vs main · 2024-05-01T14:03:00Z`.
+    pub fn summary(&self) -> String {
+        let head = self.short_head.as_deref().unwrap_or("(no commits)");
+        let mut summary = format!(
+            "{} {} · {} @ {head} vs {} · {}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            self.repository,
+            self.base,
+            dates::rfc3339(self.scanned_at),
+        );
+        if !self.arguments.is_empty() {
+            let _ = write!(summary, " · {}", self.arguments.join(" "));
+        }
+        summary
+    }
+}
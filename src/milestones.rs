@@ -0,0 +1,84 @@
+//! Maps tags to milestones or epics, so planning views follow the roadmap
+//! rather than ad-hoc tag names.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::Todo;
+
+pub struct Milestones {
+    by_tag: HashMap<String, String>,
+}
+
+impl Milestones {
+    /// Reads a milestones file with one `tag = milestone` mapping per line,
+    /// e.g. `v2 = 2.0 launch`. Blank lines and lines starting with `#` are
+    /// ignored.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut by_tag = HashMap::new();
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((tag, milestone)) = line.split_once('=') else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected `tag = milestone`", index + 1),
+                ));
+            };
+            by_tag.insert(tag.trim().to_string(), milestone.trim().to_string());
+        }
+        Ok(Self { by_tag })
+    }
+
+    pub fn assign(&self, todos: &mut [Todo]) {
+        for todo in todos {
+            todo.milestone = todo
+                .tags
+                .iter()
+                .find_map(|tag| self.by_tag.get(tag))
+                .cloned();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::matcher::Matcher;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn maps_the_first_mapped_tag_to_its_milestone() {
+        let test = TestRepo::new("milestones");
+        test.write(
+            "milestones",
+            "# roadmap\nv2 = 2.0 launch\n\nperf=Q3 speedups\n",
+        );
+        let milestones = Milestones::load(&test.dir.join("milestones")).unwrap();
+
+        let mut todos: Vec<_> = [&["cleanup", "perf"][..], &["v2"], &[]]
+            .into_iter()
+            .enumerate()
+            .map(|(n, tags)| {
+                let mut todo = Todo::new(&Matcher::default(), PathBuf::from("a.rs"), n + 1, "");
+                todo.tags = tags.iter().map(ToString::to_string).collect();
+                todo
+            })
+            .collect();
+        milestones.assign(&mut todos);
+        let assigned: Vec<_> = todos.iter().map(|todo| todo.milestone.as_deref()).collect();
+        assert_eq!(assigned, [Some("Q3 speedups"), Some("2.0 launch"), None]);
+
+        test.write("milestones", "v2 = 2.0\nv3\n");
+        let error = Milestones::load(&test.dir.join("milestones"))
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "line 2: expected `tag = milestone`");
+    }
+}
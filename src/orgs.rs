@@ -0,0 +1,64 @@
+//! Organizations for `--group-by org`: each TODO's author is bucketed by the
+//! domain of their email, which the `[orgs]` table of `.todo.toml` can name,
+//! such as `"google.com" = "Google"`, so open-source projects see how much
+//! each company and the community contribute.
+
+use crate::automation::AUTOMATION;
+use crate::Todo;
+
+/// The organization of `email` under `orgs`, mappings of a domain and its
+/// subdomains, where the most specific domain and then later mappings win.
+/// Unmapped domains are their own organization.
+fn org(orgs: &[(String, String)], email: &str) -> Option<String> {
+    let domain = email.rsplit_once('@')?.1.trim().to_lowercase();
+    if domain.is_empty() {
+        return None;
+    }
+    let mapped = orgs
+        .iter()
+        .filter(|(mapped, _)| {
+            let mapped = mapped.to_lowercase();
+            domain == mapped || domain.ends_with(&format!(".{mapped}"))
+        })
+        .max_by_key(|(mapped, _)| mapped.len());
+    Some(mapped.map_or(domain, |(_, org)| org.clone()))
+}
+
+/// Sets the organization of each of `todos` from its author's email.
+pub fn assign(orgs: &[(String, String)], todos: &mut [Todo]) {
+    for todo in todos {
+        todo.org = if todo.author == AUTOMATION {
+            Some(AUTOMATION.to_string())
+        } else {
+            org(orgs, &todo.author_email)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_domains_and_subdomains() {
+        let orgs = [
+            ("google.com".to_string(), "Google".to_string()),
+            (
+                "users.noreply.github.com".to_string(),
+                "Community".to_string(),
+            ),
+            ("deepmind.google.com".to_string(), "DeepMind".to_string()),
+        ];
+        let org = |email: &str| org(&orgs, email);
+        assert_eq!(org("ada@google.com").as_deref(), Some("Google"));
+        assert_eq!(org("ada@corp.Google.com").as_deref(), Some("Google"));
+        assert_eq!(org("ada@deepmind.google.com").as_deref(), Some("DeepMind"));
+        assert_eq!(
+            org("1+ada@users.noreply.github.com").as_deref(),
+            Some("Community")
+        );
+        assert_eq!(org("ada@example.org").as_deref(), Some("example.org"));
+        assert_eq!(org("notgoogle.com@x.io").as_deref(), Some("x.io"));
+        assert_eq!(org(""), None);
+    }
+}
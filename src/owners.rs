@@ -0,0 +1,70 @@
+//! Default owners for tags, such as `perf=@perf-team`, given with `--owner`
+//! or the `[owners]` table of `.todo.toml`. A TODO without an `@assignee` tag
+//! or `owner=` field belongs to the owner of the first of its tags with one.
+
+use crate::Todo;
+
+/// Splits a `TAG=OWNER` mapping.
+pub fn parse(flag: &str, spec: &str) -> Result<(String, String), String> {
+    let invalid = || format!("invalid owner '{spec}' for '{flag}', expected TAG=OWNER");
+    let (tag, owner) = spec.split_once('=').ok_or_else(invalid)?;
+    let (tag, owner) = (tag.trim(), owner.trim());
+    if tag.is_empty() || owner.is_empty() {
+        return Err(invalid());
+    }
+    Ok((tag.to_string(), owner.to_string()))
+}
+
+/// Sets the owner of each of `todos` that has no assignee from `owners`,
+/// where later mappings of a tag win.
+pub fn assign(owners: &[(String, String)], todos: &mut [Todo]) {
+    for todo in todos {
+        if todo.assignee().is_some() {
+            continue;
+        }
+        todo.owner = todo.tags.iter().find_map(|tag| {
+            owners
+                .iter()
+                .rev()
+                .find(|(owned, _)| owned == tag)
+                .map(|(_, owner)| owner.clone())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use std::path::PathBuf;
+
+    #[test]
+    fn assigns_the_first_owned_tag_unless_assigned() {
+        let owners = [
+            parse("--owner", "perf=@perf").unwrap(),
+            parse("--owner", "db = @storage").unwrap(),
+            parse("--owner", "perf=@perf-team").unwrap(),
+        ];
+        assert!(parse("--owner", "perf").is_err());
+        assert!(parse("--owner", "=@perf").is_err());
+
+        let todo = |tags: &[&str]| Todo {
+            tags: tags.iter().map(ToString::to_string).collect(),
+            ..Todo::new(
+                &Matcher::default(),
+                PathBuf::from("a.rs"),
+                1,
+                "// TODO: cache",
+            )
+        };
+        let mut todos = [
+            todo(&["ui", "perf", "db"]),
+            todo(&["perf", "@alice"]),
+            todo(&["ui"]),
+        ];
+        assign(&owners, &mut todos);
+        assert_eq!(todos[0].owner.as_deref(), Some("@perf-team"));
+        assert_eq!(todos[1].owner, None);
+        assert_eq!(todos[2].owner, None);
+    }
+}
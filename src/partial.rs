@@ -0,0 +1,206 @@
+//! Repositories that only have part of their content locally. Sparse
+//! checkouts leave paths outside their cone out of the working tree, and
+//! partial clones fetch blobs from a promisor remote when they are first
+//! needed, which git does on its own but libgit2 doesn't, so the blobs a scan
+//! reads are fetched here with `git fetch`. TODOs whose history can't be
+//! fetched are still reported, without a commit, as content unavailable.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use git2::{Diff, DiffOptions, ErrorCode, Oid, Repository, Sort};
+use regex::Regex;
+
+/// Shown instead of a commit for TODOs whose history is missing.
+pub const UNAVAILABLE: &str = "(content unavailable)";
+
+/// Paths a sparse checkout leaves out of the working tree: marked
+/// skip-worktree in the index and absent from disk.
+pub fn outside_checkout(repo: &Repository) -> HashSet<PathBuf> {
+    let (Ok(index), Some(root)) = (repo.index(), repo.workdir()) else {
+        return HashSet::new();
+    };
+    index
+        .iter()
+        .filter(|entry| {
+            git2::IndexEntryExtendedFlag::from_bits_truncate(entry.flags_extended)
+                .is_skip_worktree()
+        })
+        .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned()))
+        .filter(|path| !root.join(path).exists())
+        .collect()
+}
+
+/// The remote a partial clone fetches missing objects from, or `None` for a
+/// complete clone.
+fn promisor_remote(repo: &Repository) -> Option<String> {
+    let config = repo.config().ok()?;
+    if let Ok(remote) = config.get_string("extensions.partialclone") {
+        return Some(remote);
+    }
+    let remotes = repo.remotes().ok()?;
+    let remote = remotes
+        .iter()
+        .flatten()
+        .find(|name| config.get_bool(&format!("remote.{name}.promisor")) == Ok(true))
+        .map(str::to_string);
+    remote
+}
+
+/// Whether a lookup failed because an object is missing, as blobs a partial
+/// clone hasn't fetched are.
+pub fn is_missing(e: &git2::Error) -> bool {
+    e.code() == ErrorCode::NotFound
+        && Regex::new(r"\b[0-9a-f]{40}\b")
+            .unwrap()
+            .is_match(e.message())
+}
+
+/// Fetches those of `oids` that are missing from the promisor remote, in one
+/// request. Does nothing in a complete clone.
+pub fn fetch(repo: &Repository, oids: impl IntoIterator<Item = Oid>) -> Result<(), String> {
+    let Some(remote) = promisor_remote(repo) else {
+        return Ok(());
+    };
+    let odb = repo.odb().map_err(|e| e.to_string())?;
+    let missing: HashSet<_> = oids
+        .into_iter()
+        .filter(|oid| !oid.is_zero() && !odb.exists(*oid))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    // what git itself runs to fetch missing objects
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo.path())
+        .args(["-c", "fetch.negotiationAlgorithm=noop", "fetch", &remote])
+        .args([
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+        ])
+        .args(["--filter=blob:none", "--stdin", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("running git: {e}"))?;
+    let mut stdin = child.stdin.take().unwrap();
+    for oid in &missing {
+        writeln!(stdin, "{oid}").map_err(|e| e.to_string())?;
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "fetching {} objects from {remote}: {}",
+            missing.len(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Fetches the blobs on both sides of `diff`, so its patches can be read.
+pub fn fetch_diff(repo: &Repository, diff: &Diff) -> Result<(), String> {
+    fetch(
+        repo,
+        diff.deltas()
+            .flat_map(|delta| [delta.old_file().id(), delta.new_file().id()]),
+    )
+}
+
+/// Fetches every version of `paths` in `HEAD`'s history, which blaming them
+/// reads, and returns the paths whose history is still incomplete. libgit2
+/// blames lines on the wrong commit rather than fail when a version is
+/// missing, so those can't be blamed at all. A partial clone has every commit
+/// and tree, so finding the versions fetches nothing.
+pub fn fetch_history(repo: &Repository, paths: &[&Path]) -> HashSet<PathBuf> {
+    if paths.is_empty() || promisor_remote(repo).is_none() {
+        return HashSet::new();
+    }
+    let versions = match history(repo, paths) {
+        Ok(versions) => versions,
+        Err(e) => {
+            eprintln!("Error listing the history of the files to scan: {e}");
+            return paths.iter().map(|path| path.to_path_buf()).collect();
+        }
+    };
+    if let Err(e) = fetch(repo, versions.iter().map(|(oid, _)| *oid)) {
+        eprintln!("Error fetching the history of the files to scan: {e}");
+    }
+
+    let Ok(odb) = repo.odb() else {
+        return paths.iter().map(|path| path.to_path_buf()).collect();
+    };
+    versions
+        .into_iter()
+        .filter(|(oid, _)| !odb.exists(*oid))
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Each blob `paths` had in `HEAD`'s history, with its path, found by diffing
+/// each commit's tree with its parents'.
+fn history(repo: &Repository, paths: &[&Path]) -> Result<Vec<(Oid, PathBuf)>, git2::Error> {
+    let mut options = DiffOptions::new();
+    for path in paths {
+        options.pathspec(path);
+    }
+    options.disable_pathspec_match(true);
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::NONE)?;
+    walk.push_head()?;
+
+    let mut versions = Vec::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        let parents: Vec<_> = commit
+            .parents()
+            .map(|parent| parent.tree())
+            .collect::<Result<_, _>>()?;
+        let parents = if parents.is_empty() {
+            vec![None]
+        } else {
+            parents.into_iter().map(Some).collect()
+        };
+        for parent in parents {
+            let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&tree), Some(&mut options))?;
+            for delta in diff.deltas() {
+                let file = delta.new_file();
+                if let (false, Some(path)) = (file.id().is_zero(), file.path()) {
+                    versions.push((file.id(), path.to_path_buf()));
+                }
+            }
+        }
+    }
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_missing_objects() {
+        let missing = git2::Error::new(
+            ErrorCode::NotFound,
+            git2::ErrorClass::Odb,
+            "object not found - no match for id (0123456789abcdef0123456789abcdef01234567)",
+        );
+        assert!(is_missing(&missing));
+        let other = git2::Error::new(
+            ErrorCode::NotFound,
+            git2::ErrorClass::Reference,
+            "reference 'refs/heads/main' not found",
+        );
+        assert!(!is_missing(&other));
+    }
+}
@@ -0,0 +1,75 @@
+//! Whether the commit each TODO is blamed on is already on the base branch or
+//! only on this one, which tells the TODOs a branch brings from those it
+//! inherited.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Options;
+use crate::{base, get_commits_since_main, Todo};
+
+/// Where a commit is relative to the base branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Position {
+    /// Reachable from the base, named.
+    OnBase(String),
+    /// Only on the current branch.
+    Branch,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OnBase(name) => write!(f, "on {name}"),
+            Self::Branch => f.write_str("this branch"),
+        }
+    }
+}
+
+impl Position {
+    fn of(commit: Oid, branch: &HashSet<Oid>, base: &str) -> Self {
+        // blamed commits are reachable from HEAD, so those not on the branch
+        // are the base's
+        if branch.contains(&commit) {
+            Self::Branch
+        } else {
+            Self::OnBase(base.to_string())
+        }
+    }
+}
+
+pub fn annotate(
+    repo: &Repository,
+    options: &Options,
+    todos: &mut [Todo],
+) -> Result<(), git2::Error> {
+    let branch = get_commits_since_main(repo, options)?;
+    let base = base::name(repo, options);
+    for todo in todos {
+        // uncommitted lines are on no branch yet
+        if let Ok(commit) = Oid::from_str(&todo.commit_hash) {
+            todo.position = Some(Position::of(commit, &branch, &base));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_branch_commits_from_the_base() {
+        let ours = Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let theirs = Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+        let branch = HashSet::from([ours]);
+        assert_eq!(
+            Position::of(ours, &branch, "main").to_string(),
+            "this branch"
+        );
+        assert_eq!(Position::of(theirs, &branch, "main").to_string(), "on main");
+    }
+}
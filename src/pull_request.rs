@@ -0,0 +1,197 @@
+//! Resolving the pull request that introduced a commit, which is usually where
+//! the discussion behind a TODO lives.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use git2::{Commit, Oid, Repository};
+
+use crate::base;
+use crate::cli::Options;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: Option<String>,
+}
+
+impl fmt::Display for PullRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.url {
+            Some(url) => write!(f, "{url}"),
+            None => write!(f, "#{}", self.number),
+        }
+    }
+}
+
+/// Extracts the pull request number from the summaries hosting services
+/// generate: `Title (#123)` for squash merges and
+/// `Merge pull request #123 from ...` for merge commits.
+fn number_from_summary(summary: &str) -> Option<u64> {
+    let re = Regex::new(r"^Merge pull request #(\d+)|\(#(\d+)\)\s*$").unwrap();
+    let caps = re.captures(summary)?;
+    caps.get(1).or_else(|| caps.get(2))?.as_str().parse().ok()
+}
+
+/// Web URL of the `origin` remote's repository, e.g.
+/// `https://github.com/owner/repo`, for clone URLs over HTTPS or SSH.
+pub fn web_url(repo: &Repository) -> Option<String> {
+    let remote = repo.find_remote("origin").ok()?;
+    let url = remote.url()?;
+
+    let re =
+        Regex::new(r"^(?:https?://|ssh://)?(?:[^@/]+@)?([^:/]+)(?::\d+)?[:/](.+?)(?:\.git)?/?$")
+            .unwrap();
+    let caps = re.captures(url)?;
+    Some(format!("https://{}/{}", &caps[1], &caps[2]))
+}
+
+/// Base URL for pull requests of the `origin` remote, if it is a known host.
+fn pull_request_base_url(repo: &Repository) -> Option<String> {
+    let base = web_url(repo)?;
+
+    let route = if base.contains("gitlab") {
+        "-/merge_requests"
+    } else if base.contains("bitbucket") {
+        "pull-requests"
+    } else {
+        "pull"
+    };
+
+    Some(format!("{base}/{route}"))
+}
+
+pub struct PullRequests<'repo> {
+    repo: &'repo Repository,
+    options: &'repo Options,
+    base_url: Option<String>,
+    cache: HashMap<Oid, Option<u64>>,
+    /// The base branch's first-parent history from where the current branch
+    /// forked, oldest first, once needed.
+    mainline: Option<Vec<Oid>>,
+}
+
+impl<'repo> PullRequests<'repo> {
+    pub fn new(repo: &'repo Repository, options: &'repo Options) -> Self {
+        Self {
+            repo,
+            options,
+            base_url: pull_request_base_url(repo),
+            cache: HashMap::new(),
+            mainline: None,
+        }
+    }
+
+    /// The pull request that brought `commit` into the base branch: either
+    /// named in the commit's own summary (squash merges) or in the summary of
+    /// the earliest merge on the base that contains it.
+    pub fn find(&mut self, commit: &Commit<'_>) -> Option<PullRequest> {
+        let (repo, options) = (self.repo, self.options);
+        let cached = &mut self.mainline;
+        let number = *self.cache.entry(commit.id()).or_insert_with(|| {
+            number_from_summary(commit.summary().unwrap_or("")).or_else(|| {
+                let mainline = cached.get_or_insert_with(|| mainline(repo, options));
+                find_merge(repo, mainline, commit.id()).ok().flatten()
+            })
+        });
+
+        number.map(|number| PullRequest {
+            number,
+            url: self
+                .base_url
+                .as_ref()
+                .map(|base| format!("{base}/{number}")),
+        })
+    }
+}
+
+/// The first-parent history of the base branch from where the current
+/// branch forked from it, oldest first, or none if the base is missing.
+fn mainline(repo: &Repository, options: &Options) -> Vec<Oid> {
+    let mut mainline = Vec::new();
+    let mut next = base::merge_base(repo, options).ok();
+    while let Some(commit) = next {
+        mainline.push(commit.id());
+        next = commit.parent(0).ok();
+    }
+    mainline.reverse();
+    mainline
+}
+
+/// The number of the merge on `mainline` that brought `commit` into it, if
+/// it came in through a merge at all.
+fn find_merge(
+    repo: &Repository,
+    mainline: &[Oid],
+    commit: Oid,
+) -> Result<Option<u64>, git2::Error> {
+    let contains = |tip: Oid| -> Result<bool, git2::Error> {
+        Ok(tip == commit || repo.graph_descendant_of(tip, commit)?)
+    };
+
+    // each mainline commit contains those before it, so the first to contain
+    // the commit is found by bisecting
+    let (mut low, mut high) = (0, mainline.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if contains(mainline[mid])? {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    // the merge that introduced the commit is the one whose first parent
+    // does not have it yet
+    let Some(&first) = mainline.get(low).filter(|first| **first != commit) else {
+        return Ok(None);
+    };
+    let merge = repo.find_commit(first)?;
+    if merge.parent_count() < 2 {
+        return Ok(None);
+    }
+    Ok(number_from_summary(merge.summary().unwrap_or("")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn finds_the_merge_on_the_base() {
+        let test = TestRepo::new("pull-request");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("b.rs", "// TODO: b\n");
+        let feature = test.commit("Bob", "b");
+        test.checkout("main");
+        test.write("c.rs", "fn c() {}\n");
+        test.commit("Alice", "c");
+        test.merge("feature", "Alice", "Merge pull request #7 from bob/feature");
+
+        // merged into this branch, not the base
+        test.branch("topic");
+        test.branch("side");
+        test.write("d.rs", "// TODO: d\n");
+        let side = test.commit("Carol", "d");
+        test.checkout("topic");
+        test.merge("side", "Carol", "Merge pull request #8 from carol/side");
+        let squashed = test.commit("Carol", "Squashed (#9)");
+
+        let options = Options::default();
+        let mut pull_requests = PullRequests::new(&test.repo, &options);
+        let mut number = |id: Oid| {
+            let commit = test.repo.find_commit(id).unwrap();
+            pull_requests
+                .find(&commit)
+                .map(|pull_request| pull_request.number)
+        };
+        assert_eq!(number(feature), Some(7));
+        assert_eq!(number(side), None);
+        assert_eq!(number(squashed), Some(9));
+    }
+}
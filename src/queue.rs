@@ -0,0 +1,176 @@
+//! Requests to external services that failed in a way worth retrying are
+//! kept on disk, so a flaky CI network doesn't silently drop reports.
+//! `todo flush` sends them again, oldest first.
+//!
+//! Each repository's queue lives in its `.git/todo/queue`, one JSON file per
+//! request, so requests a workspace run makes are flushed from their own
+//! repository. Stored tokens aren't written to the queue: requests only name
+//! the provider whose token `todo flush` looks up again. Other headers are
+//! kept, so files are only readable by their owner.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use git2::Repository;
+
+use crate::http::Request;
+
+fn dir(repo: &Repository) -> PathBuf {
+    repo.path().join("todo").join("queue")
+}
+
+/// Statuses that may succeed if sent again later, unlike rejected requests.
+const fn is_transient(status: u16) -> bool {
+    matches!(status, 408 | 429) || status >= 500
+}
+
+/// Writes the requests to `repo`'s queue, keeping their order.
+fn push(repo: &Repository, requests: &[Request]) -> io::Result<()> {
+    let dir = dir(repo);
+    fs::create_dir_all(&dir)?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    for (index, request) in requests.iter().enumerate() {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(dir.join(format!("{nanos:020}-{index:04}.json")))?;
+        serde_json::to_writer(&mut file, request)?;
+        file.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Sends the requests in order. If one fails transiently, it and the ones
+/// after it, which may depend on it, are queued in `repo` for `todo flush`.
+pub fn send_all(repo: &Repository, requests: &[Request]) -> io::Result<()> {
+    for (index, request) in requests.iter().enumerate() {
+        let failure = match request.send() {
+            Ok(response) if response.is_success() => continue,
+            Ok(response) if !is_transient(response.status) => {
+                return Err(request.status_error(&response));
+            }
+            Ok(response) => request.status_error(&response),
+            Err(e) => e,
+        };
+
+        let queued = &requests[index..];
+        push(repo, queued)
+            .map_err(|e| io::Error::other(format!("{failure}; queueing failed: {e}")))?;
+        return Err(io::Error::other(format!(
+            "{failure}; queued {} request{} for `todo flush`",
+            queued.len(),
+            if queued.len() == 1 { "" } else { "s" }
+        )));
+    }
+    Ok(())
+}
+
+/// Like [`send_all`] for a single request.
+pub fn send(repo: &Repository, request: Request) -> io::Result<()> {
+    send_all(repo, &[request])
+}
+
+/// `todo flush`: sends `repo`'s queued requests, oldest first, stopping at
+/// the first that fails so later ones stay in order behind it.
+pub fn flush(repo: &Repository) -> io::Result<()> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir(repo)) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<_>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    if paths.is_empty() {
+        println!("No queued requests");
+        return Ok(());
+    }
+
+    for (sent, path) in paths.iter().enumerate() {
+        let request: Request = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if let Err(e) = request.send_ok() {
+            return Err(io::Error::other(format!(
+                "{e}; sent {sent} of {} queued requests",
+                paths.len()
+            )));
+        }
+        fs::remove_file(path)?;
+    }
+
+    println!(
+        "Sent {} queued request{}",
+        paths.len(),
+        if paths.len() == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::credentials::Provider;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn queues_in_each_repository() {
+        let first = TestRepo::new("queue");
+        let second = TestRepo::new("queue");
+        // nothing listens on port 1, so the request fails to connect
+        let request = || Request::new("POST", "http://127.0.0.1:1/report");
+        assert!(send(&first.repo, request()).is_err());
+        assert!(send_all(&second.repo, &[request(), request()]).is_err());
+
+        let queued = |test: &TestRepo| fs::read_dir(dir(&test.repo)).unwrap().count();
+        assert_eq!(queued(&first), 1);
+        assert_eq!(queued(&second), 2);
+    }
+
+    #[test]
+    fn looks_tokens_up_again_when_flushing() {
+        let test = TestRepo::new("queue-token");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/write", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let (mut line, mut head) = (String::new(), Vec::new());
+            while reader.read_line(&mut line).unwrap() > 2 {
+                head.push(line.trim_end().to_string());
+                line.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            head
+        });
+
+        std::env::set_var("INFLUX_TOKEN", "queued-secret");
+        let request = Request::new("POST", url).authorization("Token", Provider::Influx);
+        push(&test.repo, &[request]).unwrap();
+        let queued = fs::read_dir(dir(&test.repo)).unwrap().next().unwrap();
+        let queued = fs::read_to_string(queued.unwrap().path()).unwrap();
+        assert!(queued.contains("influx"));
+        assert!(!queued.contains("queued-secret"));
+
+        flush(&test.repo).unwrap();
+        let head = server.join().unwrap();
+        assert!(head.contains(&"Authorization: Token queued-secret".to_string()));
+        assert_eq!(fs::read_dir(dir(&test.repo)).unwrap().count(), 0);
+    }
+}
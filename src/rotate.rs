@@ -0,0 +1,192 @@
+//! `todo rotate`: a weekly "debt duty" for each team member, a few TODOs to
+//! chip away at.
+//!
+//! Assignments are derived from each TODO's fingerprint and the week, never
+//! from the order of the scan, so every run in the same week agrees and a new
+//! or resolved TODO only changes its own assignment. TODOs assigned with
+//! `TODO(@handle)` stay with that member.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use git2::Repository;
+use serde_json::json;
+
+use crate::http::Request;
+use crate::team::Team;
+use crate::{dates, queue, redact, Todo};
+
+/// An ISO 8601 week, written like `2024-W07`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Week {
+    year: i32,
+    week: u32,
+}
+
+impl Week {
+    pub fn current() -> Self {
+        let week = dates::now().iso_week();
+        Self {
+            year: week.year(),
+            week: week.week(),
+        }
+    }
+}
+
+impl fmt::Display for Week {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-W{:02}", self.year, self.week)
+    }
+}
+
+impl FromStr for Week {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid week '{s}', expected e.g. 2024-W07");
+        let (year, week) = s.split_once("-W").ok_or_else(invalid)?;
+        let year = year.parse().map_err(|_| invalid())?;
+        let week = week.parse().map_err(|_| invalid())?;
+        // rejects week 53 in years that only have 52
+        NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).ok_or_else(invalid)?;
+        Ok(Self { year, week })
+    }
+}
+
+/// FNV-1a over the parts, which unlike `std`'s hashers is the same in every
+/// build, so assignments don't move when the binary is upgraded. It is
+/// finished with `SplitMix64`'s mixer, since scores are compared by their high
+/// bits, which FNV leaves unmixed.
+fn hash(parts: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain([0xff]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// The TODOs each member of the team is on duty for in `week`, by member in
+/// team file order. Paths in fingerprints are taken relative to `root`.
+pub fn assign<'a>(
+    team: &'a Team,
+    todos: &'a [Todo],
+    root: &Path,
+    week: Week,
+) -> Vec<(&'a str, Vec<&'a Todo>)> {
+    let week = week.to_string();
+    let mut duties: BTreeMap<&str, Vec<(u64, &Todo)>> = BTreeMap::new();
+
+    for todo in todos {
+        let path = todo.file_path.strip_prefix(root).unwrap_or(&todo.file_path);
+        let path = path.to_string_lossy().replace('\\', "/");
+        let fingerprint = format!("{path}:{}", todo.normalized);
+        // rendezvous hashing: the member with the highest score gets the TODO,
+        // so joining or leaving only moves that member's share
+        let member = team.assignee(todo).or_else(|| {
+            team.names()
+                .iter()
+                .max_by_key(|name| hash(&[&week, name, &fingerprint]))
+                .map(String::as_str)
+        });
+        if let Some(member) = member {
+            let rank = hash(&[&week, &fingerprint]);
+            duties.entry(member).or_default().push((rank, todo));
+        }
+    }
+
+    team.names()
+        .iter()
+        .map(|name| {
+            let mut todos = duties.remove(name.as_str()).unwrap_or_default();
+            todos.sort_by_key(|(rank, _)| *rank);
+            todos.truncate(team.rotation.per_member);
+            (
+                name.as_str(),
+                todos.into_iter().map(|(_, todo)| todo).collect(),
+            )
+        })
+        .collect()
+}
+
+/// The assignments as plain text, for the terminal and chat alike.
+pub fn report(duties: &[(&str, Vec<&Todo>)], root: &Path, week: Week) -> String {
+    let mut out = format!("Debt duty for {week}\n");
+    for (member, todos) in duties {
+        let _ = writeln!(out, "\n{member}");
+        if todos.is_empty() {
+            let _ = writeln!(out, "  nothing this week");
+        }
+        for todo in todos {
+            let path = todo.file_path.strip_prefix(root).unwrap_or(&todo.file_path);
+            let _ = writeln!(
+                out,
+                "  {}:{}  {}",
+                path.to_string_lossy().replace('\\', "/"),
+                todo.line,
+                redact::shared(todo.raw.trim())
+            );
+        }
+    }
+    out
+}
+
+/// Posts the report to the team's webhook, queueing it in `repo` if that
+/// fails.
+pub fn send(repo: &Repository, team: &Team, report: &str) -> io::Result<()> {
+    let Some(webhook) = &team.rotation.webhook else {
+        return Err(io::Error::other(
+            "'--send' requires a webhook under [rotation] in the team file",
+        ));
+    };
+    let request = Request::new("POST", webhook.as_str()).json(&json!({ "text": report }));
+    queue::send(repo, request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use std::path::PathBuf;
+
+    #[test]
+    fn assignments_are_stable_as_todos_come_and_go() {
+        let file = std::env::temp_dir().join(format!("todo-rotate-{}.toml", std::process::id()));
+        std::fs::write(
+            &file,
+            "members = ['ann', 'bob', 'cy']\n[rotation]\nper_member = 100\n",
+        )
+        .unwrap();
+        let team = Team::load(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        let todo = |n: usize| {
+            let mut todo = Todo::new(&Matcher::default(), PathBuf::from("/repo/a.rs"), n, "");
+            todo.normalized = format!("task {n}");
+            todo
+        };
+        let week: Week = "2024-W07".parse().unwrap();
+        let owners = |todos: &[Todo]| -> BTreeMap<usize, String> {
+            assign(&team, todos, Path::new("/repo"), week)
+                .into_iter()
+                .flat_map(|(member, todos)| todos.into_iter().map(move |t| (t.line, member.into())))
+                .collect()
+        };
+
+        let before = owners(&(0..30).map(todo).collect::<Vec<_>>());
+        let mut after = owners(&(1..40).map(todo).collect::<Vec<_>>());
+        after.retain(|line, _| before.contains_key(line));
+        let mut before = before;
+        before.remove(&0);
+        assert_eq!(before, after);
+        assert!(before.values().any(|member| member == "cy"));
+    }
+}
@@ -0,0 +1,175 @@
+//! Output in SARIF 2.1.0, which GitHub code scanning accepts through the
+//! `github/codeql-action/upload-sarif` action and shows as alerts and pull
+//! request annotations.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::github::strip_ansi;
+use crate::matcher::Matcher;
+use crate::metadata::Metadata;
+use crate::severity::Severity;
+use crate::{redact, repo_relative_path, Todo};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// SARIF's levels, which code scanning shows as error, warning and note.
+const fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Low => "note",
+        Severity::Medium => "warning",
+        Severity::High => "error",
+    }
+}
+
+/// Each keyword gets its own rule, named like Sonar's `todo-comment`.
+fn rule_id(keyword: &str) -> String {
+    format!("{}-comment", keyword.to_lowercase())
+}
+
+pub fn log(
+    repo: &Repository,
+    matcher: &Matcher,
+    todos: &[Todo],
+    metadata: &Metadata,
+) -> serde_json::Value {
+    // rules are referred to by index, so they are numbered in a stable order
+    let keywords: BTreeMap<String, usize> = todos
+        .iter()
+        .map(|todo| matcher.keyword_in(&todo.raw))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .enumerate()
+        .map(|(index, keyword)| (keyword, index))
+        .collect();
+
+    let rules: Vec<_> = keywords
+        .keys()
+        .map(|keyword| {
+            json!({
+                "id": rule_id(keyword),
+                "name": format!("{keyword} comment"),
+                "shortDescription": { "text": format!("{keyword} comment") },
+                "fullDescription": {
+                    "text": "A comment marking unfinished work that should be tracked and resolved.",
+                },
+                "defaultConfiguration": { "level": level(matcher.severity(keyword)) },
+                "properties": { "tags": ["maintainability"] },
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = todos
+        .iter()
+        .map(|todo| {
+            let keyword = matcher.keyword_in(&todo.raw);
+            json!({
+                "ruleId": rule_id(&keyword),
+                "ruleIndex": keywords[&keyword],
+                "level": level(todo.severity),
+                "message": { "text": redact::shared(&strip_ansi(todo.statement.trim())) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {
+                            "uri": repo_relative_path(repo, &todo.file_path),
+                            "uriBaseId": "%SRCROOT%",
+                        },
+                        "region": { "startLine": todo.line },
+                    },
+                }],
+                // lets code scanning follow an alert as lines move
+                "partialFingerprints": { "todoId/v1": todo.id },
+                "properties": {
+                    "tags": todo.tags,
+                    "author": todo.author,
+                    "owner": todo.owner,
+                    "assignee": todo.assignee(),
+                    "issue": todo.fields.issue,
+                    "priority": todo.fields.priority,
+                    "test": todo.test,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+            "properties": { "metadata": metadata.json() },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn numbers_a_rule_per_keyword() {
+        let test = TestRepo::new("sarif");
+        test.write(
+            "src/a.rs",
+            "// TODO(perf): one\n// FIXME: two\n// TODO: three\n",
+        );
+        test.commit("Alice", "a");
+        let mut options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        options.add_keyword("FIXME").unwrap();
+        let (mut todos, _) = crate::get_todos(&test.repo, &options);
+        todos.sort_by_key(|todo| todo.line);
+        let matcher = Matcher::configured(&options);
+
+        let log = log(
+            &test.repo,
+            &matcher,
+            &todos,
+            &Metadata::new(&test.repo, &options),
+        );
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        let rules: Vec<_> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rule| rule["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, ["fixme-comment", "todo-comment"]);
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["defaultConfiguration"]["level"],
+            "error"
+        );
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            (
+                &results[1]["ruleId"],
+                &results[1]["ruleIndex"],
+                &results[1]["level"]
+            ),
+            (&json!("fixme-comment"), &json!(0), &json!("error"))
+        );
+        assert_eq!(results[0]["level"], "note");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/a.rs");
+        assert_eq!(location["region"]["startLine"], 1);
+        assert_eq!(results[0]["partialFingerprints"]["todoId/v1"], todos[0].id);
+        assert_eq!(results[0]["properties"]["tags"], json!(["perf"]));
+    }
+}
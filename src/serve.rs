@@ -0,0 +1,205 @@
+//! `todo serve`: serves the scan history over HTTP, rescanning the repository
+//! and recording the result whenever the last scan is older than the interval,
+//! and the latest scan's TODOs through the JSON API of [`api`]. Push
+//! webhooks to `/webhook` fetch and rescan right away, and [`annotations`]
+//! keeps what viewers say about TODOs across rescans.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use git2::Repository;
+
+use crate::annotations::{self, Store};
+use crate::api::{self, Scan};
+use crate::cli::Options;
+use crate::history::History;
+use crate::server::{self, Request, Response};
+use crate::stats::Stats;
+use crate::webhook::{self, Event};
+use crate::{dry_run, grafana, scan};
+
+/// Whether a page on another origin than `allow_origin` tries to record or
+/// fetch something. Browsers send the page's origin, and any page can reach
+/// a server on localhost.
+fn is_cross_origin_change(request: &Request, allow_origin: Option<&str>) -> bool {
+    let changes_state = request.method != "GET"
+        && matches!(
+            request.path.as_str(),
+            "/api/refresh" | "/api/annotations" | "/webhook"
+        );
+    changes_state
+        && request
+            .header("Origin")
+            .is_some_and(|origin| Some(origin) != allow_origin)
+}
+
+/// Serves on `addr`. Addresses other than loopback ones are reachable from
+/// other machines, for webhooks and the team's annotations, so they need a
+/// webhook secret. Web pages can only use the API from `allow_origin`.
+pub fn run(
+    repo: &Repository,
+    options: &Options,
+    addr: SocketAddr,
+    interval: Duration,
+    allow_origin: Option<&str>,
+) -> io::Result<()> {
+    let secret = std::env::var(webhook::SECRET_VAR)
+        .ok()
+        .filter(|secret| !secret.is_empty());
+    if !addr.ip().is_loopback() && secret.is_none() {
+        return Err(io::Error::other(format!(
+            "refusing to serve on {} without {} set",
+            addr.ip(),
+            webhook::SECRET_VAR
+        )));
+    }
+
+    let mut history = History::load(repo)?;
+    let annotations = Store::open(repo).map_err(io::Error::other)?;
+    let mut latest: Option<(Instant, Scan)> = None;
+
+    eprintln!("Serving TODO history on http://{addr}");
+
+    server::serve(addr, allow_origin, |request| {
+        if is_cross_origin_change(request, allow_origin) {
+            return Response::error(403, "cross-origin requests may not change anything");
+        }
+        let mut refresh =
+            (request.method.as_str(), request.path.as_str()) == ("POST", "/api/refresh");
+        if (request.method.as_str(), request.path.as_str()) == ("POST", "/webhook") {
+            match webhook::event(request, secret.as_deref()) {
+                Ok(Event::Push(pushed)) => {
+                    eprintln!("Push to {pushed}, fetching and rescanning");
+                    if let Err(e) = webhook::update(repo, &pushed) {
+                        eprintln!("Error updating the repository: {e}");
+                    }
+                    refresh = true;
+                }
+                Ok(Event::Other(name)) => {
+                    return Response::json(&serde_json::json!({ "ignored": name }));
+                }
+                Err(response) => return response,
+            }
+        }
+        if refresh
+            || latest
+                .as_ref()
+                .is_none_or(|(at, _)| at.elapsed() >= interval)
+        {
+            let todos = scan(repo, options);
+            if dry_run::enabled() {
+                eprintln!("[dry run] would record a scan of {} TODOs", todos.len());
+            } else if let Err(e) = history.record(repo, Stats::compute(&todos)) {
+                eprintln!("Error recording scan: {e}");
+            }
+            let scan = Scan {
+                todos,
+                scanned_at: Utc::now(),
+            };
+            latest = Some((Instant::now(), scan));
+        }
+
+        let (_, scan) = latest.as_ref().unwrap();
+        route(repo, request, &history, scan, &annotations)
+    })
+}
+
+fn route(
+    repo: &Repository,
+    request: &Request,
+    history: &History,
+    scan: &Scan,
+    annotations: &Store,
+) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        // the datasource plugin checks this when the datasource is saved
+        ("GET", "/") => Response::text(200, "ok"),
+        // the preflight of a browser's POST or DELETE, allowed from `--allow-origin`
+        ("OPTIONS", _) => Response::text(200, ""),
+        ("POST", "/search") => Response::json(&grafana::search(history)),
+        ("POST", "/metrics") => Response::json(&grafana::metrics(history)),
+        ("POST", "/query") => Response::json(&grafana::query(history, &request.json())),
+        ("POST", "/annotations") => Response::json(&grafana::annotations(history, &request.json())),
+        ("GET", "/api/todos") => match annotations.all() {
+            Ok(all) => api::todos(repo, scan, &all, request),
+            Err(e) => Response::error(500, format!("annotations: {e}")),
+        },
+        ("GET", "/api/stats") => api::stats(scan),
+        ("POST", "/api/refresh" | "/webhook") => api::refreshed(scan),
+        ("GET", "/api/annotations") => annotations::list(annotations, request),
+        ("POST" | "DELETE", "/api/annotations") if dry_run::enabled() => {
+            Response::error(403, "annotations are read-only with --dry-run")
+        }
+        ("POST", "/api/annotations") => annotations::annotate(annotations, scan, request),
+        ("DELETE", "/api/annotations") => annotations::delete(annotations, request),
+        ("GET", "/api/openapi.json") => Response {
+            content_type: "application/json",
+            ..Response::text(200, api::OPENAPI)
+        },
+        _ => Response::not_found(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn refuses_other_addresses_without_a_secret() {
+        let test = TestRepo::new("serve");
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        let error = run(&test.repo, &Options::default(), addr, Duration::ZERO, None).unwrap_err();
+        assert!(error.to_string().contains(webhook::SECRET_VAR));
+    }
+
+    #[test]
+    fn only_the_allowed_origin_changes_state() {
+        let request = |method: &str, path: &str, origin: Option<&str>| Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: Vec::new(),
+            headers: origin
+                .map(|origin| ("Origin".to_string(), origin.to_string()))
+                .into_iter()
+                .collect(),
+            body: Vec::new(),
+        };
+        let evil = Some("https://evil.example");
+        let dashboard = Some("https://dash.example");
+
+        assert!(is_cross_origin_change(
+            &request("POST", "/api/annotations", evil),
+            None
+        ));
+        assert!(is_cross_origin_change(
+            &request("DELETE", "/api/annotations", evil),
+            dashboard
+        ));
+        assert!(is_cross_origin_change(
+            &request("POST", "/api/refresh", evil),
+            dashboard
+        ));
+        assert!(!is_cross_origin_change(
+            &request("POST", "/api/refresh", dashboard),
+            dashboard
+        ));
+        // reads, and clients that aren't browsers
+        assert!(!is_cross_origin_change(
+            &request("GET", "/api/annotations", evil),
+            None
+        ));
+        assert!(!is_cross_origin_change(
+            &request("POST", "/query", evil),
+            None
+        ));
+        assert!(!is_cross_origin_change(
+            &request("POST", "/webhook", None),
+            None
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! A deliberately small HTTP/1.1 server for `todo serve`: one connection at a
+//! time, no keep-alive, which is plenty for dashboards polling a few endpoints.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+/// Requests with larger bodies are rejected.
+const MAX_BODY: usize = 10 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Query string parameters, decoded, in order.
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first query string parameter called `name`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+/// Decodes a query string component: `+` is a space and `%XX` a byte.
+fn decode(component: &str) -> String {
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut rest = component.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => match rest
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(decoded) => {
+                    bytes.push(decoded);
+                    rest = &rest[2..];
+                }
+                None => bytes.push(b'%'),
+            },
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The parameters of a query string such as `tag=perf&limit=10`.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    pub fn text(status: u16, text: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: text.into().into_bytes(),
+        }
+    }
+
+    /// A JSON `{"error": message}` body, for the API.
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            ..Self::json(&serde_json::json!({ "error": message.into() }))
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "not found")
+    }
+
+    const fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            500 => "Internal Server Error",
+            _ => "",
+        }
+    }
+
+    /// Writes the response, letting pages on `allowed_origin`, if any, read it.
+    fn write_to(&self, stream: &mut impl Write, allowed_origin: Option<&str>) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
+            self.status,
+            self.reason(),
+            self.content_type,
+            self.body.len()
+        )?;
+        if let Some(origin) = allowed_origin {
+            write!(
+                stream,
+                "Access-Control-Allow-Origin: {origin}\r\nVary: Origin\r\n\
+                 Access-Control-Allow-Methods: GET, POST, DELETE\r\n\
+                 Access-Control-Allow-Headers: Content-Type\r\n"
+            )?;
+        }
+        write!(stream, "Connection: close\r\n\r\n")?;
+        stream.write_all(&self.body)
+    }
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "malformed request line",
+        ));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: parse_query(query),
+        headers,
+        body: Vec::new(),
+    };
+
+    let length: usize = request
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request body too large",
+        ));
+    }
+    request.body.resize(length, 0);
+    reader.read_exact(&mut request.body)?;
+
+    Ok(request)
+}
+
+/// Accepts connections on `addr` forever, answering each with `handler`.
+/// Only pages on `allow_origin` may read the responses in a browser.
+pub fn serve(
+    addr: SocketAddr,
+    allow_origin: Option<&str>,
+    mut handler: impl FnMut(&Request) -> Response,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error accepting connection: {e}");
+                continue;
+            }
+        };
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+        let (response, origin) = match read_request(&stream) {
+            Ok(request) => {
+                let origin =
+                    allow_origin.filter(|&allowed| request.header("Origin") == Some(allowed));
+                (handler(&request), origin)
+            }
+            Err(e) => (Response::text(400, e.to_string()), None),
+        };
+        if let Err(e) = response.write_to(&mut stream, origin) {
+            eprintln!("Error writing response: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_query_strings() {
+        assert_eq!(
+            parse_query("tag=perf&q=free+the%20buffer&&path=src%2Fa.rs&test"),
+            [
+                ("tag", "perf"),
+                ("q", "free the buffer"),
+                ("path", "src/a.rs"),
+                ("test", ""),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+        assert_eq!(decode("100%"), "100%");
+    }
+
+    #[test]
+    fn allows_only_the_configured_origin() {
+        let write = |origin| {
+            let mut out = Vec::new();
+            Response::text(200, "ok")
+                .write_to(&mut out, origin)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(!write(None).contains("Access-Control"));
+        let allowed = write(Some("https://dash.example"));
+        assert!(allowed.contains("Access-Control-Allow-Origin: https://dash.example\r\n"));
+        assert!(allowed.ends_with("\r\n\r\nok"));
+    }
+}
@@ -0,0 +1,218 @@
+//! How urgent a TODO is. TODOs start out at their keyword's severity, are
+//! raised by the `[severity]` path rules of `.todo.toml` and are escalated
+//! when they outlive `--max-age`, so old debt stands out instead of hiding in
+//! the tree.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use git2::Repository;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{dates, repo_relative_path, Todo};
+
+/// The tag escalated TODOs get.
+pub const STALE_TAG: &str = "stale";
+
+/// Keywords that are more urgent than a TODO unless configured otherwise.
+const KEYWORD_DEFAULTS: [(&str, Severity); 4] = [
+    ("FIXME", Severity::High),
+    ("BUG", Severity::High),
+    ("HACK", Severity::Medium),
+    ("XXX", Severity::Medium),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    const ALL: [Self; 3] = [Self::Low, Self::Medium, Self::High];
+
+    /// The severity of a keyword configured without one.
+    #[must_use]
+    pub fn of_keyword(keyword: &str) -> Self {
+        KEYWORD_DEFAULTS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(keyword))
+            .map_or(Self::Low, |(_, severity)| *severity)
+    }
+
+    /// The next level up, staying at the top once there.
+    #[must_use]
+    pub const fn bumped(self) -> Self {
+        match self {
+            Self::Low => Self::Medium,
+            Self::Medium | Self::High => Self::High,
+        }
+    }
+
+    /// The upper-case name code-quality APIs such as Sonar's and Bitbucket's
+    /// use.
+    #[must_use]
+    pub const fn api_name(self) -> &'static str {
+        match self {
+            Self::Low => "LOW",
+            Self::Medium => "MEDIUM",
+            Self::High => "HIGH",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|severity| severity.api_name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown severity '{s}', expected low, medium or high"))
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.api_name().to_lowercase())
+    }
+}
+
+/// The `[severity]` rules: the least severity of TODOs in paths matching a
+/// pattern, in gitignore syntax, such as `"src/security/**" = "high"`.
+pub struct PathRules {
+    rules: Vec<(Gitignore, Severity)>,
+}
+
+impl PathRules {
+    pub fn new(root: &Path, rules: &[(String, Severity)]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|(pattern, severity)| {
+                let mut builder = GitignoreBuilder::new(root);
+                builder
+                    .add_line(None, pattern)
+                    .map_err(|e| format!("invalid severity path '{pattern}': {e}"))?;
+                let patterns = builder.build().map_err(|e| e.to_string())?;
+                Ok((patterns, *severity))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { rules })
+    }
+
+    /// The highest severity the rules give `path`, relative to the repository
+    /// root, if any matches.
+    fn severity(&self, path: &str) -> Option<Severity> {
+        self.rules
+            .iter()
+            .filter(|(patterns, _)| {
+                patterns
+                    .matched_path_or_any_parents(path, false)
+                    .is_ignore()
+            })
+            .map(|(_, severity)| *severity)
+            .max()
+    }
+
+    /// Raises each of `todos` to the severity of its path, whatever its
+    /// keyword.
+    pub fn raise(&self, repo: &Repository, todos: &mut [Todo]) {
+        if self.rules.is_empty() {
+            return;
+        }
+        for todo in todos {
+            if let Some(severity) = self.severity(&repo_relative_path(repo, &todo.file_path)) {
+                todo.severity = todo.severity.max(severity);
+            }
+        }
+    }
+}
+
+/// Escalates TODOs first written more than `max_age_days` ago: bumps their
+/// severity, tags them `stale` and marks them for the reports' stale section.
+pub fn escalate(todos: &mut [Todo], max_age_days: u32) {
+    let max_age = chrono::TimeDelta::days(i64::from(max_age_days));
+    for todo in todos {
+        let written = todo.introduced_date.unwrap_or(todo.author_date);
+        if dates::now() - written <= max_age {
+            continue;
+        }
+        todo.stale = true;
+        todo.severity = todo.severity.bumped();
+        if !todo.tags.iter().any(|tag| tag == STALE_TAG) {
+            todo.tags.push(STALE_TAG.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_rules_take_the_highest_match() {
+        let rules = PathRules::new(
+            Path::new("/repo"),
+            &[
+                ("src/security/**".to_string(), Severity::High),
+                ("*.sql".to_string(), Severity::Medium),
+                ("src/".to_string(), Severity::Low),
+            ],
+        )
+        .unwrap();
+        assert_eq!(rules.severity("src/security/auth.rs"), Some(Severity::High));
+        assert_eq!(rules.severity("db/schema.sql"), Some(Severity::Medium));
+        assert_eq!(rules.severity("src/main.rs"), Some(Severity::Low));
+        assert_eq!(rules.severity("docs/guide.md"), None);
+    }
+
+    #[test]
+    fn escalates_todos_older_than_the_max_age() {
+        let matcher = crate::matcher::Matcher::default();
+        let todo = |raw: &str, days: i64, introduced: Option<i64>| {
+            let mut todo = Todo::new(&matcher, "a.rs".into(), 1, raw);
+            todo.author_date = dates::now() - chrono::TimeDelta::days(days);
+            todo.introduced_date =
+                introduced.map(|days| dates::now() - chrono::TimeDelta::days(days));
+            todo
+        };
+        let mut todos = [
+            todo("// TODO: old", 40, None),
+            Todo {
+                severity: Severity::High,
+                ..todo("// TODO: urgent", 40, None)
+            },
+            todo("// TODO: recent", 10, None),
+            // squashed recently, but written long before
+            todo("// TODO: squashed", 1, Some(90)),
+        ];
+        escalate(&mut todos, 30);
+
+        let stale: Vec<_> = todos.iter().map(|todo| todo.stale).collect();
+        assert_eq!(stale, [true, true, false, true]);
+        let severities: Vec<_> = todos.iter().map(|todo| todo.severity).collect();
+        assert_eq!(
+            severities,
+            [
+                Severity::Medium,
+                Severity::High,
+                Severity::Low,
+                Severity::Medium
+            ]
+        );
+        assert_eq!(todos[0].tags, [STALE_TAG]);
+        // the stale section lists the oldest first
+        let stale: Vec<_> = crate::stale_todos(&todos)
+            .iter()
+            .map(|todo| todo.raw.as_str())
+            .collect();
+        assert_eq!(
+            stale,
+            ["// TODO: squashed", "// TODO: old", "// TODO: urgent"]
+        );
+    }
+}
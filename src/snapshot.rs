@@ -0,0 +1,215 @@
+//! `todo snapshot`: stores each scan as a git note under `refs/notes/todo`,
+//! so the history of TODOs travels with the repository (push and fetch the
+//! ref like any other) and needs no external database. Git stores the notes
+//! zlib-compressed like every other object.
+
+use std::collections::BTreeSet;
+
+use colored::Colorize;
+use git2::{Oid, Repository, Signature};
+use serde_json::json;
+
+use crate::{dates, dry_run, export, Todo};
+
+pub const NOTES_REF: &str = "refs/notes/todo";
+
+/// Records the TODOs found at `HEAD` as a note on it, replacing any earlier
+/// snapshot of the same commit.
+pub fn record(repo: &Repository, todos: &[Todo]) -> Result<(), git2::Error> {
+    let head = repo.head()?.peel_to_commit()?;
+    let snapshot = json!({
+        "timestamp": dates::now().timestamp(),
+        "head": head.id().to_string(),
+        "todos": export::json(repo, todos),
+    });
+
+    let short_id = head.as_object().short_id()?;
+    let short_id = short_id.as_str().unwrap_or_default();
+    if dry_run::enabled() {
+        println!(
+            "[dry run] would record {} TODOs for {short_id} in {NOTES_REF}:\n{snapshot}",
+            todos.len()
+        );
+        return Ok(());
+    }
+
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("todo", "todo@localhost"))?;
+    repo.note(
+        &signature,
+        &signature,
+        Some(NOTES_REF),
+        head.id(),
+        &snapshot.to_string(),
+        true,
+    )?;
+
+    println!(
+        "Recorded {} TODOs for {short_id} in {NOTES_REF}",
+        todos.len()
+    );
+    Ok(())
+}
+
+fn load(repo: &Repository, commit: Oid) -> Result<serde_json::Value, git2::Error> {
+    let note = repo.find_note(Some(NOTES_REF), commit)?;
+    serde_json::from_str(note.message().unwrap_or_default())
+        .map_err(|e| git2::Error::from_str(&format!("damaged snapshot of {commit}: {e}")))
+}
+
+/// Snapshotted commits, oldest first.
+fn snapshotted(repo: &Repository) -> Result<Vec<Oid>, git2::Error> {
+    let mut commits = Vec::new();
+    for note in repo.notes(Some(NOTES_REF))? {
+        let (_, commit) = note?;
+        let time = repo.find_commit(commit)?.time().seconds();
+        commits.push((time, commit));
+    }
+    commits.sort();
+    Ok(commits.into_iter().map(|(_, commit)| commit).collect())
+}
+
+fn todo_count(snapshot: &serde_json::Value) -> usize {
+    snapshot["todos"].as_array().map_or(0, Vec::len)
+}
+
+/// Lists the snapshots, newest first, with their TODO counts and the change
+/// from the snapshot before.
+pub fn log(repo: &Repository) -> Result<(), git2::Error> {
+    let commits = snapshotted(repo)?;
+    if commits.is_empty() {
+        println!("No snapshots yet; run `todo snapshot` to record one");
+        return Ok(());
+    }
+
+    let mut previous = None;
+    let mut lines = Vec::new();
+    for id in commits {
+        let commit = repo.find_commit(id)?;
+        let count = todo_count(&load(repo, id)?);
+        let change =
+            previous.map_or_else(String::new, |previous: usize| match count.cmp(&previous) {
+                std::cmp::Ordering::Greater => format!("+{}", count - previous).red().to_string(),
+                std::cmp::Ordering::Less => format!("−{}", previous - count).green().to_string(),
+                std::cmp::Ordering::Equal => "±0".dimmed().to_string(),
+            });
+        let date = chrono::DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_default();
+        lines.push(format!(
+            "{} {:>5} {:>5}  {} {}",
+            commit
+                .as_object()
+                .short_id()?
+                .as_str()
+                .unwrap_or_default()
+                .yellow(),
+            count,
+            change,
+            commit.summary().unwrap_or_default(),
+            format!("({})", dates::ago(date)).dimmed()
+        ));
+        previous = Some(count);
+    }
+
+    for line in lines.iter().rev() {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// TODOs of a snapshot as `path: statement`, which stays stable when lines
+/// move.
+fn fingerprints(snapshot: &serde_json::Value) -> BTreeSet<String> {
+    snapshot["todos"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|todo| {
+            format!(
+                "{}: {}",
+                todo["path"].as_str().unwrap_or_default(),
+                todo["statement"].as_str().unwrap_or_default()
+            )
+        })
+        .collect()
+}
+
+/// Shows the TODOs added and removed between two snapshots. Without revisions
+/// the two latest snapshots are compared; with one, its snapshot is compared
+/// with the latest.
+pub fn diff(repo: &Repository, from: Option<&str>, to: Option<&str>) -> Result<(), git2::Error> {
+    let commits = snapshotted(repo)?;
+    let resolve = |spec: &str| repo.revparse_single(spec)?.peel_to_commit().map(|c| c.id());
+
+    let to = match to {
+        Some(spec) => resolve(spec)?,
+        None => *commits
+            .last()
+            .ok_or_else(|| git2::Error::from_str("no snapshots to compare"))?,
+    };
+    let from = match from {
+        Some(spec) => resolve(spec)?,
+        None => *commits
+            .iter()
+            .rev()
+            .nth(1)
+            .ok_or_else(|| git2::Error::from_str("only one snapshot, nothing to compare"))?,
+    };
+
+    let before = fingerprints(&load(repo, from)?);
+    let after = fingerprints(&load(repo, to)?);
+
+    for removed in before.difference(&after) {
+        println!("{}", format!("- {removed}").green());
+    }
+    for added in after.difference(&before) {
+        println!("{}", format!("+ {added}").red());
+    }
+    println!(
+        "{} added, {} removed",
+        after.difference(&before).count(),
+        before.difference(&after).count()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn records_scans_as_notes_on_each_commit() {
+        let test = TestRepo::new("snapshot");
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let snapshot = || {
+            let (todos, _) = crate::get_todos(&test.repo, &options);
+            record(&test.repo, &todos).unwrap();
+        };
+        test.write("a.rs", "// TODO: one\n// TODO: two\n");
+        let first = test.commit("Alice", "a");
+        snapshot();
+        test.write("a.rs", "// TODO: two\n// TODO: three\n// TODO: four\n");
+        let second = test.commit("Alice", "b");
+        snapshot();
+        // recording the same commit again replaces its snapshot
+        snapshot();
+
+        assert_eq!(snapshotted(&test.repo).unwrap(), [first, second]);
+        let (before, after) = (
+            load(&test.repo, first).unwrap(),
+            load(&test.repo, second).unwrap(),
+        );
+        assert_eq!(before["head"], first.to_string());
+        assert_eq!((todo_count(&before), todo_count(&after)), (2, 3));
+        let (before, after) = (fingerprints(&before), fingerprints(&after));
+        assert_eq!(before.difference(&after).collect::<Vec<_>>(), ["a.rs: one"]);
+        assert_eq!(after.difference(&before).count(), 2);
+        assert!(diff(&test.repo, None, None).is_ok());
+    }
+}
@@ -0,0 +1,96 @@
+//! Output in `SonarQube`'s Generic Issue Import format, loaded by setting
+//! `sonar.externalIssuesReportPaths` to the generated file.
+
+use std::collections::BTreeSet;
+
+use git2::Repository;
+use serde_json::json;
+
+use crate::github::strip_ansi;
+use crate::severity::Severity;
+use crate::{redact, repo_relative_path, Todo};
+
+const ENGINE_ID: &str = "todo";
+const RULE_ID: &str = "todo-comment";
+
+/// Sonar sets severity per rule, so each severity in use gets its own rule;
+/// low keeps the original id.
+fn rule_id(severity: Severity) -> String {
+    match severity {
+        Severity::Low => RULE_ID.to_string(),
+        severity => format!("{RULE_ID}-{severity}"),
+    }
+}
+
+pub fn generic_issues(repo: &Repository, todos: &[Todo]) -> serde_json::Value {
+    let issues: Vec<_> = todos
+        .iter()
+        .map(|todo| {
+            json!({
+                "ruleId": rule_id(todo.severity),
+                "primaryLocation": {
+                    "message": redact::shared(&strip_ansi(todo.statement.trim())),
+                    "filePath": repo_relative_path(repo, &todo.file_path),
+                    "textRange": { "startLine": todo.line },
+                },
+            })
+        })
+        .collect();
+
+    let severities: BTreeSet<Severity> = todos.iter().map(|todo| todo.severity).collect();
+    let rules: Vec<_> = severities
+        .into_iter()
+        .map(|severity| {
+            json!({
+                "id": rule_id(severity),
+                "name": "TODO comment",
+                "description": "A TODO comment marks unfinished work that should be tracked and resolved.",
+                "engineId": ENGINE_ID,
+                "cleanCodeAttribute": "COMPLETE",
+                "impacts": [{ "softwareQuality": "MAINTAINABILITY", "severity": severity.api_name() }],
+            })
+        })
+        .collect();
+
+    json!({ "rules": rules, "issues": issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn gives_each_severity_a_rule() {
+        let test = TestRepo::new("sonar");
+        let todo = |line: usize, severity: Severity| {
+            let mut todo = crate::Todo::new(
+                &Matcher::default(),
+                test.dir.join("src/a.rs"),
+                line,
+                "// TODO: x",
+            );
+            todo.severity = severity;
+            todo
+        };
+        let todos = [
+            todo(1, Severity::Low),
+            todo(2, Severity::High),
+            todo(3, Severity::High),
+        ];
+
+        let report = generic_issues(&test.repo, &todos);
+        let rules: Vec<_> = report["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rule| rule["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, ["todo-comment", "todo-comment-high"]);
+        let issue = &report["issues"][1];
+        assert_eq!(issue["ruleId"], "todo-comment-high");
+        assert_eq!(issue["primaryLocation"]["filePath"], "src/a.rs");
+        assert_eq!(issue["primaryLocation"]["textRange"]["startLine"], 2);
+    }
+}
@@ -0,0 +1,157 @@
+//! Recovering when TODOs were really introduced on repositories that squash
+//! merge, where blame only sees the squash commit.
+//!
+//! The original branch history often survives in remote-tracking branches or
+//! reflogs even after the squash. Any TODO line found added there, and not on
+//! the base branch, earlier than its blame date keeps that earlier date.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository};
+
+use crate::base;
+use crate::branch_commits::todo_changes;
+use crate::cli::Options;
+use crate::github::strip_ansi;
+use crate::matcher::Matcher;
+use crate::trend::fingerprint;
+use crate::{repo_relative_path, Todo};
+
+/// Tips of remote-tracking branches plus every commit a reflog remembers,
+/// where squashed branches linger, unlike unrelated local branches and tags.
+fn history_tips(repo: &Repository) -> Result<HashSet<Oid>, git2::Error> {
+    let mut tips = HashSet::new();
+
+    for reference in repo.references()? {
+        let reference = reference?;
+        if let Some(target) = reference.target().filter(|_| reference.is_remote()) {
+            tips.insert(target);
+        }
+
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        if let Ok(reflog) = repo.reflog(name) {
+            tips.extend(reflog.iter().map(|entry| entry.id_new()));
+        }
+    }
+
+    if let Ok(reflog) = repo.reflog("HEAD") {
+        tips.extend(reflog.iter().map(|entry| entry.id_new()));
+    }
+
+    tips.remove(&Oid::zero());
+    Ok(tips)
+}
+
+/// Earliest author date at which each (path, fingerprint) was added by a
+/// commit that is part of neither `HEAD`'s history nor the base branch's.
+fn earliest_additions(
+    repo: &Repository,
+    options: &Options,
+) -> Result<HashMap<(String, String), DateTime<Utc>>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    for tip in history_tips(repo)? {
+        // reflogs can mention commits that have since been garbage collected
+        if repo.find_commit(tip).is_ok() {
+            revwalk.push(tip)?;
+        }
+    }
+    revwalk.hide_head()?;
+    // the base's own history is what blame already sees, not squashed away
+    if let Ok(base) = base::commit(repo, options) {
+        revwalk.hide(base.id())?;
+    }
+
+    let matcher = Matcher::configured(options);
+    let mut earliest: HashMap<(String, String), DateTime<Utc>> = HashMap::new();
+
+    for id in revwalk {
+        let commit = repo.find_commit(id?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+
+        let date =
+            DateTime::from_timestamp(commit.author().when().seconds(), 0).unwrap_or_default();
+        for change in todo_changes(repo, &matcher, &commit)?.added {
+            earliest
+                .entry((change.path, fingerprint(&change.text)))
+                .and_modify(|existing| *existing = (*existing).min(date))
+                .or_insert(date);
+        }
+    }
+
+    Ok(earliest)
+}
+
+/// Records the original introduction date on TODOs that pre-squash history
+/// shows were added before the commit blame attributes them to.
+pub fn restore_introduction_dates(
+    repo: &Repository,
+    options: &Options,
+    todos: &mut [Todo],
+) -> Result<(), git2::Error> {
+    let earliest = earliest_additions(repo, options)?;
+
+    for todo in todos {
+        let key = (
+            repo_relative_path(repo, &todo.file_path),
+            fingerprint(&strip_ansi(&todo.statement)),
+        );
+        if let Some(&date) = earliest.get(&key) {
+            if date < todo.author_date {
+                todo.introduced_date = Some(date);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn dates_from_squashed_branches_only() {
+        let test = TestRepo::new("squash");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("topic");
+        test.checkout("main");
+        test.write("c.rs", "// TODO: same\n");
+        test.commit("Alice", "on main");
+        test.checkout("topic");
+        test.branch("feature");
+        test.write("b.rs", "// TODO: squashed\n");
+        let feature = test.commit("Bob", "b");
+        let tip = test.repo.find_commit(feature).unwrap();
+        test.repo
+            .reference("refs/remotes/origin/feature", feature, false, "push")
+            .unwrap();
+        test.checkout("topic");
+        test.repo
+            .find_branch("feature", git2::BranchType::Local)
+            .unwrap()
+            .delete()
+            .unwrap();
+        test.write("b.rs", "// TODO: squashed\n");
+        test.write("c.rs", "// TODO: same\n");
+        test.commit("Carol", "Squash (#1)");
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (mut todos, _) = crate::get_todos(&test.repo, &options);
+        restore_introduction_dates(&test.repo, &options, &mut todos).unwrap();
+        let introduced: Vec<_> = todos.iter().map(|todo| todo.introduced_date).collect();
+        let bob = DateTime::from_timestamp(tip.author().when().seconds(), 0);
+        assert_eq!(introduced, [bob, None]);
+    }
+}
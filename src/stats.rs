@@ -0,0 +1,378 @@
+//! Aggregate TODO counts, printed or pushed to a time-series database so debt
+//! can be charted over time.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use url::Url;
+
+use crate::credentials::Provider;
+use crate::http::Request;
+use crate::severity::Severity;
+use crate::{dates, dry_run, queue, Todo};
+
+const UNTAGGED: &str = "untagged";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub total: usize,
+    pub by_tag: BTreeMap<String, usize>,
+    pub by_author: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    pub fn compute(todos: &[Todo]) -> Self {
+        let mut stats = Self {
+            total: todos.len(),
+            ..Self::default()
+        };
+
+        for todo in todos {
+            if todo.tags.is_empty() {
+                *stats.by_tag.entry(UNTAGGED.to_string()).or_default() += 1;
+            }
+            for tag in &todo.tags {
+                *stats.by_tag.entry(tag.clone()).or_default() += 1;
+            }
+
+            *stats.by_author.entry(todo.author.clone()).or_default() += 1;
+            for co_author in &todo.co_authors {
+                if *co_author != todo.author {
+                    *stats.by_author.entry(co_author.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        stats
+    }
+
+    pub fn print(&self) {
+        println!("{} {}", "TODOs:".bold(), self.total);
+        print_counts("By tag", &self.by_tag);
+        print_counts("By author", &self.by_author);
+    }
+}
+
+/// Tags named in a summary; the rest are only counted.
+const SUMMARY_TAGS: usize = 5;
+
+/// The key numbers a report ends with, e.g. `27 TODOs (3 high, 20 medium,
+/// 4 low) across 14 files, oldest 287 days; 5 perf, 22 untagged`.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub total: usize,
+    /// Counts of high, medium and low severity TODOs.
+    pub severities: [usize; 3],
+    pub files: usize,
+    /// Days since the oldest TODO was first written.
+    pub oldest_days: Option<i64>,
+    /// Tags by count, largest first, with untagged TODOs as `untagged`.
+    pub tags: Vec<(String, usize)>,
+}
+
+impl Summary {
+    pub fn compute(todos: &[Todo]) -> Self {
+        let mut severities = [0; 3];
+        for todo in todos {
+            severities[match todo.severity {
+                Severity::High => 0,
+                Severity::Medium => 1,
+                Severity::Low => 2,
+            }] += 1;
+        }
+        let files: BTreeSet<_> = todos.iter().map(|todo| &todo.file_path).collect();
+        let oldest_days = todos
+            .iter()
+            .map(|todo| todo.introduced_date.unwrap_or(todo.author_date))
+            .min()
+            .map(|oldest| (dates::now() - oldest).num_days());
+        let mut tags: Vec<_> = Stats::compute(todos).by_tag.into_iter().collect();
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Self {
+            total: todos.len(),
+            severities,
+            files: files.len(),
+            oldest_days,
+            tags,
+        }
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        let [high, medium, low] = self.severities;
+        json!({
+            "total": self.total,
+            "severities": { "high": high, "medium": medium, "low": low },
+            "files": self.files,
+            "oldest_days": self.oldest_days,
+            "tags": self.tags.iter().cloned().collect::<BTreeMap<_, _>>(),
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |one: bool| if one { "" } else { "s" };
+        let [high, medium, low] = self.severities;
+        write!(
+            f,
+            "{} TODO{} ({high} high, {medium} medium, {low} low) across {} file{}",
+            self.total,
+            plural(self.total == 1),
+            self.files,
+            plural(self.files == 1),
+        )?;
+        if let Some(days) = self.oldest_days {
+            write!(f, ", oldest {days} day{}", plural(days == 1))?;
+        }
+        if !self.tags.is_empty() {
+            let mut tags: Vec<_> = self
+                .tags
+                .iter()
+                .take(SUMMARY_TAGS)
+                .map(|(tag, count)| format!("{count} {tag}"))
+                .collect();
+            if self.tags.len() > SUMMARY_TAGS {
+                tags.push(format!("{} more tags", self.tags.len() - SUMMARY_TAGS));
+            }
+            write!(f, "; {}", tags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints counts as an aligned table, largest first.
+fn print_counts(title: &str, counts: &BTreeMap<String, usize>) {
+    if counts.is_empty() {
+        return;
+    }
+
+    let mut sorted: Vec<_> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let width = sorted
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    println!("\n{}", format!("{title}:").bold());
+    for (name, count) in sorted {
+        println!("  {name:<width$}  {count}");
+    }
+}
+
+/// Name of the repository's working directory, used to label the series.
+fn repo_name(repo: &Repository) -> String {
+    repo.workdir().and_then(|dir| dir.file_name()).map_or_else(
+        || "repository".to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Pushes the counts to the time-series database at `target`:
+///
+/// - `influx://[user:password@]host[:port]/database` (or `influxs://` for
+///   TLS) writes line protocol to Influx's `/write` endpoint. A
+///   token from `$INFLUX_TOKEN` or `todo auth login influx`, if any, is sent
+///   for Influx 2.
+/// - `graphite://host[:port][/prefix]` writes Graphite's plaintext protocol.
+pub fn push(repo: &Repository, stats: &Stats, target: &str) -> io::Result<()> {
+    let url = Url::parse(target).map_err(|e| io::Error::other(format!("{target}: {e}")))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let name = repo_name(repo);
+
+    match url.scheme() {
+        "influx" | "influxs" => push_influx(repo, &url, &influx_lines(&name, stats, timestamp)),
+        "graphite" => push_graphite(&url, &graphite_lines(&url, &name, stats, timestamp)),
+        scheme => Err(io::Error::other(format!(
+            "unsupported scheme '{scheme}', expected influx://, influxs:// or graphite://"
+        ))),
+    }
+}
+
+/// Escapes commas, spaces and equals signs in Influx tag values.
+fn influx_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(' ', "\\ ")
+        .replace('=', "\\=")
+}
+
+fn influx_lines(repo: &str, stats: &Stats, timestamp: u64) -> String {
+    let repo = influx_escape(repo);
+    let mut lines = vec![format!(
+        "todos,repo={repo} count={}i {timestamp}",
+        stats.total
+    )];
+
+    for (tag, count) in &stats.by_tag {
+        lines.push(format!(
+            "todos_by_tag,repo={repo},tag={} count={count}i {timestamp}",
+            influx_escape(tag)
+        ));
+    }
+    for (author, count) in &stats.by_author {
+        lines.push(format!(
+            "todos_by_author,repo={repo},author={} count={count}i {timestamp}",
+            influx_escape(author)
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn push_influx(repo: &Repository, url: &Url, lines: &str) -> io::Result<()> {
+    let scheme = if url.scheme() == "influxs" {
+        "https"
+    } else {
+        "http"
+    };
+    let host = url.host_str().unwrap_or("localhost");
+    let port = url.port().unwrap_or(8086);
+    let database = url.path().trim_matches('/');
+
+    let mut write_url = Url::parse(&format!("{scheme}://{host}:{port}/write"))
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    write_url
+        .query_pairs_mut()
+        .append_pair("db", database)
+        .append_pair("precision", "s");
+    if !url.username().is_empty() {
+        write_url
+            .query_pairs_mut()
+            .append_pair("u", url.username())
+            .append_pair("p", url.password().unwrap_or(""));
+    }
+
+    let request = Request::new("POST", write_url.as_str())
+        .body(lines.as_bytes().to_vec())
+        .authorization("Token", Provider::Influx);
+    queue::send(repo, request)
+}
+
+/// Keeps Graphite path components to characters it does not treat specially.
+fn graphite_escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn graphite_lines(url: &Url, repo: &str, stats: &Stats, timestamp: u64) -> String {
+    let prefix = url.path().trim_matches('/').replace('/', ".");
+    let prefix = if prefix.is_empty() {
+        "todos".to_string()
+    } else {
+        prefix
+    };
+    let base = format!("{prefix}.{}", graphite_escape(repo));
+
+    let mut lines = vec![format!("{base}.total {} {timestamp}", stats.total)];
+    for (tag, count) in &stats.by_tag {
+        lines.push(format!(
+            "{base}.tag.{} {count} {timestamp}",
+            graphite_escape(tag)
+        ));
+    }
+    for (author, count) in &stats.by_author {
+        lines.push(format!(
+            "{base}.author.{} {count} {timestamp}",
+            graphite_escape(author)
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn push_graphite(url: &Url, lines: &str) -> io::Result<()> {
+    let host = url.host_str().unwrap_or("localhost");
+    let port = url.port().unwrap_or(2003);
+
+    if dry_run::enabled() {
+        eprint!("[dry run] graphite {host}:{port}\n{lines}");
+        return Ok(());
+    }
+    let mut stream = TcpStream::connect((host, port))?;
+    stream.write_all(lines.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    fn stats() -> Stats {
+        Stats {
+            total: 3,
+            by_tag: BTreeMap::from([("perf".to_string(), 2), (UNTAGGED.to_string(), 1)]),
+            by_author: BTreeMap::from([("Ada Lovelace".to_string(), 3)]),
+        }
+    }
+
+    #[test]
+    fn writes_influx_line_protocol() {
+        assert_eq!(
+            influx_lines("my repo", &stats(), 1_700_000_000),
+            "todos,repo=my\\ repo count=3i 1700000000\n\
+             todos_by_tag,repo=my\\ repo,tag=perf count=2i 1700000000\n\
+             todos_by_tag,repo=my\\ repo,tag=untagged count=1i 1700000000\n\
+             todos_by_author,repo=my\\ repo,author=Ada\\ Lovelace count=3i 1700000000\n"
+        );
+    }
+
+    #[test]
+    fn pushes_graphite_plaintext() {
+        let test = TestRepo::new("graphite");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        push(
+            &test.repo,
+            &stats(),
+            &format!("graphite://127.0.0.1:{port}/ci/debt"),
+        )
+        .unwrap();
+
+        let mut received = String::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_string(&mut received)
+            .unwrap();
+        let name = graphite_escape(&repo_name(&test.repo));
+        let metrics: Vec<_> = received
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            metrics,
+            [
+                format!("ci.debt.{name}.total 3"),
+                format!("ci.debt.{name}.tag.perf 2"),
+                format!("ci.debt.{name}.tag.untagged 1"),
+                format!("ci.debt.{name}.author.Ada_Lovelace 3"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Who is still on the team, so TODOs left behind by departed contributors
+//! can be found and reassigned.
+//!
+//! A team file lists one member per line. A `.toml` file instead lists them
+//! under `members` and can configure `todo rotate`:
+//!
+//! ```toml
+//! members = ["alice@example.com", "@bob", "Carol Smith"]
+//!
+//! [rotation]
+//! per_member = 3
+//! webhook = "https://hooks.slack.com/services/..."
+//! ```
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::Todo;
+
+/// How `todo rotate` hands out TODOs.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rotation {
+    /// TODOs each member is on duty for per week.
+    #[serde(default = "Rotation::default_per_member")]
+    pub per_member: usize,
+    /// Where `rotate --send` posts the assignments, as a Slack-style
+    /// `{"text": ...}` message.
+    pub webhook: Option<String>,
+}
+
+impl Rotation {
+    const fn default_per_member() -> usize {
+        3
+    }
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self {
+            per_member: Self::default_per_member(),
+            webhook: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TeamFile {
+    members: Vec<String>,
+    #[serde(default)]
+    rotation: Rotation,
+}
+
+pub struct Team {
+    /// Members as written in the team file, in order.
+    names: Vec<String>,
+    /// Lowercased names, emails and `@handles`.
+    members: HashSet<String>,
+    pub rotation: Rotation,
+}
+
+impl Team {
+    /// Reads a team file: one member per line, given by name, email or
+    /// `@handle`. Blank lines and lines starting with `#` are ignored. Files
+    /// ending in `.toml` are read as TOML instead.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let file = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.message().to_string()))?
+        } else {
+            TeamFile {
+                members: contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string)
+                    .collect(),
+                rotation: Rotation::default(),
+            }
+        };
+        Ok(Self {
+            members: file
+                .members
+                .iter()
+                .map(|name| name.to_lowercase())
+                .collect(),
+            names: file.members,
+            rotation: file.rotation,
+        })
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The member a TODO is assigned to with `TODO(@handle)` or
+    /// `TODO(owner=handle)`, if any.
+    pub fn assignee(&self, todo: &Todo) -> Option<&str> {
+        todo.assignees()
+            .find_map(|assignee| {
+                self.names
+                    .iter()
+                    .find(|name| name.trim_start_matches('@').eq_ignore_ascii_case(assignee))
+            })
+            .map(String::as_str)
+    }
+
+    fn contains(&self, identity: &str) -> bool {
+        !identity.is_empty() && self.members.contains(&identity.to_lowercase())
+    }
+
+    /// A TODO assigned with `TODO(@handle)` or `TODO(owner=handle)` belongs
+    /// to its assignees, and otherwise to the author of the line.
+    fn owns(&self, todo: &Todo) -> bool {
+        let mut assignees = todo.assignees().peekable();
+        if assignees.peek().is_some() {
+            return assignees
+                .any(|assignee| self.contains(assignee) || self.contains(&format!("@{assignee}")));
+        }
+        // uncommitted lines are the current user's
+        todo.commit_hash.is_empty()
+            || self.contains(&todo.author)
+            || self.contains(&todo.author_email)
+    }
+
+    pub fn mark_orphaned(&self, todos: &mut [Todo]) {
+        for todo in todos {
+            todo.orphaned = !self.owns(todo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn orphans_todos_of_departed_authors_and_assignees() {
+        let test = TestRepo::new("team");
+        test.write("a.rs", "// TODO: stays\n");
+        test.commit("Alice", "a");
+        test.write(
+            "a.rs",
+            "// TODO: stays\n// TODO: left\n// TODO(@alice): handed over\n// TODO(owner=dave): gone\n",
+        );
+        test.commit("Bob", "b");
+        test.write(
+            "team.txt",
+            "# current members\nalice@example.com\n\n@Carol\n",
+        );
+        let team = Team::load(&test.dir.join("team.txt")).unwrap();
+        assert_eq!(team.names(), ["alice@example.com", "@Carol"]);
+
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (mut todos, _) = crate::get_todos(&test.repo, &options);
+        todos.retain(|todo| todo.file_path.ends_with("a.rs"));
+        todos.sort_by_key(|todo| todo.line);
+        team.mark_orphaned(&mut todos);
+        let orphaned: Vec<_> = todos.iter().map(|todo| todo.orphaned).collect();
+        assert_eq!(orphaned, [false, true, true, true]);
+
+        test.write("team.toml", "members = [\"Bob\", \"Alice\"]\n");
+        let team = Team::load(&test.dir.join("team.toml")).unwrap();
+        assert_eq!(team.rotation.per_member, 3);
+        team.mark_orphaned(&mut todos);
+        let orphaned: Vec<_> = todos.iter().map(|todo| todo.orphaned).collect();
+        assert_eq!(orphaned, [false, false, false, true]);
+        assert_eq!(team.assignee(&todos[2]), Some("Alice"));
+    }
+}
@@ -0,0 +1,307 @@
+//! Optional OpenTelemetry tracing of the scan phases, exported as OTLP/HTTP
+//! JSON when `--otel-endpoint` is set.
+//!
+//! Spans are collected in memory and sent in one request when the run ends,
+//! so a slow or unreachable collector never slows down the scan itself.
+
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::fmt::{Display, Write as _};
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::http::{Request, Retry};
+
+struct FinishedSpan {
+    id: String,
+    parent_id: Option<String>,
+    name: &'static str,
+    start: u128,
+    end: u128,
+    attributes: Vec<(&'static str, String)>,
+}
+
+struct Trace {
+    endpoint: String,
+    id: String,
+    spans: Vec<FinishedSpan>,
+}
+
+static TRACE: Mutex<Option<Trace>> = Mutex::new(None);
+
+thread_local! {
+    /// Ids of the spans currently open on this thread, innermost last.
+    static OPEN_SPANS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut hex = String::with_capacity(bytes * 2);
+    while hex.len() < bytes * 2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(now_nanos());
+        write!(hex, "{:016x}", hasher.finish()).unwrap();
+    }
+    hex.truncate(bytes * 2);
+    hex
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+/// Starts collecting spans to send to the collector at `endpoint`.
+pub fn init(endpoint: &str) {
+    *TRACE.lock().unwrap() = Some(Trace {
+        endpoint: endpoint.trim_end_matches('/').to_string(),
+        id: random_hex(16),
+        spans: Vec::new(),
+    });
+}
+
+fn enabled() -> bool {
+    TRACE.lock().unwrap().is_some()
+}
+
+/// A span that ends when dropped. Does nothing unless tracing is enabled.
+pub struct Span {
+    open: Option<OpenSpan>,
+}
+
+struct OpenSpan {
+    id: String,
+    parent_id: Option<String>,
+    name: &'static str,
+    start: u128,
+    attributes: Vec<(&'static str, String)>,
+}
+
+/// Opens a span nested in the innermost span open on this thread.
+pub fn span(name: &'static str) -> Span {
+    if !enabled() {
+        return Span { open: None };
+    }
+
+    let id = random_hex(8);
+    let parent_id = OPEN_SPANS.with_borrow_mut(|open| {
+        let parent = open.last().cloned();
+        open.push(id.clone());
+        parent
+    });
+
+    Span {
+        open: Some(OpenSpan {
+            id,
+            parent_id,
+            name,
+            start: now_nanos(),
+            attributes: Vec::new(),
+        }),
+    }
+}
+
+/// The id of the innermost span open on this thread, for spans that other
+/// threads open on its behalf.
+pub fn current() -> Option<String> {
+    OPEN_SPANS.with_borrow(|open| open.last().cloned())
+}
+
+/// Runs `f` with the spans it opens nested in `parent`, a span from
+/// [`current`] on another thread.
+pub fn within<T>(parent: Option<String>, f: impl FnOnce() -> T) -> T {
+    let Some(parent) = parent else {
+        return f();
+    };
+    OPEN_SPANS.with_borrow_mut(|open| open.push(parent.clone()));
+    let result = f();
+    OPEN_SPANS.with_borrow_mut(|open| open.retain(|id| *id != parent));
+    result
+}
+
+impl Span {
+    pub fn attribute(mut self, key: &'static str, value: impl Display) -> Self {
+        if let Some(open) = &mut self.open {
+            open.attributes.push((key, value.to_string()));
+        }
+        self
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(open) = self.open.take() else {
+            return;
+        };
+
+        OPEN_SPANS.with_borrow_mut(|spans| spans.retain(|id| *id != open.id));
+
+        if let Some(trace) = TRACE.lock().unwrap().as_mut() {
+            trace.spans.push(FinishedSpan {
+                id: open.id,
+                parent_id: open.parent_id,
+                name: open.name,
+                start: open.start,
+                end: now_nanos(),
+                attributes: open.attributes,
+            });
+        }
+    }
+}
+
+/// Extra headers from `OTEL_EXPORTER_OTLP_HEADERS` (`key=value,key=value`),
+/// which collectors commonly use for authentication.
+fn env_headers() -> Vec<(String, String)> {
+    std::env::var("OTEL_EXPORTER_OTLP_HEADERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Sends the collected spans, if tracing was enabled.
+pub fn export() -> io::Result<()> {
+    let Some(trace) = TRACE.lock().unwrap().take() else {
+        return Ok(());
+    };
+
+    let spans: Vec<_> = trace
+        .spans
+        .iter()
+        .map(|span| {
+            let attributes: Vec<_> = span
+                .attributes
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+                .collect();
+            json!({
+                "traceId": trace.id,
+                "spanId": span.id,
+                "parentSpanId": span.parent_id.clone().unwrap_or_default(),
+                "name": span.name,
+                "kind": 1,
+                "startTimeUnixNano": span.start.to_string(),
+                "endTimeUnixNano": span.end.to_string(),
+                "attributes": attributes,
+            })
+        })
+        .collect();
+
+    let body = json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    { "key": "service.name", "value": { "stringValue": env!("CARGO_PKG_NAME") } },
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    });
+
+    let url = if trace.endpoint.ends_with("/v1/traces") {
+        trace.endpoint
+    } else {
+        format!("{}/v1/traces", trace.endpoint)
+    };
+
+    // traces are exported as the run exits, so don't hold it up for long
+    let mut request = Request::new("POST", url).json(&body).retry(Retry {
+        attempts: 2,
+        ..Retry::default()
+    });
+    for (key, value) in env_headers() {
+        request = request.header(&key, value);
+    }
+    request.send_ok()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn exports_nested_spans() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+        let collector = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let (mut line, mut length) = (String::new(), 0);
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            (
+                request_line,
+                serde_json::from_slice::<Value>(&body).unwrap(),
+            )
+        });
+
+        init(&endpoint);
+        {
+            let _outer = span("test.outer").attribute("files", 2);
+            let _inner = span("test.inner");
+            let parent = current();
+            thread::spawn(|| within(parent, || drop(span("test.worker"))))
+                .join()
+                .unwrap();
+        }
+        export().unwrap();
+        let (request_line, body) = collector.join().unwrap();
+        assert!(request_line.starts_with("POST /v1/traces "));
+
+        // other tests scanning meanwhile add their own spans
+        let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        let find = |name: &str| {
+            spans
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|span| span["name"] == name)
+                .unwrap()
+                .clone()
+        };
+        let (outer, inner) = (find("test.outer"), find("test.inner"));
+        assert_eq!(inner["parentSpanId"], outer["spanId"]);
+        // spans of other threads nest where they were started from
+        assert_eq!(find("test.worker")["parentSpanId"], inner["spanId"]);
+        assert_eq!(inner["traceId"], outer["traceId"]);
+        assert_eq!(outer["parentSpanId"], "");
+        assert_eq!(
+            outer["attributes"],
+            json!([{ "key": "files", "value": { "stringValue": "2" } }])
+        );
+        // sent once, then disabled again
+        assert!(!enabled());
+    }
+}
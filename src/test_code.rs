@@ -0,0 +1,107 @@
+//! Whether TODOs are in tests or production code, and in which language, since
+//! debt in tests is usually less urgent. Test files are recognized by the
+//! usual directory and file name conventions, plus `--test-path` patterns or
+//! `test-paths` in `.todo.toml`, in gitignore syntax.
+
+use std::path::Path;
+
+use git2::Repository;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::{language, repo_relative_path, Todo};
+
+/// Directories whose contents are tests, matched in any case.
+const TEST_DIRS: [&str; 9] = [
+    "test",
+    "tests",
+    "__tests__",
+    "__mocks__",
+    "spec",
+    "specs",
+    "testdata",
+    "fixtures",
+    "e2e",
+];
+
+/// Whether a path relative to the repository root, with `/` separators, is a
+/// test by convention: in a test directory, or named like `parser_test.go`,
+/// `test_parser.py`, `parser.spec.ts` or `ParserTest.java`.
+fn is_conventional_test(path: &str) -> bool {
+    let mut components: Vec<&str> = path.split('/').collect();
+    let name = components.pop().unwrap_or_default();
+    if components
+        .iter()
+        .any(|dir| TEST_DIRS.iter().any(|test| dir.eq_ignore_ascii_case(test)))
+    {
+        return true;
+    }
+
+    let stem = name.split('.').next().unwrap_or_default();
+    name == "conftest.py"
+        || name.contains(".test.")
+        || name.contains(".spec.")
+        || stem.starts_with("test_")
+        || ["_test", "_tests", "_spec", "Test", "Tests", "Spec", "IT"]
+            .iter()
+            .any(|suffix| stem.len() > suffix.len() && stem.ends_with(suffix))
+}
+
+/// Classifies test files by convention and by `patterns`.
+pub struct Classifier {
+    patterns: Gitignore,
+}
+
+impl Classifier {
+    pub fn new(root: &Path, patterns: &[String]) -> Result<Self, String> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| format!("invalid test path '{pattern}': {e}"))?;
+        }
+        Ok(Self {
+            patterns: builder.build().map_err(|e| e.to_string())?,
+        })
+    }
+
+    /// Whether `path`, relative to the repository root, is a test.
+    pub fn is_test(&self, path: &str) -> bool {
+        is_conventional_test(path)
+            || self
+                .patterns
+                .matched_path_or_any_parents(path, false)
+                .is_ignore()
+    }
+
+    /// Sets the language of each of `todos` and whether it is in a test.
+    pub fn classify(&self, repo: &Repository, todos: &mut [Todo]) {
+        for todo in todos {
+            todo.language = language::language(&todo.file_path).to_string();
+            todo.test = self.is_test(&repo_relative_path(repo, &todo.file_path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_tests_by_convention_and_pattern() {
+        let classifier = Classifier::new(Path::new("/repo"), &["qa/**".to_string()]).unwrap();
+        for test in [
+            "tests/cli.rs",
+            "src/__tests__/app.tsx",
+            "pkg/parser_test.go",
+            "test_parser.py",
+            "web/parser.spec.ts",
+            "src/main/java/ParserTest.java",
+            "qa/smoke.sh",
+        ] {
+            assert!(classifier.is_test(test), "{test}");
+        }
+        for production in ["src/latest.rs", "src/testing.rs", "Test.java", "src/qa.rs"] {
+            assert!(!classifier.is_test(production), "{production}");
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Throwaway repositories for tests that walk git history.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use git2::{IndexAddOption, Oid, Repository, Signature, Time};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A repository in a temporary directory, on `main`, removed when dropped.
+pub struct TestRepo {
+    pub dir: PathBuf,
+    pub repo: Repository,
+    /// Commit times, a minute apart, so the order of commits is the order
+    /// they were made in.
+    time: AtomicUsize,
+}
+
+impl TestRepo {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "todo-{name}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        Self {
+            dir,
+            repo,
+            time: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn signature(&self, author: &str) -> Signature<'static> {
+        let minutes = self.time.fetch_add(1, Ordering::Relaxed);
+        let time = Time::new(1_700_000_000 + 60 * i64::try_from(minutes).unwrap(), 0);
+        let email = format!("{}@example.com", author.to_lowercase());
+        Signature::new(author, &email, &time).unwrap()
+    }
+
+    /// Commits the whole working tree on `HEAD` as `author`.
+    pub fn commit(&self, author: &str, message: &str) -> Oid {
+        let mut index = self.repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.update_all(["*"], None).unwrap();
+        index.write().unwrap();
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = self
+            .repo
+            .head()
+            .ok()
+            .map(|head| head.peel_to_commit().unwrap());
+        let signature = self.signature(author);
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                parent.as_slice().iter().collect::<Vec<_>>().as_slice(),
+            )
+            .unwrap()
+    }
+
+    /// Creates `name` at `HEAD` and checks it out.
+    pub fn branch(&self, name: &str) {
+        let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+        self.repo.branch(name, &head, false).unwrap();
+        self.checkout(name);
+    }
+
+    pub fn checkout(&self, name: &str) {
+        self.repo.set_head(&format!("refs/heads/{name}")).unwrap();
+        self.repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+    }
+
+    /// Merges `branch` into `HEAD` with a merge commit by `author`.
+    pub fn merge(&self, branch: &str, author: &str, message: &str) -> Oid {
+        let head = self.repo.head().unwrap().peel_to_commit().unwrap();
+        let theirs = self
+            .repo
+            .find_branch(branch, git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        let mut index = self.repo.merge_commits(&head, &theirs, None).unwrap();
+        let tree = self
+            .repo
+            .find_tree(index.write_tree_to(&self.repo).unwrap())
+            .unwrap();
+        let signature = self.signature(author);
+        let merge = self
+            .repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &[&head, &theirs],
+            )
+            .unwrap();
+        self.repo
+            .checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        merge
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
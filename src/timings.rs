@@ -0,0 +1,172 @@
+//! `--timings`: how long each phase of the run took and how much the caches
+//! saved, printed to stderr at the end, for finding out why a run is slow
+//! without setting up a trace collector. `--quiet --machine` prints the same
+//! with the report's counts as one line of JSON instead, for benchmarks and
+//! scripts.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::cli::Options;
+
+/// Whether anything is recorded, for `--timings` or `--machine`.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TABLE: AtomicBool = AtomicBool::new(false);
+static MACHINE: AtomicBool = AtomicBool::new(false);
+
+/// Time spent in each phase, in the order they first ended, with how many
+/// times they ran.
+static PHASES: Mutex<Vec<(&'static str, Duration, usize)>> = Mutex::new(Vec::new());
+
+static COUNTS: Mutex<BTreeMap<&'static str, usize>> = Mutex::new(BTreeMap::new());
+
+/// The report's counts, for `--machine`.
+static SUMMARY: Mutex<Option<serde_json::Value>> = Mutex::new(None);
+
+pub fn configure(options: &Options) {
+    ENABLED.store(options.timings || options.machine, Ordering::Relaxed);
+    TABLE.store(options.timings, Ordering::Relaxed);
+    MACHINE.store(options.machine, Ordering::Relaxed);
+}
+
+fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A phase that is timed until dropped. Phases that run once per file, on
+/// several threads, add up to more than the time the run took.
+pub struct Phase {
+    name: &'static str,
+    start: Instant,
+}
+
+pub fn phase(name: &'static str) -> Phase {
+    Phase {
+        name,
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        if !enabled() {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        let mut phases = PHASES.lock().unwrap();
+        match phases.iter_mut().find(|(name, _, _)| *name == self.name) {
+            Some((_, total, runs)) => {
+                *total += elapsed;
+                *runs += 1;
+            }
+            None => phases.push((self.name, elapsed, 1)),
+        }
+    }
+}
+
+/// Adds `n` to the counter `name`, such as cache hits.
+pub fn count(name: &'static str, n: usize) {
+    if enabled() {
+        *COUNTS.lock().unwrap().entry(name).or_default() += n;
+    }
+}
+
+/// Keeps the report's counts for the `--machine` line.
+pub fn summarize(summary: serde_json::Value) {
+    if MACHINE.load(Ordering::Relaxed) {
+        *SUMMARY.lock().unwrap() = Some(summary);
+    }
+}
+
+/// The `--machine` line: the report's counts, whether the run succeeded, the
+/// phases in milliseconds and the counters.
+fn machine_line(
+    summary: Option<&serde_json::Value>,
+    success: bool,
+    phases: &[(&str, Duration, usize)],
+    counts: &BTreeMap<&str, usize>,
+) -> String {
+    let phases: serde_json::Map<_, _> = phases
+        .iter()
+        .map(|(name, total, _)| {
+            let ms = (total.as_secs_f64() * 1e6).round() / 1e3;
+            ((*name).to_string(), ms.into())
+        })
+        .collect();
+    serde_json::json!({
+        "summary": summary,
+        "success": success,
+        "timings_ms": phases,
+        "counts": counts,
+    })
+    .to_string()
+}
+
+/// The phases and counters, as printed.
+fn render(phases: &[(&str, Duration, usize)], counts: &BTreeMap<&str, usize>) -> String {
+    let width = phases
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .chain(counts.keys().map(|name| name.len()))
+        .max()
+        .unwrap_or_default();
+    let mut out = String::from("Timings:\n");
+    for (name, total, runs) in phases {
+        let ms = total.as_secs_f64() * 1000.0;
+        let _ = write!(out, "  {name:<width$}  {ms:>9.1} ms");
+        let _ = if *runs > 1 {
+            writeln!(out, " over {runs}")
+        } else {
+            writeln!(out)
+        };
+    }
+    for (name, count) in counts {
+        let _ = writeln!(out, "  {name:<width$}  {count:>9}");
+    }
+    out
+}
+
+/// Prints what was recorded: the table to stderr with `--timings`, and the
+/// line to stdout with `--machine`.
+pub fn report(success: bool) {
+    let phases = PHASES.lock().unwrap().clone();
+    let counts = COUNTS.lock().unwrap().clone();
+    if TABLE.load(Ordering::Relaxed) {
+        eprint!("{}", render(&phases, &counts));
+    }
+    if MACHINE.load(Ordering::Relaxed) {
+        let summary = SUMMARY.lock().unwrap().take();
+        println!(
+            "{}",
+            machine_line(summary.as_ref(), success, &phases, &counts)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_phases_and_counts() {
+        let phases = [
+            ("scan", Duration::from_millis(1500), 1),
+            ("blame", Duration::from_micros(2300), 3),
+        ];
+        let counts = BTreeMap::from([("blob cache hits", 12)]);
+        assert_eq!(
+            render(&phases, &counts),
+            "Timings:\n\
+             \x20 scan                1500.0 ms\n\
+             \x20 blame                  2.3 ms over 3\n\
+             \x20 blob cache hits         12\n"
+        );
+        assert_eq!(
+            machine_line(None, true, &phases, &counts),
+            r#"{"counts":{"blob cache hits":12},"success":true,"summary":null,"timings_ms":{"blame":2.3,"scan":1500.0}}"#
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+
+use colored::Colorize;
+use git2::Repository;
+
+use crate::cli::Options;
+use crate::matcher::Matcher;
+use crate::{clean_statement, get_changes, partial, PathFilter};
+
+/// How many TODO lines the branch adds and removes relative to the base branch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Trend {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for Trend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} / {}",
+            format!("+{}", self.added).green(),
+            format!("−{}", self.removed).red(),
+        )
+    }
+}
+
+/// The TODO lines of every file that differs between the base branch and
+/// `HEAD`, or with `--staged` or `--dirty` between `HEAD` and the index or the
+/// working tree, on either side of the diff.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    base: Vec<String>,
+    branch: Vec<String>,
+}
+
+/// Identifies a TODO independently of its position, indentation and trailing
+/// comment syntax, so that re-indenting or moving a TODO does not count as
+/// removing and re-adding it.
+pub fn fingerprint(line: &str) -> String {
+    clean_statement(line)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn todo_fingerprints<'a>(
+    matcher: &'a Matcher,
+    content: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    content
+        .lines()
+        .filter(|line| matcher.is_match(line))
+        .map(fingerprint)
+}
+
+impl Comparison {
+    pub fn compute(repo: &Repository, options: &Options) -> Result<Self, git2::Error> {
+        let root_dir = repo.workdir().unwrap();
+        let diff = get_changes(repo, options)?;
+        // missing blobs fail the comparison below when they can't be fetched
+        let _ = partial::fetch_diff(repo, &diff);
+
+        let mut comparison = Self::default();
+        let matcher = Matcher::configured(options);
+        // the same files as the scan, so the trend counts what it reports
+        let filter = PathFilter::new(repo, options);
+
+        for delta in diff.deltas() {
+            let is_excluded =
+                |file: git2::DiffFile| file.path().is_some_and(|path| !filter.includes(path));
+            if is_excluded(delta.old_file()) || is_excluded(delta.new_file()) {
+                continue;
+            }
+            let old_file = delta.old_file();
+            if !old_file.id().is_zero() {
+                let blob = repo.find_blob(old_file.id())?;
+                if !blob.is_binary() {
+                    let content = String::from_utf8_lossy(blob.content());
+                    comparison
+                        .base
+                        .extend(todo_fingerprints(&matcher, &content));
+                }
+            }
+
+            let new_file = delta.new_file();
+            if delta.status() == git2::Delta::Deleted {
+                continue;
+            }
+            let content = if options.dirty {
+                // the working tree's side has no blob
+                let Some(path) = new_file.path() else {
+                    continue;
+                };
+                let Ok(content) = fs::read_to_string(root_dir.join(path)) else {
+                    continue;
+                };
+                content
+            } else {
+                let blob = repo.find_blob(new_file.id())?;
+                if blob.is_binary() {
+                    continue;
+                }
+                String::from_utf8_lossy(blob.content()).into_owned()
+            };
+            comparison
+                .branch
+                .extend(todo_fingerprints(&matcher, &content));
+        }
+
+        Ok(comparison)
+    }
+
+    pub fn trend(&self) -> Trend {
+        // positive counts are TODOs only on the branch, negative ones only on base
+        let mut balance: HashMap<&str, isize> = HashMap::new();
+        for fingerprint in &self.base {
+            *balance.entry(fingerprint).or_default() -= 1;
+        }
+        for fingerprint in &self.branch {
+            *balance.entry(fingerprint).or_default() += 1;
+        }
+
+        let mut trend = Trend::default();
+        for count in balance.into_values() {
+            if count > 0 {
+                trend.added += count.unsigned_abs();
+            } else {
+                trend.removed += count.unsigned_abs();
+            }
+        }
+        trend
+    }
+
+    /// Fingerprints present on the branch that the base branch has nowhere in
+    /// the changed files.
+    pub fn new_fingerprints(&self) -> Vec<&str> {
+        let base: HashSet<&str> = self.base.iter().map(String::as_str).collect();
+        let mut new: Vec<&str> = self
+            .branch
+            .iter()
+            .map(String::as_str)
+            .filter(|fingerprint| !base.contains(fingerprint))
+            .collect();
+        new.sort_unstable();
+        new.dedup();
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn counts_todos_the_branch_adds_and_removes() {
+        let test = TestRepo::new("trend");
+        test.write("a.rs", "// TODO: keep\n// TODO: drop\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        // re-indenting a TODO doesn't remove and re-add it
+        test.write("a.rs", "fn a() {\n    // TODO: keep\n}\n// TODO: new\n");
+        test.write("b.rs", "// TODO: another\n");
+        test.commit("Bob", "b");
+
+        let comparison = Comparison::compute(&test.repo, &Options::default()).unwrap();
+        assert_eq!(
+            comparison.trend(),
+            Trend {
+                added: 2,
+                removed: 1
+            }
+        );
+        assert_eq!(comparison.new_fingerprints().len(), 2);
+    }
+
+    #[test]
+    fn only_todos_missing_from_the_base_are_new() {
+        let test = TestRepo::new("new");
+        test.write("a.rs", "// TODO: legacy\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        // moved to another file, so not new
+        test.write("a.rs", "fn a() {}\n");
+        test.write("b.rs", "    // TODO: legacy\n");
+        test.commit("Bob", "b");
+        let new = || {
+            let comparison = Comparison::compute(&test.repo, &Options::default()).unwrap();
+            comparison.new_fingerprints().len()
+        };
+        assert_eq!(new(), 0);
+
+        test.write(
+            "b.rs",
+            "    // TODO: legacy\n// TODO: fresh\n// TODO: fresh\n",
+        );
+        test.commit("Bob", "c");
+        assert_eq!(new(), 1);
+    }
+
+    #[test]
+    fn counts_only_the_files_the_scan_looks_at() {
+        let test = TestRepo::new("trend-filter");
+        test.write("a.rs", "fn a() {}\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("src/a.rs", "// TODO: rust\n");
+        test.write("src/b.py", "# TODO: python\n");
+        test.write("vendor/c.rs", "// TODO: vendored\n");
+        test.commit("Bob", "b");
+
+        let added = |options: Options| {
+            let comparison = Comparison::compute(&test.repo, &options).unwrap();
+            comparison.trend().added
+        };
+        assert_eq!(added(Options::default()), 3);
+        assert_eq!(
+            added(Options {
+                types: vec!["rust".to_string()],
+                ..Options::default()
+            }),
+            2
+        );
+        assert_eq!(
+            added(Options {
+                paths: vec!["src".to_string()],
+                ..Options::default()
+            }),
+            2
+        );
+        assert_eq!(
+            added(Options {
+                exclude: vec!["vendor/**".to_string()],
+                types: vec!["py".to_string()],
+                ..Options::default()
+            }),
+            1
+        );
+    }
+}
@@ -0,0 +1,558 @@
+//! `todo tui`: browsing the TODOs in the terminal, with a filterable list
+//! grouped by commit, author, tag or file, the code around the selected TODO,
+//! and a key to open it in `$EDITOR`. The terminal is driven with `stty` and
+//! ANSI escapes, like the token prompt in `todo auth login`, so it needs a
+//! Unix terminal.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use git2::Repository;
+
+use crate::cli::Options;
+use crate::{repo_relative_path, scanned_source, Todo};
+
+const HELP: &str =
+    "↑/↓ move  / filter  tab group  c/a/t/f commit/author/tag/file  enter open  q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    Commit,
+    Author,
+    Tag,
+    File,
+}
+
+impl Grouping {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Commit => "commit",
+            Self::Author => "author",
+            Self::Tag => "tag",
+            Self::File => "file",
+        }
+    }
+
+    const fn next(self) -> Self {
+        match self {
+            Self::Commit => Self::Author,
+            Self::Author => Self::Tag,
+            Self::Tag => Self::File,
+            Self::File => Self::Commit,
+        }
+    }
+
+    /// The groups a TODO is listed under, each with what orders it before
+    /// its name: newest first for commits, alphabetical otherwise. A TODO
+    /// with several tags is listed under each.
+    fn groups(self, todo: &Todo, path: &str, untagged: &str) -> Vec<(i64, String)> {
+        match self {
+            Self::Commit if todo.commit_hash.is_empty() => {
+                vec![(i64::MIN, todo.short_hash.clone())]
+            }
+            Self::Commit => vec![(
+                -todo.author_date.timestamp(),
+                format!("{} {}", todo.short_hash, todo.commit_title),
+            )],
+            Self::Author if todo.author.is_empty() => vec![(0, todo.short_hash.clone())],
+            Self::Author => vec![(0, todo.author.clone())],
+            Self::Tag if todo.tags.is_empty() => vec![(1, untagged.to_string())],
+            Self::Tag => todo.tags.iter().map(|tag| (0, tag.clone())).collect(),
+            Self::File => vec![(0, path.to_string())],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Tab,
+    Enter,
+    Esc,
+    Backspace,
+    CtrlC,
+    Char(char),
+}
+
+/// The keys in one read from the terminal, which holds a whole escape
+/// sequence when an arrow key is pressed.
+fn keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let text = String::from_utf8_lossy(bytes);
+    let mut rest = text.as_ref();
+    while let Some(c) = rest.chars().next() {
+        let (key, len) = match c {
+            '\x1b' => match rest.get(1..4).or_else(|| rest.get(1..3)) {
+                Some(s) if s.starts_with("[A") || s.starts_with("OA") => (Key::Up, 3),
+                Some(s) if s.starts_with("[B") || s.starts_with("OB") => (Key::Down, 3),
+                Some("[5~") => (Key::PageUp, 4),
+                Some("[6~") => (Key::PageDown, 4),
+                _ => (Key::Esc, 1),
+            },
+            '\x03' => (Key::CtrlC, 1),
+            '\t' => (Key::Tab, 1),
+            '\r' | '\n' => (Key::Enter, 1),
+            '\x7f' | '\x08' => (Key::Backspace, 1),
+            c if c.is_control() => {
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            c => (Key::Char(c), c.len_utf8()),
+        };
+        keys.push(key);
+        rest = &rest[len..];
+    }
+    keys
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Row {
+    Header(String),
+    Todo(usize),
+}
+
+enum Action {
+    None,
+    Open(usize),
+    Quit,
+}
+
+struct App<'a> {
+    todos: &'a [Todo],
+    /// Each TODO's path relative to the repository root.
+    paths: Vec<String>,
+    /// Each file's contents as scanned, which the line numbers refer to.
+    sources: HashMap<PathBuf, Option<String>>,
+    untagged: &'a str,
+    grouping: Grouping,
+    filter: String,
+    editing_filter: bool,
+    /// Which of the listed TODOs is selected.
+    selected: usize,
+    scroll: usize,
+    list_height: usize,
+    status: String,
+}
+
+impl<'a> App<'a> {
+    fn new(repo: &Repository, options: &'a Options, todos: &'a [Todo]) -> Self {
+        let mut sources = HashMap::new();
+        for todo in todos {
+            sources
+                .entry(todo.file_path.clone())
+                .or_insert_with(|| scanned_source(repo, options, &todo.file_path));
+        }
+        Self {
+            todos,
+            paths: todos
+                .iter()
+                .map(|todo| repo_relative_path(repo, &todo.file_path))
+                .collect(),
+            sources,
+            untagged: options.untagged_label(),
+            grouping: Grouping::Commit,
+            filter: String::new(),
+            editing_filter: false,
+            selected: 0,
+            scroll: 0,
+            list_height: 1,
+            status: String::new(),
+        }
+    }
+
+    /// Whether the TODO at `index` contains the filter, in any case, in its
+    /// path, text, author or tags.
+    fn matches(&self, index: usize) -> bool {
+        if self.filter.is_empty() {
+            return true;
+        }
+        let filter = self.filter.to_lowercase();
+        let todo = &self.todos[index];
+        [&self.paths[index], &todo.raw, &todo.author]
+            .into_iter()
+            .chain(&todo.tags)
+            .any(|field| field.to_lowercase().contains(&filter))
+    }
+
+    /// The list: a header for each group, followed by its TODOs.
+    fn rows(&self) -> Vec<Row> {
+        let mut groups: BTreeMap<(i64, String), Vec<usize>> = BTreeMap::new();
+        for index in (0..self.todos.len()).filter(|&index| self.matches(index)) {
+            for group in self
+                .grouping
+                .groups(&self.todos[index], &self.paths[index], self.untagged)
+            {
+                groups.entry(group).or_default().push(index);
+            }
+        }
+        let mut rows = Vec::new();
+        for ((_, name), mut indices) in groups {
+            indices.sort_by(|&a, &b| {
+                (&self.paths[a], self.todos[a].line).cmp(&(&self.paths[b], self.todos[b].line))
+            });
+            rows.push(Row::Header(format!("{name} ({})", indices.len())));
+            rows.extend(indices.into_iter().map(Row::Todo));
+        }
+        rows
+    }
+
+    /// Applies a key typed while editing the filter, returning whether it
+    /// was one the filter takes.
+    fn edit_filter(&mut self, key: Key) -> bool {
+        match key {
+            Key::Enter => self.editing_filter = false,
+            Key::Esc => {
+                self.filter.clear();
+                self.editing_filter = false;
+            }
+            Key::Backspace => {
+                self.filter.pop();
+                self.selected = 0;
+            }
+            Key::Char(c) => {
+                self.filter.push(c);
+                self.selected = 0;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle(&mut self, key: Key, listed: usize) -> Action {
+        if self.editing_filter && self.edit_filter(key) {
+            return Action::None;
+        }
+        let last = listed.saturating_sub(1);
+        match key {
+            Key::CtrlC | Key::Char('q') => return Action::Quit,
+            Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.selected = (self.selected + 1).min(last),
+            Key::PageUp => self.selected = self.selected.saturating_sub(self.list_height),
+            Key::PageDown => self.selected = (self.selected + self.list_height).min(last),
+            Key::Esc => self.filter.clear(),
+            Key::Tab => self.grouping = self.grouping.next(),
+            Key::Enter | Key::Char('e') => {
+                let rows = self.rows();
+                if let Some(index) = rows
+                    .iter()
+                    .filter_map(|row| match row {
+                        Row::Todo(index) => Some(*index),
+                        Row::Header(_) => None,
+                    })
+                    .nth(self.selected)
+                {
+                    return Action::Open(index);
+                }
+            }
+            Key::Char('/') => self.editing_filter = true,
+            Key::Char('c') => self.grouping = Grouping::Commit,
+            Key::Char('a') => self.grouping = Grouping::Author,
+            Key::Char('t') => self.grouping = Grouping::Tag,
+            Key::Char('f') => self.grouping = Grouping::File,
+            Key::Backspace | Key::Char(_) => {}
+        }
+        Action::None
+    }
+
+    /// Draws the header, the list, the code around the selected TODO and
+    /// the footer, filling a terminal of `height` lines and `width` columns.
+    fn draw(&mut self, rows: &[Row], height: usize, width: usize) -> String {
+        let body = height.saturating_sub(3);
+        self.list_height = (body / 2).max(1);
+        let preview_height = body.saturating_sub(self.list_height);
+
+        let positions: Vec<usize> = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| matches!(row, Row::Todo(_)))
+            .map(|(position, _)| position)
+            .collect();
+        self.selected = self.selected.min(positions.len().saturating_sub(1));
+        let cursor = positions.get(self.selected).copied();
+
+        // keep the selection in view, with its group's header when it fits
+        if let Some(cursor) = cursor {
+            let top = if cursor > 0 && matches!(rows[cursor - 1], Row::Header(_)) {
+                cursor - 1
+            } else {
+                cursor
+            };
+            self.scroll = self
+                .scroll
+                .min(top)
+                .max((cursor + 1).saturating_sub(self.list_height));
+        } else {
+            self.scroll = 0;
+        }
+
+        let mut out = String::from("\x1b[H\x1b[2J");
+        let filter = if self.filter.is_empty() {
+            String::new()
+        } else {
+            format!(" · filter: {}", self.filter)
+        };
+        let title = format!(
+            " {} of {} TODOs · grouped by {}{filter}",
+            positions.len(),
+            self.todos.len(),
+            self.grouping.name()
+        );
+        let _ = write!(out, "\x1b[7m{:width$}\x1b[0m\r\n", fit(&title, width));
+
+        for (position, row) in rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.list_height)
+        {
+            let line = match row {
+                Row::Header(name) => format!("\x1b[1m{}\x1b[0m", fit(name, width)),
+                Row::Todo(index) => {
+                    let todo = &self.todos[*index];
+                    let text = format!(
+                        "  {}:{}  {}",
+                        self.paths[*index],
+                        todo.line,
+                        todo.raw.trim()
+                    );
+                    if Some(position) == cursor {
+                        format!("\x1b[7m{:width$}\x1b[0m", fit(&text, width))
+                    } else {
+                        fit(&text, width)
+                    }
+                }
+            };
+            let _ = write!(out, "{line}\r\n");
+        }
+        let drawn = rows.len().saturating_sub(self.scroll).min(self.list_height);
+        out.push_str(&"\r\n".repeat(self.list_height - drawn));
+
+        let _ = write!(out, "\x1b[2m{}\x1b[0m\r\n", "─".repeat(width));
+        let index = cursor.and_then(|cursor| match rows[cursor] {
+            Row::Todo(index) => Some(index),
+            Row::Header(_) => None,
+        });
+        let preview = index.map_or_else(Vec::new, |index| {
+            let todo = &self.todos[index];
+            let source = self.sources.get(&todo.file_path).and_then(Option::as_deref);
+            preview(todo, source, preview_height, width)
+        });
+        for line in &preview {
+            let _ = write!(out, "{line}\r\n");
+        }
+        out.push_str(&"\r\n".repeat(preview_height.saturating_sub(preview.len())));
+
+        let footer = if self.editing_filter {
+            format!("/{}", self.filter)
+        } else if self.status.is_empty() {
+            HELP.to_string()
+        } else {
+            self.status.clone()
+        };
+        out.push_str(&fit(&footer, width));
+        out
+    }
+}
+
+/// `text` on one line of `width` columns, cut short if it is longer.
+fn fit(text: &str, width: usize) -> String {
+    text.replace('\t', "    ")
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\x1b')
+        .take(width)
+        .collect()
+}
+
+/// Up to `height` numbered lines of `source`, the TODO's file, around it,
+/// with the TODO's own line highlighted.
+fn preview(todo: &Todo, source: Option<&str>, height: usize, width: usize) -> Vec<String> {
+    let Some(content) = source else {
+        return vec![format!(
+            "\x1b[2m{}\x1b[0m",
+            fit("(file not readable)", width)
+        )];
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let index = todo.line.saturating_sub(1);
+    let start = index
+        .saturating_sub(height / 2)
+        .min(lines.len().saturating_sub(height));
+    lines
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(height)
+        .map(|(i, line)| {
+            let text = fit(&format!("{:>5} │ {line}", i + 1), width);
+            if i == index {
+                format!("\x1b[1;33m{text}\x1b[0m")
+            } else {
+                text
+            }
+        })
+        .collect()
+}
+
+/// Runs `stty` on the terminal, returning what it printed.
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("running stty: {e}"))?;
+    if !output.status.success() {
+        return Err("stty could not set up the terminal".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The terminal's lines and columns.
+fn size() -> (usize, usize) {
+    stty(&["size"])
+        .ok()
+        .and_then(|size| {
+            let (rows, columns) = size.split_once(' ')?;
+            Some((rows.parse().ok()?, columns.parse().ok()?))
+        })
+        .unwrap_or((24, 80))
+}
+
+/// The terminal in raw mode on the alternate screen, restored on drop.
+struct Terminal {
+    /// The settings to restore, as `stty -g` prints them.
+    saved: String,
+}
+
+impl Terminal {
+    fn enter() -> Result<Self, String> {
+        let terminal = Self {
+            saved: stty(&["-g"])?,
+        };
+        Self::resume()?;
+        Ok(terminal)
+    }
+
+    fn resume() -> Result<(), String> {
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush().map_err(|e| e.to_string())
+    }
+
+    fn suspend(&self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.suspend();
+    }
+}
+
+/// Opens `path` at `line` in `$VISUAL` or `$EDITOR`, by default `vi`, passing
+/// the line the way the editor expects it.
+fn open_in_editor(path: &Path, line: usize) -> Result<(), String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let name = Path::new(program)
+        .file_stem()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+    let mut command = Command::new(program);
+    command.args(words);
+    let at = format!("{}:{line}", path.display());
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" => command.args(["--goto", &at]),
+        "subl" | "zed" | "hx" => command.arg(&at),
+        _ => command.arg(format!("+{line}")).arg(path),
+    };
+    let status = command
+        .status()
+        .map_err(|e| format!("running {program}: {e}"))?;
+    if !status.success() {
+        return Err(format!("{program} exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Browses `todos` until the user quits.
+pub fn run(repo: &Repository, options: &Options, todos: &[Todo]) -> Result<(), String> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err("`todo tui` needs a terminal".to_string());
+    }
+    let mut app = App::new(repo, options, todos);
+    let terminal = Terminal::enter()?;
+    let mut input = [0; 64];
+    loop {
+        let rows = app.rows();
+        let (height, width) = size();
+        let frame = app.draw(&rows, height, width);
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(frame.as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|e| e.to_string())?;
+        drop(stdout);
+
+        let read = io::stdin().read(&mut input).map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(());
+        }
+        app.status.clear();
+        let listed = rows
+            .iter()
+            .filter(|row| matches!(row, Row::Todo(_)))
+            .count();
+        for key in keys(&input[..read]) {
+            match app.handle(key, listed) {
+                Action::None => {}
+                Action::Quit => return Ok(()),
+                Action::Open(index) => {
+                    let todo = &todos[index];
+                    terminal.suspend();
+                    if let Err(e) = open_in_editor(&todo.file_path, todo.line) {
+                        app.status = e;
+                    }
+                    Terminal::resume()?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_keys_and_escape_sequences() {
+        assert_eq!(
+            keys(b"j\x1b[A\x1b[B\x1b[6~\x1b\r\x7f\t\x03/\xc3\xa9"),
+            [
+                Key::Char('j'),
+                Key::Up,
+                Key::Down,
+                Key::PageDown,
+                Key::Esc,
+                Key::Enter,
+                Key::Backspace,
+                Key::Tab,
+                Key::CtrlC,
+                Key::Char('/'),
+                Key::Char('é'),
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Uploads reports to an HTTP artifact store, such as an S3 or GCS presigned
+//! URL or any endpoint that accepts `PUT`, so CI jobs can archive them.
+
+use std::io;
+
+use git2::Repository;
+
+use crate::cli::Format;
+use crate::credentials::Provider;
+use crate::http::{redact, Request};
+use crate::matcher::Matcher;
+use crate::metadata::Metadata;
+use crate::queue;
+use crate::{export, gerrit, html, sarif, sonar, Todo};
+
+/// The report in the requested format, or JSON when that format is meant to
+/// be read where it is written, like the tree, Markdown and annotations.
+pub fn report(
+    repo: &Repository,
+    matcher: &Matcher,
+    todos: &[Todo],
+    format: Format,
+    metadata: &Metadata,
+) -> (Vec<u8>, &'static str) {
+    let json = match format {
+        Format::Csv => return (export::csv(repo, todos, metadata).into_bytes(), "text/csv"),
+        Format::Html => {
+            return (
+                html::report(repo, todos, metadata).into_bytes(),
+                "text/html",
+            )
+        }
+        Format::Gerrit => gerrit::review_input(repo, todos, metadata),
+        Format::Sonar => metadata.attach(sonar::generic_issues(repo, todos)),
+        Format::Sarif => sarif::log(repo, matcher, todos, metadata),
+        Format::Tree | Format::Json | Format::Markdown | Format::Github => {
+            metadata.attach(export::json(repo, todos))
+        }
+    };
+    (format!("{json:#}\n").into_bytes(), "application/json")
+}
+
+/// Headers from `$TODO_UPLOAD_HEADERS` (`key=value,key=value`).
+fn env_headers() -> Vec<(String, String)> {
+    std::env::var("TODO_UPLOAD_HEADERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Uploads with the headers from `$TODO_UPLOAD_HEADERS`, plus a bearer token
+/// from `$TODO_UPLOAD_TOKEN` or `todo auth login upload`. Presigned URLs
+/// carry their credentials in the query string and need neither.
+pub fn put(repo: &Repository, url: &str, body: Vec<u8>, content_type: &str) -> io::Result<()> {
+    let mut request = Request::new("PUT", url)
+        .header("Content-Type", content_type)
+        .authorization("Bearer", Provider::Upload)
+        .body(body);
+    for (name, value) in env_headers() {
+        request = request.header(&name, value);
+    }
+    queue::send(repo, request)
+        .map_err(|e| io::Error::other(e.to_string().replace(url, &redact(url))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::cli::Options;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn puts_the_report_with_its_headers() {
+        let test = TestRepo::new("upload");
+        test.write("a.rs", "// TODO: one\n");
+        test.commit("Alice", "a");
+        let options = Options {
+            all_files: true,
+            no_cache: true,
+            ..Options::default()
+        };
+        let (todos, _) = crate::get_todos(&test.repo, &options);
+        let metadata = Metadata::new(&test.repo, &options);
+        let matcher = Matcher::default();
+        // formats read where they are written upload as JSON
+        let (body, content_type) =
+            report(&test.repo, &matcher, &todos, Format::Markdown, &metadata);
+        assert_eq!(content_type, "application/json");
+        let (_, content_type) = report(&test.repo, &matcher, &todos, Format::Csv, &metadata);
+        assert_eq!(content_type, "text/csv");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/reports/a.json", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let (mut line, mut head) = (String::new(), Vec::new());
+            while reader.read_line(&mut line).unwrap() > 2 {
+                head.push(line.trim_end().to_string());
+                line.clear();
+            }
+            let length: usize = head
+                .iter()
+                .find_map(|line| line.strip_prefix("Content-Length: "))
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(stream, "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n").unwrap();
+            (head, body)
+        });
+
+        std::env::set_var("TODO_UPLOAD_TOKEN", "upload-secret");
+        std::env::set_var("TODO_UPLOAD_HEADERS", "x-amz-acl=private, X-Run = 7");
+        put(&test.repo, &url, body.clone(), "application/json").unwrap();
+        let (head, received) = server.join().unwrap();
+        assert_eq!(head[0], "PUT /reports/a.json HTTP/1.1");
+        for header in [
+            "Content-Type: application/json",
+            "Authorization: Bearer upload-secret",
+            "x-amz-acl: private",
+            "X-Run: 7",
+        ] {
+            assert!(head.contains(&header.to_string()), "{header} in {head:?}");
+        }
+        assert_eq!(received, body);
+    }
+}
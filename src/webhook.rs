@@ -0,0 +1,207 @@
+//! Push webhooks for `todo serve`, so the dashboard follows pushes without a
+//! cron job. GitHub and GitLab POST to `/webhook`, proving they know the
+//! secret in `TODO_WEBHOOK_SECRET`, and the server fetches, fast-forwards the
+//! checked-out branch if it was the one pushed, and rescans.
+
+use std::fmt::Write;
+use std::process::Command;
+
+use git2::Repository;
+use sha2::{Digest, Sha256};
+
+use crate::dry_run;
+use crate::server::{Request, Response};
+
+/// The environment variable holding the secret webhooks are configured with.
+pub const SECRET_VAR: &str = "TODO_WEBHOOK_SECRET";
+
+/// HMAC-SHA256 of `message` under `key`, as GitHub signs payloads.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block = [0; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|key| key ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Compares in time independent of where `a` and `b` differ, so a forged
+/// signature can't be found a byte at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Whether the request carries the secret: as GitHub's `X-Hub-Signature-256`
+/// of the body, or as GitLab's `X-Gitlab-Token`.
+fn verify(request: &Request, secret: &str) -> bool {
+    if let Some(signature) = request.header("X-Hub-Signature-256") {
+        let expected = hex(&hmac_sha256(secret.as_bytes(), &request.body));
+        return constant_time_eq(
+            signature.as_bytes(),
+            format!("sha256={expected}").as_bytes(),
+        );
+    }
+    request
+        .header("X-Gitlab-Token")
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), secret.as_bytes()))
+}
+
+/// What a verified webhook asks for.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A push to the ref, such as `refs/heads/main`.
+    Push(String),
+    /// Anything else, such as the ping GitHub sends when a hook is created.
+    Other(String),
+}
+
+/// Checks the request is a webhook signed with the secret, answering it
+/// directly if it isn't.
+pub fn event(request: &Request, secret: Option<&str>) -> Result<Event, Response> {
+    let Some(secret) = secret.filter(|secret| !secret.is_empty()) else {
+        return Err(Response::error(
+            404,
+            format!("webhooks are disabled; set {SECRET_VAR} to enable them"),
+        ));
+    };
+    if !verify(request, secret) {
+        return Err(Response::error(401, "invalid webhook signature or token"));
+    }
+    let name = request
+        .header("X-GitHub-Event")
+        .or_else(|| request.header("X-Gitlab-Event"))
+        .unwrap_or_default();
+    if name != "push" && name != "Push Hook" {
+        return Ok(Event::Other(name.to_string()));
+    }
+    request.json()["ref"]
+        .as_str()
+        .map(|pushed| Event::Push(pushed.to_string()))
+        .ok_or_else(|| Response::error(400, "the push has no ref"))
+}
+
+/// Runs git in the repository's working tree, or prints the command with
+/// `--dry-run`.
+fn git(repo: &Repository, args: &[&str]) -> Result<(), String> {
+    if dry_run::enabled() {
+        eprintln!("[dry run] git {}", args.join(" "));
+        return Ok(());
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo.workdir().unwrap_or_else(|| repo.path()))
+        .args(args)
+        .output()
+        .map_err(|e| format!("running git: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "git {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Fetches the remote of the checked-out branch, or `origin`, and
+/// fast-forwards the branch when `pushed` is its upstream. A branch with
+/// diverging commits or conflicting changes is left as it is.
+pub fn update(repo: &Repository, pushed: &str) -> Result<(), String> {
+    let head = repo.head().ok().filter(git2::Reference::is_branch);
+    let head_name = head.as_ref().and_then(|head| head.name());
+    let remote = head_name
+        .and_then(|name| repo.branch_upstream_remote(name).ok())
+        .and_then(|remote| remote.as_str().map(ToString::to_string))
+        .unwrap_or_else(|| "origin".to_string());
+    git(repo, &["fetch", "--quiet", "--no-tags", &remote])?;
+
+    let tracks_pushed = head
+        .as_ref()
+        .and_then(|head| head.shorthand())
+        .and_then(|branch| {
+            let config = repo.config().ok()?;
+            config.get_string(&format!("branch.{branch}.merge")).ok()
+        })
+        .is_some_and(|merge| merge == pushed);
+    if tracks_pushed {
+        git(repo, &["merge", "--ff-only", "--quiet", "@{upstream}"])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)], body: &str) -> Request {
+        Request {
+            method: "POST".to_string(),
+            path: "/webhook".to_string(),
+            query: Vec::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn verifies_github_and_gitlab_secrets() {
+        // RFC 4231, test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            hex(&mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let body = r#"{"ref":"refs/heads/main"}"#;
+        let signature = hex(&hmac_sha256(b"s3cret", body.as_bytes()));
+        let github = request(
+            &[
+                ("X-GitHub-Event", "push"),
+                ("X-Hub-Signature-256", &format!("sha256={signature}")),
+            ],
+            body,
+        );
+        assert_eq!(
+            event(&github, Some("s3cret")),
+            Ok(Event::Push("refs/heads/main".to_string()))
+        );
+        assert_eq!(event(&github, Some("other")).unwrap_err().status, 401);
+        assert_eq!(event(&github, None).unwrap_err().status, 404);
+
+        let gitlab = request(
+            &[
+                ("X-Gitlab-Event", "Push Hook"),
+                ("X-Gitlab-Token", "s3cret"),
+            ],
+            body,
+        );
+        assert!(matches!(event(&gitlab, Some("s3cret")), Ok(Event::Push(_))));
+        let ping = request(&[("X-Gitlab-Token", "s3cret")], "{}");
+        assert_eq!(
+            event(&ping, Some("s3cret")),
+            Ok(Event::Other(String::new()))
+        );
+    }
+}
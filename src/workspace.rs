@@ -0,0 +1,383 @@
+//! `todo workspace`: scans every repository under a directory in one run.
+//!
+//! A `.todo-workspace` file in the directory, or the file given with
+//! `--repos`, selects repositories and overrides options per repository:
+//!
+//! ```text
+//! # paths relative to the workspace; `*` matches anything
+//! include = services/*
+//! exclude = services/legacy-*
+//!
+//! [services/billing]
+//! base = origin/release
+//! keyword = FIXME=high
+//! match = strict
+//! ```
+//!
+//! JSON and HTML reports cover the whole workspace in one document, with the
+//! repositories at the top level; other formats report them one by one.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use git2::Repository;
+use regex::Regex;
+use walkdir::WalkDir;
+
+use crate::automation::wildcard_regex;
+use crate::cli::{Command, Format, Options};
+use crate::matcher::Preset;
+use crate::metadata::Metadata;
+use crate::{
+    compared_to, config, export, html, redact, run_command, scan, sorted_and_grouped, trend,
+};
+
+/// Read from the workspace directory when `--repos` names no other file.
+const DEFAULT_FILE: &str = ".todo-workspace";
+
+/// Directories never searched for repositories.
+const SKIPPED_DIRS: [&str; 2] = ["node_modules", "target"];
+
+#[derive(Debug, Clone)]
+enum Override {
+    Base(String),
+    Keyword(String),
+    Match(Preset),
+}
+
+#[derive(Default)]
+pub struct Workspace {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    /// Overrides for the repositories matching each section's pattern, in
+    /// file order, so later sections win.
+    sections: Vec<(Regex, Vec<Override>)>,
+}
+
+fn pattern(pattern: &str) -> Regex {
+    // wildcards are escaped, so the pattern is always valid
+    Regex::new(&wildcard_regex(pattern.trim_end_matches('/'))).unwrap()
+}
+
+impl Workspace {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut workspace = Self::default();
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {message}", path.display(), index + 1),
+                )
+            };
+
+            if let Some(section) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                workspace
+                    .sections
+                    .push((pattern(section.trim()), Vec::new()));
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid("expected `key = value`"));
+            };
+            let value = value.trim().to_string();
+            match (key.trim(), workspace.sections.last_mut()) {
+                ("include", None) => workspace.include.push(pattern(&value)),
+                ("exclude", None) => workspace.exclude.push(pattern(&value)),
+                ("base", Some((_, overrides))) => overrides.push(Override::Base(value)),
+                ("keyword", Some((_, overrides))) => {
+                    Options::default()
+                        .add_keyword(&value)
+                        .map_err(|e| invalid(&e))?;
+                    overrides.push(Override::Keyword(value));
+                }
+                ("match", Some((_, overrides))) => {
+                    overrides.push(Override::Match(
+                        value.parse().map_err(|e: String| invalid(&e))?,
+                    ));
+                }
+                (key, None) => {
+                    return Err(invalid(&format!(
+                        "unknown setting '{key}', expected include or exclude"
+                    )))
+                }
+                (key, Some(_)) => {
+                    return Err(invalid(&format!(
+                        "unknown setting '{key}', expected base, keyword or match"
+                    )))
+                }
+            }
+        }
+        Ok(workspace)
+    }
+
+    fn is_selected(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(path)))
+            && !self.exclude.iter().any(|re| re.is_match(path))
+    }
+
+    /// The options for the repository at `path`, with its overrides applied.
+    fn options(&self, path: &str, options: &Options) -> Options {
+        let mut options = Options {
+            command: match options.command {
+                Command::Check => Command::Check,
+                _ => Command::List,
+            },
+            ..options.clone()
+        };
+        let overrides = self
+            .sections
+            .iter()
+            .filter(|(re, _)| re.is_match(path))
+            .flat_map(|(_, overrides)| overrides);
+        for item in overrides {
+            match item {
+                Override::Base(base) => options.base = Some(base.clone()),
+                Override::Keyword(keyword) => {
+                    // validated when the file was loaded
+                    let _ = options.add_keyword(keyword);
+                }
+                Override::Match(preset) => options.match_preset = *preset,
+            }
+        }
+        options
+    }
+}
+
+/// Git repositories under `dir`, without descending into them, sorted by path.
+fn discover(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut repos = Vec::new();
+    let mut entries = WalkDir::new(dir).into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.depth() == 0
+            || entry.file_type().is_dir()
+                && !name.starts_with('.')
+                && !SKIPPED_DIRS.contains(&name.as_ref())
+    });
+
+    while let Some(entry) = entries.next() {
+        let entry = entry.map_err(io::Error::other)?;
+        if entry.path().join(".git").exists() {
+            repos.push(entry.into_path());
+            entries.skip_current_dir();
+        }
+    }
+
+    repos.sort();
+    Ok(repos)
+}
+
+/// Path of a repository relative to the workspace, with `/` separators, as
+/// patterns are written.
+fn relative(dir: &Path, repo: &Path) -> String {
+    let path = repo.strip_prefix(dir).unwrap_or(repo);
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.is_empty() {
+        ".".to_string()
+    } else {
+        path
+    }
+}
+
+/// One repository's part of the aggregate report: its TODO count and trend
+/// against its base, over the same grouping as `--format json --grouped`.
+fn summary(repo: &Repository, name: &str, options: &Options) -> serde_json::Value {
+    let mut todos = scan(repo, options);
+    let trend = trend::Comparison::compute(repo, options)
+        .ok()
+        .map(|comparison| comparison.trend());
+
+    let grouped = sorted_and_grouped(&mut todos, options);
+    let mut summary = export::grouped_json(repo, &todos, &grouped);
+    summary["repository"] = name.into();
+    summary["metadata"] = Metadata::new(repo, options).json();
+    summary["base"] = compared_to(repo, options).into();
+    summary["trend"] = trend.map_or(
+        serde_json::Value::Null,
+        |trend| serde_json::json!({ "added": trend.added, "removed": trend.removed }),
+    );
+    summary
+}
+
+/// Reports each selected repository under `dir`, in one document for JSON
+/// and HTML and in turn otherwise. Returns `false` if any of them fails.
+pub fn run(options: &Options, dir: &Path, file: Option<&Path>) -> bool {
+    let workspace = file.map_or_else(
+        || match Workspace::load(&dir.join(DEFAULT_FILE)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Workspace::default()),
+            loaded => loaded,
+        },
+        Workspace::load,
+    );
+    let workspace = match workspace {
+        Ok(workspace) => workspace,
+        Err(e) => {
+            eprintln!("Error reading workspace: {e}");
+            return false;
+        }
+    };
+
+    let repos = match discover(dir) {
+        Ok(repos) => repos,
+        Err(e) => {
+            eprintln!("Error finding repositories in {}: {e}", dir.display());
+            return false;
+        }
+    };
+
+    let aggregate = matches!(options.format, Format::Json | Format::Html);
+    let mut summaries = Vec::new();
+    let mut success = true;
+    let mut scanned = 0;
+    for path in repos {
+        let name = relative(dir, &path);
+        if !workspace.is_selected(&name) {
+            continue;
+        }
+        scanned += 1;
+        if !aggregate {
+            println!("{}\n", name.bold().underline());
+        }
+
+        let options = match config::options(&path, &workspace.options(&name, options)) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("Error in {name}: {e}");
+                success = false;
+                continue;
+            }
+        };
+        redact::configure(&options);
+
+        match Repository::open(&path) {
+            Ok(repo) if aggregate => summaries.push(summary(&repo, &name, &options)),
+            Ok(repo) => success &= run_command(&repo, &options),
+            Err(e) => {
+                eprintln!("Error opening {name}: {e}");
+                success = false;
+            }
+        }
+        if !aggregate {
+            println!();
+        }
+    }
+
+    if scanned == 0 {
+        eprintln!("No repositories selected in {}", dir.display());
+    }
+
+    if aggregate {
+        let count: u64 = summaries
+            .iter()
+            .filter_map(|summary| summary["count"].as_u64())
+            .sum();
+        let report = serde_json::json!({ "count": count, "repositories": summaries });
+        match options.format {
+            Format::Html => print!("{}", html::workspace_report(&report)),
+            _ => println!("{report:#}"),
+        }
+    }
+    success
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use crate::test_repo::TestRepo;
+
+    #[test]
+    fn selects_repositories_and_overrides_their_options() {
+        let workspace = TestRepo::new("workspace");
+        for path in [
+            "services/billing",
+            "services/legacy-auth",
+            "tools",
+            "node_modules/dep",
+        ] {
+            fs::create_dir_all(workspace.dir.join(path).join(".git")).unwrap();
+        }
+        workspace.write(
+            DEFAULT_FILE,
+            "# services only\ninclude = services/*\nexclude = services/legacy-*\n\n\
+             [services/*]\nkeyword = FIXME=high\n[services/billing]\nbase = origin/release\nmatch = strict\n",
+        );
+
+        let found: Vec<_> = discover(&workspace.dir)
+            .unwrap()
+            .iter()
+            .map(|repo| relative(&workspace.dir, repo))
+            .collect();
+        // the workspace itself is a repository, and nothing below it is searched
+        assert_eq!(found, ["."]);
+        fs::remove_dir_all(workspace.dir.join(".git")).unwrap();
+        let found: Vec<_> = discover(&workspace.dir)
+            .unwrap()
+            .iter()
+            .map(|repo| relative(&workspace.dir, repo))
+            .collect();
+        assert_eq!(found, ["services/billing", "services/legacy-auth", "tools"]);
+
+        let loaded = Workspace::load(&workspace.dir.join(DEFAULT_FILE)).unwrap();
+        let selected: Vec<_> = found
+            .iter()
+            .filter(|path| loaded.is_selected(path))
+            .collect();
+        assert_eq!(selected, ["services/billing"]);
+
+        let options = loaded.options("services/billing", &Options::default());
+        assert_eq!(options.base.as_deref(), Some("origin/release"));
+        assert_eq!(options.match_preset, Preset::Strict);
+        assert!(Matcher::configured(&options).is_match("// FIXME: later"));
+        let options = loaded.options("services/other", &Options::default());
+        assert_eq!(options.base, None);
+
+        workspace.write(DEFAULT_FILE, "[tools]\ninclude = tools\n");
+        let error = Workspace::load(&workspace.dir.join(DEFAULT_FILE))
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .ends_with(":2: unknown setting 'include', expected base, keyword or match"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn summarizes_each_repository_for_the_aggregate_report() {
+        let test = TestRepo::new("aggregate");
+        test.write("a.rs", "// TODO: old\n");
+        test.commit("Alice", "a");
+        test.branch("feature");
+        test.write("a.rs", "// TODO(perf): new\n");
+        test.commit("Bob", "b");
+
+        let options = Options {
+            no_cache: true,
+            ..Options::default()
+        };
+        let summary = summary(&test.repo, "services/api", &options);
+        assert_eq!(summary["repository"], "services/api");
+        assert_eq!(summary["count"], 1);
+        assert_eq!(summary["base"], "main");
+        assert_eq!(
+            summary["trend"],
+            serde_json::json!({ "added": 1, "removed": 1 })
+        );
+
+        let report = serde_json::json!({ "count": 1, "repositories": [summary] });
+        let page = html::workspace_report(&report);
+        assert!(page.contains("<h1>1 TODOs across 1 repositories</h1>"));
+        assert!(page.contains("<a href=\"#repo-0\">services/api</a>"));
+        assert!(page.contains("<details id=\"repo-0\">"));
+        assert!(page.contains("perf"));
+        assert!(page.contains("<code>a.rs:1</code> // TODO(perf): new"));
+    }
+}